@@ -0,0 +1,441 @@
+use super::itemset::ExcludeFilter;
+use super::progress::{self, ProgressPhase};
+use super::store::{self, ProfileManifest};
+use super::{operation, vault};
+use crate::error::{AppError, ErrorCode};
+use crate::models::user::AppConfig;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// 归档文件扩展名
+pub const ARCHIVE_EXTENSION: &str = "roxybak";
+
+/// 归档格式标识
+const ARCHIVE_FORMAT: &str = "roxybak";
+
+/// 当前归档格式版本，导入时拒绝更高版本
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// 归档内清单文件名
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// 清单的大小上限，防止压缩炸弹在解析前耗尽内存
+const MAX_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 归档中的单个文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveFile {
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// 归档清单，记录来源环境与每个文件的哈希
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format: String,
+    #[serde(rename = "formatVersion")]
+    pub format_version: u32,
+    #[serde(rename = "appVersion")]
+    pub app_version: String,
+    pub platform: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub users: Vec<String>,
+    pub files: Vec<ArchiveFile>,
+}
+
+/// 归档内路径只允许普通的相对路径，防止解压到目标目录之外
+fn is_safe_relative(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn write_failed(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorCode::Io, "archive.write").caused_by(e)
+}
+
+fn invalid(key: &'static str) -> AppError {
+    AppError::new(ErrorCode::InvalidArchive, key)
+}
+
+fn zip_options(size: u64) -> SimpleFileOptions {
+    SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(size >= u32::MAX as u64)
+}
+
+/// 将配置和用户备份写成单个归档文件，返回因排除规则跳过的文件数
+///
+/// 先写入 `.partial` 临时文件，全部完成后再重命名为目标文件。
+pub fn write_archive(
+    archive_path: &Path,
+    config: &AppConfig,
+    manifests: &[ProfileManifest],
+    exclude: &ExcludeFilter,
+) -> Result<usize, AppError> {
+    let partial_path = archive_path.with_extension(format!("{}.partial", ARCHIVE_EXTENSION));
+    let result = write_archive_to(&partial_path, config, manifests, exclude).and_then(|skipped| {
+        fs::rename(&partial_path, archive_path)
+            .map(|_| skipped)
+            .map_err(write_failed)
+    });
+    if result.is_err() {
+        fs::remove_file(&partial_path).ok();
+    }
+    result
+}
+
+fn write_archive_to(
+    path: &Path,
+    config: &AppConfig,
+    manifests: &[ProfileManifest],
+    exclude: &ExcludeFilter,
+) -> Result<usize, AppError> {
+    let key = vault::current_key()?;
+    let file = fs::File::create(path)
+        .map_err(|e| AppError::new(ErrorCode::Io, "archive.create").caused_by(e))?;
+    let mut zip = ZipWriter::new(file);
+    let mut files = Vec::new();
+    let mut skipped = 0;
+
+    // 配置文件
+    let config_content = serde_json::to_vec_pretty(config)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "archive.serialize").caused_by(e))?;
+    zip.start_file("config.json", zip_options(config_content.len() as u64))
+        .map_err(write_failed)?;
+    zip.write_all(&config_content).map_err(write_failed)?;
+    files.push(ArchiveFile {
+        path: "config.json".to_string(),
+        size: config_content.len() as u64,
+        hash: format!("{:x}", Sha256::digest(&config_content)),
+    });
+
+    // 用户备份：blob 内容直接写入，哈希沿用清单中的记录
+    let (files_total, bytes_total) = manifests
+        .iter()
+        .flat_map(|m| &m.files)
+        .filter(|entry| !exclude.covers(&entry.path))
+        .fold((0, 0), |(n, bytes), entry| (n + 1, bytes + entry.size));
+    progress::phase(ProgressPhase::Archive, None, files_total, bytes_total);
+
+    for manifest in manifests {
+        let prefix = format!("profiles/{}", manifest.user_id);
+        for dir in manifest.dirs.iter().filter(|d| !exclude.covers(d)) {
+            zip.add_directory(format!("{}/{}/", prefix, dir), SimpleFileOptions::default())
+                .map_err(write_failed)?;
+        }
+        for entry in &manifest.files {
+            if exclude.covers(&entry.path) {
+                skipped += 1;
+                continue;
+            }
+            operation::check_cancelled()?;
+            let name = format!("{}/{}", prefix, entry.path);
+            zip.start_file(name.as_str(), zip_options(entry.size))
+                .map_err(write_failed)?;
+            store::copy_blob_to(&entry.hash, key.as_ref(), &mut zip)?;
+            progress::advance(&name, entry.size);
+            files.push(ArchiveFile {
+                path: name,
+                size: entry.size,
+                hash: entry.hash.clone(),
+            });
+        }
+    }
+
+    let manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        format_version: ARCHIVE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: std::env::consts::OS.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        users: manifests.iter().map(|m| m.user_id.clone()).collect(),
+        files,
+    };
+    let manifest_content = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "archive.serialize").caused_by(e))?;
+    zip.start_file(MANIFEST_NAME, zip_options(manifest_content.len() as u64))
+        .map_err(write_failed)?;
+    zip.write_all(&manifest_content).map_err(write_failed)?;
+
+    let file = zip.finish().map_err(write_failed)?;
+    file.sync_all().map_err(write_failed)?;
+    Ok(skipped)
+}
+
+/// 读取并校验归档清单
+fn read_manifest<R: Read + io::Seek>(zip: &mut ZipArchive<R>) -> Result<ArchiveManifest, AppError> {
+    let mut entry = zip
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| invalid("archive.manifestMissing"))?;
+    // 先核对声明的大小，读取时再限制实际字节数
+    if entry.size() > MAX_MANIFEST_SIZE {
+        return Err(invalid("archive.manifestTooLarge"));
+    }
+    let mut content = String::new();
+    (&mut entry)
+        .take(MAX_MANIFEST_SIZE + 1)
+        .read_to_string(&mut content)
+        .map_err(|e| invalid("archive.manifestInvalid").caused_by(e))?;
+    if content.len() as u64 > MAX_MANIFEST_SIZE {
+        return Err(invalid("archive.manifestTooLarge"));
+    }
+    let manifest: ArchiveManifest = serde_json::from_str(&content)
+        .map_err(|e| invalid("archive.manifestInvalid").caused_by(e))?;
+
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err(invalid("archive.notRoxybak"));
+    }
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(invalid("archive.tooNew").with("version", &manifest.app_version));
+    }
+    Ok(manifest)
+}
+
+/// 将归档解压到 `dest_dir`，并逐个校验文件哈希
+///
+/// 解压目标应为临时目录：只有返回成功后，调用方才应把内容导入存储。
+pub fn extract_archive(archive_path: &Path, dest_dir: &Path) -> Result<ArchiveManifest, AppError> {
+    let file = fs::File::open(archive_path)
+        .map_err(|e| AppError::new(ErrorCode::Io, "archive.open").caused_by(e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| invalid("archive.corrupt").caused_by(e))?;
+    let manifest = read_manifest(&mut zip)?;
+
+    let mut expected: HashMap<&str, &ArchiveFile> = HashMap::new();
+    for f in &manifest.files {
+        if !is_safe_relative(&f.path) {
+            return Err(invalid("archive.unsafePath").with("path", &f.path));
+        }
+        expected.insert(f.path.as_str(), f);
+    }
+
+    progress::phase(
+        ProgressPhase::Extract,
+        None,
+        manifest.files.len(),
+        manifest.files.iter().map(|f| f.size).sum(),
+    );
+
+    let mut seen = HashSet::new();
+    for i in 0..zip.len() {
+        operation::check_cancelled()?;
+        let mut entry = zip
+            .by_index(i)
+            .map_err(|e| invalid("archive.corrupt").caused_by(e))?;
+        let name = entry.name().to_string();
+        if name == MANIFEST_NAME {
+            continue;
+        }
+        let Some(rel) = entry.enclosed_name() else {
+            return Err(invalid("archive.unsafePath").with("path", &name));
+        };
+        let extract_failed = |e: io::Error| {
+            AppError::new(ErrorCode::Io, "archive.extractFile")
+                .with("path", &name)
+                .caused_by(e)
+        };
+        let dst = dest_dir.join(rel);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&dst).map_err(extract_failed)?;
+            continue;
+        }
+
+        let Some(expect) = expected.get(name.as_str()) else {
+            return Err(invalid("archive.unexpectedFile").with("path", &name));
+        };
+        // 写入前先核对声明的解压大小，读取时再限制实际字节数，防止压缩炸弹写满磁盘
+        let size_mismatch =
+            || AppError::new(ErrorCode::DataCorrupted, "archive.sizeMismatch").with("path", &name);
+        if entry.size() != expect.size {
+            return Err(size_mismatch());
+        }
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(extract_failed)?;
+        }
+
+        let mut out = fs::File::create(&dst).map_err(extract_failed)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut limited = (&mut entry).take(expect.size + 1);
+        loop {
+            let n = limited.read(&mut buf).map_err(extract_failed)?;
+            if n == 0 {
+                break;
+            }
+            size += n as u64;
+            if size > expect.size {
+                return Err(size_mismatch());
+            }
+            hasher.update(&buf[..n]);
+            out.write_all(&buf[..n]).map_err(extract_failed)?;
+        }
+
+        if size != expect.size || format!("{:x}", hasher.finalize()) != expect.hash {
+            return Err(
+                AppError::new(ErrorCode::DataCorrupted, "archive.hashMismatch").with("path", &name),
+            );
+        }
+        progress::advance(&name, size);
+        seen.insert(name);
+    }
+
+    if seen.len() != manifest.files.len() {
+        return Err(invalid("archive.incomplete"));
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::{root, TestRoot};
+
+    /// 写出归档：`entries` 为实际内容，`listed` 为清单中记录的内容
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])], listed: &[(&str, &[u8])]) {
+        let manifest = ArchiveManifest {
+            format: ARCHIVE_FORMAT.to_string(),
+            format_version: ARCHIVE_FORMAT_VERSION,
+            app_version: "test".to_string(),
+            platform: "test".to_string(),
+            created_at: String::new(),
+            users: Vec::new(),
+            files: listed
+                .iter()
+                .map(|(path, content)| ArchiveFile {
+                    path: path.to_string(),
+                    size: content.len() as u64,
+                    hash: format!("{:x}", Sha256::digest(content)),
+                })
+                .collect(),
+        };
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip_options(content.len() as u64))
+                .unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.start_file(MANIFEST_NAME, zip_options(0)).unwrap();
+        zip.write_all(&serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn extracts_and_verifies_hashes() {
+        let _root = TestRoot::new("archive-ok");
+        let dir = root().unwrap();
+        let archive = dir.join("a.roxybak");
+        let files: &[(&str, &[u8])] = &[("config.json", b"{}"), ("profiles/u/Cookies", b"cookie")];
+        write_zip(&archive, files, files);
+
+        let manifest = extract_archive(&archive, &dir.join("out")).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(
+            fs::read(dir.join("out/profiles/u/Cookies")).unwrap(),
+            b"cookie"
+        );
+    }
+
+    #[test]
+    fn rejects_modified_content() {
+        let _root = TestRoot::new("archive-hash");
+        let dir = root().unwrap();
+        let archive = dir.join("a.roxybak");
+        // 大小相同、内容不同
+        write_zip(
+            &archive,
+            &[("config.json", b"[]")],
+            &[("config.json", b"{}")],
+        );
+        let e = extract_archive(&archive, &dir.join("out")).unwrap_err();
+        assert_eq!(e.key, "archive.hashMismatch");
+    }
+
+    #[test]
+    fn rejects_entries_larger_than_listed() {
+        let _root = TestRoot::new("archive-size");
+        let dir = root().unwrap();
+        let archive = dir.join("a.roxybak");
+        let big = vec![0u8; 1024 * 1024];
+        write_zip(
+            &archive,
+            &[("config.json", &big)],
+            &[("config.json", b"{}")],
+        );
+        let e = extract_archive(&archive, &dir.join("out")).unwrap_err();
+        assert_eq!(e.key, "archive.sizeMismatch");
+        // 声明的大小不符时不写入任何内容
+        assert!(!dir.join("out/config.json").exists());
+    }
+
+    #[test]
+    fn rejects_oversized_manifests() {
+        let _root = TestRoot::new("archive-manifest");
+        let dir = root().unwrap();
+        let archive = dir.join("a.roxybak");
+        let mut zip = ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file(MANIFEST_NAME, options).unwrap();
+        zip.write_all(&vec![b' '; MAX_MANIFEST_SIZE as usize + 1])
+            .unwrap();
+        zip.finish().unwrap();
+
+        let e = extract_archive(&archive, &dir.join("out")).unwrap_err();
+        assert_eq!(e.key, "archive.manifestTooLarge");
+    }
+
+    #[test]
+    fn rejects_unlisted_and_missing_files() {
+        let _root = TestRoot::new("archive-listed");
+        let dir = root().unwrap();
+        let archive = dir.join("a.roxybak");
+        write_zip(
+            &archive,
+            &[("config.json", b"{}"), ("extra", b"x")],
+            &[("config.json", b"{}")],
+        );
+        let e = extract_archive(&archive, &dir.join("out")).unwrap_err();
+        assert_eq!(e.key, "archive.unexpectedFile");
+
+        write_zip(
+            &archive,
+            &[("config.json", b"{}")],
+            &[("config.json", b"{}"), ("gone", b"x")],
+        );
+        let e = extract_archive(&archive, &dir.join("out2")).unwrap_err();
+        assert_eq!(e.key, "archive.incomplete");
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_destination() {
+        let _root = TestRoot::new("archive-path");
+        let dir = root().unwrap();
+        let archive = dir.join("a.roxybak");
+        for path in ["../evil", "/etc/evil", "profiles/../../evil", ""] {
+            write_zip(
+                &archive,
+                &[("config.json", b"{}")],
+                &[("config.json", b"{}"), (path, b"x")],
+            );
+            let e = extract_archive(&archive, &dir.join("out")).unwrap_err();
+            assert_eq!(e.key, "archive.unsafePath", "{}", path);
+        }
+        assert!(!dir.join("evil").exists());
+        assert!(is_safe_relative(
+            "profiles/u/Local Storage/leveldb/000003.log"
+        ));
+    }
+}
@@ -0,0 +1,577 @@
+use super::progress::{self, ProgressPhase, ProgressReader};
+use crate::error::{AppError, ErrorCode};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{generic_array::GenericArray, Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// 加密导出文件的文件头标识
+const EXPORT_MAGIC: &[u8; 8] = b"ROXYENC1";
+
+/// 每个加密分块的明文大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 分块认证标签长度
+const TAG_SIZE: usize = 16;
+
+/// XChaCha20-Poly1305 STREAM 构造的 nonce 前缀长度（24 - 5）
+const STREAM_NONCE_SIZE: usize = 19;
+
+/// 读取文件头时允许的最大长度，防止被构造的文件耗尽内存
+const MAX_HEADER_SIZE: u32 = 64 * 1024;
+
+/// 文件头中 Argon2 参数的上限，超出时视为被篡改，避免构造的文件让派生耗尽内存或时间
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 10;
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+/// 32 字节对称密钥
+pub type SecretKey = [u8; 32];
+
+/// 密钥派生参数（Argon2id）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    #[serde(rename = "memoryKib")]
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// 十六进制盐值
+    pub salt: String,
+}
+
+impl KdfParams {
+    /// 生成带随机盐的默认参数
+    pub fn generate() -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            algorithm: "argon2id".to_string(),
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+            salt: hex::encode(salt),
+        }
+    }
+}
+
+/// 加密导出文件头
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    kdf: KdfParams,
+    /// 十六进制 STREAM nonce 前缀
+    nonce: String,
+    #[serde(rename = "chunkSize")]
+    chunk_size: u32,
+    /// 派生密钥的校验值，用于区分“密码错误”和“文件被篡改”
+    #[serde(rename = "keyCheck")]
+    key_check: String,
+}
+
+fn read_failed(e: io::Error) -> AppError {
+    AppError::new(ErrorCode::Io, "crypto.read").caused_by(e)
+}
+
+fn write_failed(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorCode::Io, "crypto.write").caused_by(e)
+}
+
+fn tampered() -> AppError {
+    AppError::new(ErrorCode::DataCorrupted, "crypto.tampered")
+}
+
+fn header_corrupt() -> AppError {
+    AppError::new(ErrorCode::DataCorrupted, "crypto.headerCorrupt")
+}
+
+/// 由密码派生密钥
+pub fn derive_key(passphrase: &str, params: &KdfParams) -> Result<SecretKey, AppError> {
+    if params.algorithm != "argon2id" {
+        return Err(
+            AppError::new(ErrorCode::DataCorrupted, "crypto.unsupportedKdf")
+                .with("algorithm", &params.algorithm),
+        );
+    }
+    if params.memory_kib > MAX_KDF_MEMORY_KIB
+        || params.iterations > MAX_KDF_ITERATIONS
+        || params.parallelism > MAX_KDF_PARALLELISM
+    {
+        return Err(tampered());
+    }
+    let salt = hex::decode(&params.salt)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted, "crypto.invalidKdf"))?;
+    let argon_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(32),
+    )
+    .map_err(|e| AppError::new(ErrorCode::DataCorrupted, "crypto.invalidKdf").caused_by(e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "crypto.deriveFailed").caused_by(e))?;
+    Ok(key)
+}
+
+/// 密钥校验值
+pub fn key_check(key: &SecretKey) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"roxy-manager-key-check");
+    hasher.update(key);
+    hex::encode(hasher.finalize())
+}
+
+/// 尽量读满缓冲区，返回实际读取的字节数（小于缓冲区长度表示已到结尾）
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn read_chunk<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; size];
+    let n = read_full(reader, &mut buf)?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+/// 以 STREAM 方式分块加密：每块独立认证，最后一块带结束标记，
+/// 截断、重排或修改任意分块都会在解密时被发现
+fn seal_stream<R: Read, W: Write>(
+    key: &SecretKey,
+    nonce: &[u8],
+    aad: &[u8],
+    chunk_size: usize,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), AppError> {
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(key));
+    let mut encryptor = EncryptorBE32::from_aead(cipher, GenericArray::from_slice(nonce));
+
+    let mut current = read_chunk(reader, chunk_size).map_err(read_failed)?;
+    loop {
+        let next = if current.len() == chunk_size {
+            read_chunk(reader, chunk_size).map_err(read_failed)?
+        } else {
+            Vec::new()
+        };
+
+        if next.is_empty() {
+            let sealed = encryptor
+                .encrypt_last(Payload { msg: &current, aad })
+                .map_err(|_| AppError::new(ErrorCode::Internal, "crypto.encryptFailed"))?;
+            writer.write_all(&sealed).map_err(write_failed)?;
+            return Ok(());
+        }
+
+        let sealed = encryptor
+            .encrypt_next(Payload { msg: &current, aad })
+            .map_err(|_| AppError::new(ErrorCode::Internal, "crypto.encryptFailed"))?;
+        writer.write_all(&sealed).map_err(write_failed)?;
+        current = next;
+    }
+}
+
+/// 解密 [`seal_stream`] 的输出，任何分块认证失败都返回错误
+fn open_stream<R: Read, W: Write>(
+    key: &SecretKey,
+    nonce: &[u8],
+    aad: &[u8],
+    chunk_size: usize,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), AppError> {
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(key));
+    let mut decryptor = DecryptorBE32::from_aead(cipher, GenericArray::from_slice(nonce));
+    let sealed_size = chunk_size + TAG_SIZE;
+
+    let mut current = read_chunk(reader, sealed_size).map_err(read_failed)?;
+    loop {
+        let next = if current.len() == sealed_size {
+            read_chunk(reader, sealed_size).map_err(read_failed)?
+        } else {
+            Vec::new()
+        };
+
+        if next.is_empty() {
+            let plain = decryptor
+                .decrypt_last(Payload { msg: &current, aad })
+                .map_err(|_| tampered())?;
+            writer.write_all(&plain).map_err(write_failed)?;
+            return Ok(());
+        }
+
+        let plain = decryptor
+            .decrypt_next(Payload { msg: &current, aad })
+            .map_err(|_| tampered())?;
+        writer.write_all(&plain).map_err(write_failed)?;
+        current = next;
+    }
+}
+
+/// 判断文件是否为加密导出文件
+pub fn is_encrypted_export(path: &Path) -> bool {
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut magic = [0u8; 8];
+    matches!(read_full(&mut file, &mut magic), Ok(8)) && &magic == EXPORT_MAGIC
+}
+
+/// 用密码加密导出文件
+///
+/// 文件头（含 KDF 参数和 nonce）作为每个分块的关联数据一并认证。
+/// 先写入 `.partial` 临时文件，完成后再重命名为目标文件。
+pub fn encrypt_export(src: &Path, dst: &Path, passphrase: &str) -> Result<(), AppError> {
+    let kdf = KdfParams::generate();
+    let key = derive_key(passphrase, &kdf)?;
+    let mut nonce = [0u8; STREAM_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let header = ExportHeader {
+        kdf,
+        nonce: hex::encode(nonce),
+        chunk_size: CHUNK_SIZE as u32,
+        key_check: key_check(&key),
+    };
+    let header_json = serde_json::to_vec(&header)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "crypto.serializeHeader").caused_by(e))?;
+
+    let mut aad = Vec::with_capacity(12 + header_json.len());
+    aad.extend_from_slice(EXPORT_MAGIC);
+    aad.extend_from_slice(&(header_json.len() as u32).to_le_bytes());
+    aad.extend_from_slice(&header_json);
+
+    let partial = dst.with_extension("partial");
+    let result = (|| {
+        let file = fs::File::open(src)
+            .map_err(|e| AppError::new(ErrorCode::Io, "crypto.openSource").caused_by(e))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        progress::phase(ProgressPhase::Encrypt, None, 0, size);
+        let mut reader = ProgressReader::new(io::BufReader::new(file));
+        let file = fs::File::create(&partial)
+            .map_err(|e| AppError::new(ErrorCode::Io, "crypto.createOutput").caused_by(e))?;
+        let mut writer = io::BufWriter::new(file);
+        writer.write_all(&aad).map_err(write_failed)?;
+        seal_stream(&key, &nonce, &aad, CHUNK_SIZE, &mut reader, &mut writer)?;
+        let file = writer.into_inner().map_err(write_failed)?;
+        file.sync_all().map_err(write_failed)?;
+        fs::rename(&partial, dst).map_err(write_failed)
+    })();
+
+    if result.is_err() {
+        fs::remove_file(&partial).ok();
+    }
+    result
+}
+
+/// 用密码解密导出文件到 `dst`
+///
+/// 密码错误与内容被篡改分别给出不同的错误；失败时删除已写出的部分。
+pub fn decrypt_export(src: &Path, dst: &Path, passphrase: &str) -> Result<(), AppError> {
+    let mut reader = io::BufReader::new(
+        fs::File::open(src)
+            .map_err(|e| AppError::new(ErrorCode::Io, "crypto.openSource").caused_by(e))?,
+    );
+
+    let mut prefix = [0u8; 12];
+    if read_full(&mut reader, &mut prefix).map_err(read_failed)? != 12
+        || &prefix[..8] != EXPORT_MAGIC
+    {
+        return Err(AppError::new(
+            ErrorCode::InvalidArchive,
+            "crypto.notEncrypted",
+        ));
+    }
+    let header_len = u32::from_le_bytes([prefix[8], prefix[9], prefix[10], prefix[11]]);
+    if header_len > MAX_HEADER_SIZE {
+        return Err(header_corrupt());
+    }
+    let mut header_json = vec![0u8; header_len as usize];
+    if read_full(&mut reader, &mut header_json).map_err(read_failed)? != header_json.len() {
+        return Err(header_corrupt());
+    }
+    let header: ExportHeader =
+        serde_json::from_slice(&header_json).map_err(|_| header_corrupt())?;
+
+    let nonce = hex::decode(&header.nonce).map_err(|_| header_corrupt())?;
+    let chunk_size = header.chunk_size as usize;
+    if nonce.len() != STREAM_NONCE_SIZE || chunk_size == 0 || chunk_size > 16 * 1024 * 1024 {
+        return Err(header_corrupt());
+    }
+
+    let key = derive_key(passphrase, &header.kdf)?;
+    if key_check(&key) != header.key_check {
+        return Err(AppError::new(
+            ErrorCode::WrongPassphrase,
+            "crypto.wrongPassphrase",
+        ));
+    }
+
+    let mut aad = prefix.to_vec();
+    aad.extend_from_slice(&header_json);
+
+    let result = (|| {
+        let file = fs::File::create(dst)
+            .map_err(|e| AppError::new(ErrorCode::Io, "crypto.createOutput").caused_by(e))?;
+        let mut writer = io::BufWriter::new(file);
+        let size = fs::metadata(src).map(|m| m.len()).unwrap_or(0);
+        progress::phase(
+            ProgressPhase::Decrypt,
+            None,
+            0,
+            size.saturating_sub(aad.len() as u64),
+        );
+        let mut reader = ProgressReader::new(&mut reader);
+        open_stream(&key, &nonce, &aad, chunk_size, &mut reader, &mut writer)?;
+        writer.flush().map_err(write_failed)
+    })();
+
+    if result.is_err() {
+        fs::remove_file(dst).ok();
+    }
+    result
+}
+
+/// 存储中加密 blob 的文件头标识
+const BLOB_MAGIC: &[u8; 8] = b"ROXYBLB1";
+
+/// 包装密钥使用的 XChaCha20-Poly1305 nonce 长度
+const WRAP_NONCE_SIZE: usize = 24;
+
+/// 生成随机密钥
+pub fn generate_key() -> SecretKey {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+/// 用密钥加密密钥（KEK）包装数据密钥，返回十六进制的 nonce + 密文
+pub fn wrap_key(kek: &SecretKey, key: &SecretKey) -> Result<String, AppError> {
+    let mut nonce = [0u8; WRAP_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(kek));
+    let sealed = cipher
+        .encrypt(GenericArray::from_slice(&nonce), key.as_slice())
+        .map_err(|_| AppError::new(ErrorCode::Internal, "crypto.encryptFailed"))?;
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&sealed);
+    Ok(hex::encode(out))
+}
+
+/// 解开 [`wrap_key`] 包装的数据密钥
+pub fn unwrap_key(kek: &SecretKey, wrapped: &str) -> Result<SecretKey, AppError> {
+    let keyfile_corrupt = || AppError::new(ErrorCode::DataCorrupted, "vault.keyfileCorrupt");
+    let bytes = hex::decode(wrapped).map_err(|_| keyfile_corrupt())?;
+    if bytes.len() <= WRAP_NONCE_SIZE {
+        return Err(keyfile_corrupt());
+    }
+    let (nonce, sealed) = bytes.split_at(WRAP_NONCE_SIZE);
+    let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(kek));
+    let plain = cipher
+        .decrypt(GenericArray::from_slice(nonce), sealed)
+        .map_err(|_| AppError::new(ErrorCode::WrongPassphrase, "crypto.wrongPassphrase"))?;
+    plain.try_into().map_err(|_| keyfile_corrupt())
+}
+
+/// 判断 blob 文件是否已加密
+pub fn is_sealed_blob(path: &Path) -> bool {
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut magic = [0u8; 8];
+    matches!(read_full(&mut file, &mut magic), Ok(8)) && &magic == BLOB_MAGIC
+}
+
+/// 判断内存中的内容是否为 [`seal_blob`] 的输出
+pub fn is_sealed_data(data: &[u8]) -> bool {
+    data.starts_with(BLOB_MAGIC)
+}
+
+/// 加密存储中 blob 的文件名：用数据密钥对内容哈希做 HMAC-SHA256，不暴露明文的哈希
+pub fn blob_name(key: &SecretKey, hash: &str) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(b"roxy-blob-name\0");
+    mac.update(hash.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 加密一个 blob：文件头 + 随机 nonce，内容按 STREAM 分块认证
+pub fn seal_blob<R: Read, W: Write>(
+    key: &SecretKey,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), AppError> {
+    let mut nonce = [0u8; STREAM_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let mut aad = BLOB_MAGIC.to_vec();
+    aad.extend_from_slice(&nonce);
+
+    writer.write_all(&aad).map_err(write_failed)?;
+    seal_stream(key, &nonce, &aad, CHUNK_SIZE, reader, writer)
+}
+
+/// 解密 [`seal_blob`] 写出的内容
+pub fn open_blob<R: Read, W: Write>(
+    key: &SecretKey,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), AppError> {
+    let mut aad = [0u8; 8 + STREAM_NONCE_SIZE];
+    if read_full(reader, &mut aad).map_err(read_failed)? != aad.len() || &aad[..8] != BLOB_MAGIC {
+        return Err(AppError::new(ErrorCode::DataCorrupted, "store.blobCorrupt"));
+    }
+    let nonce = aad[8..].to_vec();
+    open_stream(key, &nonce, &aad, CHUNK_SIZE, reader, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seal(key: &SecretKey, nonce: &[u8], chunk_size: usize, plain: &[u8]) -> Vec<u8> {
+        let mut sealed = Vec::new();
+        seal_stream(key, nonce, b"aad", chunk_size, &mut &plain[..], &mut sealed).unwrap();
+        sealed
+    }
+
+    fn open(
+        key: &SecretKey,
+        nonce: &[u8],
+        chunk_size: usize,
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, AppError> {
+        let mut plain = Vec::new();
+        open_stream(key, nonce, b"aad", chunk_size, &mut &sealed[..], &mut plain)?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn stream_round_trip() {
+        let key = generate_key();
+        let nonce = [7u8; STREAM_NONCE_SIZE];
+        // 空内容、不足一块、恰好整块、跨多块
+        for len in [0, 5, 16, 16 * 3 + 1] {
+            let plain: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let sealed = seal(&key, &nonce, 16, &plain);
+            assert_eq!(open(&key, &nonce, 16, &sealed).unwrap(), plain);
+        }
+    }
+
+    #[test]
+    fn stream_rejects_tampering() {
+        let key = generate_key();
+        let nonce = [7u8; STREAM_NONCE_SIZE];
+        let sealed = seal(&key, &nonce, 16, &[1u8; 40]);
+
+        let mut flipped = sealed.clone();
+        flipped[20] ^= 1;
+        assert_eq!(
+            open(&key, &nonce, 16, &flipped).unwrap_err().key,
+            "crypto.tampered"
+        );
+
+        // 在分块边界截断：剩下的最后一块没有结束标记
+        let truncated = &sealed[..2 * (16 + TAG_SIZE)];
+        assert_eq!(
+            open(&key, &nonce, 16, truncated).unwrap_err().key,
+            "crypto.tampered"
+        );
+
+        let mut other_aad = Vec::new();
+        let result = open_stream(&key, &nonce, b"other", 16, &mut &sealed[..], &mut other_aad);
+        assert_eq!(result.unwrap_err().key, "crypto.tampered");
+        assert_eq!(
+            open(&generate_key(), &nonce, 16, &sealed).unwrap_err().key,
+            "crypto.tampered"
+        );
+    }
+
+    #[test]
+    fn blob_round_trip() {
+        let key = generate_key();
+        let plain = vec![42u8; CHUNK_SIZE + 100];
+        let mut sealed = Vec::new();
+        seal_blob(&key, &mut &plain[..], &mut sealed).unwrap();
+        assert_eq!(&sealed[..8], BLOB_MAGIC);
+
+        let mut opened = Vec::new();
+        open_blob(&key, &mut &sealed[..], &mut opened).unwrap();
+        assert_eq!(opened, plain);
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        let result = open_blob(&key, &mut &sealed[..], &mut Vec::new());
+        assert_eq!(result.unwrap_err().key, "crypto.tampered");
+        let result = open_blob(&key, &mut &b"not a blob"[..], &mut Vec::new());
+        assert_eq!(result.unwrap_err().key, "store.blobCorrupt");
+    }
+
+    #[test]
+    fn wrapped_key_needs_the_same_kek() {
+        let kek = generate_key();
+        let key = generate_key();
+        let wrapped = wrap_key(&kek, &key).unwrap();
+        assert_eq!(unwrap_key(&kek, &wrapped).unwrap(), key);
+        let e = unwrap_key(&generate_key(), &wrapped).unwrap_err();
+        assert_eq!(e.code, ErrorCode::WrongPassphrase);
+        assert_eq!(
+            unwrap_key(&kek, "zz").unwrap_err().key,
+            "vault.keyfileCorrupt"
+        );
+    }
+
+    #[test]
+    fn kdf_params_are_bounded() {
+        let cheap = KdfParams {
+            memory_kib: 8,
+            iterations: 1,
+            ..KdfParams::generate()
+        };
+        let key = derive_key("pw", &cheap).unwrap();
+        assert_eq!(derive_key("pw", &cheap).unwrap(), key);
+        assert_ne!(derive_key("other", &cheap).unwrap(), key);
+
+        for params in [
+            KdfParams {
+                memory_kib: MAX_KDF_MEMORY_KIB + 1,
+                ..cheap.clone()
+            },
+            KdfParams {
+                iterations: MAX_KDF_ITERATIONS + 1,
+                ..cheap.clone()
+            },
+            KdfParams {
+                parallelism: MAX_KDF_PARALLELISM + 1,
+                ..cheap.clone()
+            },
+        ] {
+            assert_eq!(
+                derive_key("pw", &params).unwrap_err().key,
+                "crypto.tampered"
+            );
+        }
+        let unknown = KdfParams {
+            algorithm: "scrypt".to_string(),
+            ..cheap
+        };
+        assert_eq!(
+            derive_key("pw", &unknown).unwrap_err().key,
+            "crypto.unsupportedKdf"
+        );
+    }
+}
@@ -0,0 +1,685 @@
+use super::launcher;
+use super::profile::{load_config, BACKUP_ITEMS};
+use super::settings::{load_settings, BackupItem, BackupItemSet, VersionItemSet};
+use super::store::{to_local_path, top_level_item, ProfileManifest};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n;
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path};
+
+/// 内置备份项集合的编号
+pub const BUILTIN_SET_ID: &str = "default";
+
+/// 按集合解析出的实际备份范围
+#[derive(Debug, Clone)]
+pub struct ResolvedItems {
+    /// 集合编号，导入等不按集合解析时为 `None`
+    pub set_id: Option<String>,
+    /// 需要整体备份/替换的路径（相对数据目录，`/` 分隔），互不嵌套
+    pub roots: Vec<String>,
+    /// 没有匹配到任何路径的必需项
+    pub missing: Vec<String>,
+    exclude: ExcludeFilter,
+}
+
+/// 排除规则：匹配到的文件或目录在备份、恢复和导出时跳过
+#[derive(Debug, Clone)]
+pub struct ExcludeFilter {
+    set: GlobSet,
+}
+
+impl ExcludeFilter {
+    pub fn new(patterns: &[String]) -> Result<Self, AppError> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(
+                GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| invalid_pattern(pattern, e))?,
+            );
+        }
+        let set = builder.build().map_err(|e| {
+            AppError::new(ErrorCode::InvalidSettings, "itemSet.invalidExclude").caused_by(e)
+        })?;
+        Ok(Self { set })
+    }
+
+    /// 设置中的全局排除规则
+    pub fn from_settings() -> Result<Self, AppError> {
+        Self::new(&load_settings().unwrap_or_default().exclude_patterns)
+    }
+
+    /// 路径（相对数据目录，`/` 分隔）是否被排除
+    pub fn is_excluded(&self, rel: &str) -> bool {
+        self.set.is_match(rel)
+    }
+
+    /// 路径本身或任一上级目录是否被排除
+    pub fn covers(&self, rel: &str) -> bool {
+        let mut prefix = String::new();
+        rel.split('/').any(|part| {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            self.is_excluded(&prefix)
+        })
+    }
+}
+
+impl ResolvedItems {
+    /// 不按集合，直接取目录下的全部条目（用于导入已导出的用户目录）
+    pub fn all_entries(root: &Path) -> Result<Self, AppError> {
+        let mut roots: Vec<String> = fs::read_dir(root)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        roots.sort();
+        Ok(Self {
+            set_id: None,
+            roots,
+            missing: Vec::new(),
+            exclude: ExcludeFilter::from_settings()?,
+        })
+    }
+
+    /// 路径是否被排除
+    pub fn is_excluded(&self, rel: &str) -> bool {
+        self.exclude.is_excluded(rel)
+    }
+
+    /// 必需项缺失时返回错误
+    pub fn ensure_complete(&self) -> Result<(), AppError> {
+        if self.missing.is_empty() {
+            Ok(())
+        } else {
+            Err(
+                AppError::new(ErrorCode::BackupIncomplete, "backup.missingRequired")
+                    .with("items", self.missing.join(", ")),
+            )
+        }
+    }
+}
+
+/// 内置集合：与早期版本固定备份的条目一致
+pub fn builtin_set() -> BackupItemSet {
+    BackupItemSet {
+        id: BUILTIN_SET_ID.to_string(),
+        name: i18n::text("itemSet.builtinName").to_string(),
+        include: BACKUP_ITEMS
+            .iter()
+            .map(|item| BackupItem {
+                pattern: item.to_string(),
+                required: false,
+            })
+            .collect(),
+        exclude: Vec::new(),
+    }
+}
+
+/// 按编号查找集合，找不到时返回 `None`
+pub fn find_set(set_id: &str) -> Option<BackupItemSet> {
+    if set_id == BUILTIN_SET_ID {
+        return Some(builtin_set());
+    }
+    load_settings()
+        .ok()?
+        .backup_item_sets
+        .into_iter()
+        .find(|s| s.id == set_id)
+}
+
+/// 版本号是否以 `prefix` 开头，按点分段比较
+fn version_matches(prefix: &str, version: &str) -> bool {
+    let mut parts = version.trim().split('.');
+    prefix.trim().split('.').all(|p| parts.next() == Some(p))
+}
+
+/// 与版本号匹配的集合编号，多个前缀匹配时取最长的
+fn set_for_version(version_sets: &[VersionItemSet], version: &str) -> Option<String> {
+    version_sets
+        .iter()
+        .filter(|v| version_matches(&v.version, version))
+        .max_by_key(|v| v.version.trim().split('.').count())
+        .map(|v| v.set_id.clone())
+}
+
+/// 校验按版本选择的集合：版本前缀由数字和点组成且不重复，集合必须存在
+pub fn validate_version_sets(
+    version_sets: &[VersionItemSet],
+    set_exists: impl Fn(&str) -> bool,
+) -> Result<(), AppError> {
+    let mut versions = HashSet::new();
+    for v in version_sets {
+        let version = v.version.trim();
+        let valid = version
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if !valid || !versions.insert(version) {
+            return Err(
+                AppError::new(ErrorCode::InvalidSettings, "itemSet.invalidVersion")
+                    .with("version", version),
+            );
+        }
+        if !set_exists(&v.set_id) {
+            return Err(
+                AppError::new(ErrorCode::ItemSetNotFound, "itemSet.notFound").with("id", &v.set_id),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 用户实际使用的集合：用户单独指定的优先，其次是与 RoxyBrowser 版本匹配的集合，
+/// 再次是设置中的默认集合，最后是内置集合
+pub fn set_for_user(user_id: Option<&str>) -> BackupItemSet {
+    let user_set = user_id.and_then(|user_id| {
+        load_config()
+            .ok()?
+            .users
+            .into_iter()
+            .find(|u| u.id == user_id)
+            .and_then(|u| u.backup_item_set)
+    });
+    let settings = load_settings().ok();
+    // 没有配置按版本选择的集合时不需要检测版本
+    let version_set = || {
+        let version_sets = &settings.as_ref()?.version_backup_item_sets;
+        if version_sets.is_empty() {
+            return None;
+        }
+        set_for_version(version_sets, &launcher::detect_version()?)
+    };
+    let default_set = || settings.as_ref()?.default_backup_item_set.clone();
+
+    user_set
+        .and_then(|id| find_set(&id))
+        .or_else(|| version_set().and_then(|id| find_set(&id)))
+        .or_else(|| default_set().and_then(|id| find_set(&id)))
+        .unwrap_or_else(builtin_set)
+}
+
+fn has_glob_meta(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+fn invalid_pattern(pattern: &str, e: globset::Error) -> AppError {
+    AppError::new(ErrorCode::InvalidSettings, "itemSet.invalidPattern")
+        .with("pattern", pattern)
+        .caused_by(e)
+}
+
+fn compile(pattern: &str) -> Result<GlobMatcher, AppError> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|g| g.compile_matcher())
+        .map_err(|e| invalid_pattern(pattern, e))
+}
+
+/// 模式只能是数据目录内的相对路径
+fn validate_pattern(pattern: &str) -> Result<(), AppError> {
+    let path = Path::new(pattern);
+    let relative = !pattern.is_empty()
+        && !pattern.starts_with('/')
+        && path.components().all(|c| matches!(c, Component::Normal(_)));
+    if !relative {
+        return Err(
+            AppError::new(ErrorCode::InvalidSettings, "itemSet.notRelative")
+                .with("pattern", pattern),
+        );
+    }
+    if has_glob_meta(pattern) {
+        compile(pattern)?;
+    }
+    Ok(())
+}
+
+/// 校验自定义集合：编号唯一且不与内置集合冲突，模式合法
+pub fn validate_sets(sets: &[BackupItemSet]) -> Result<(), AppError> {
+    let mut ids = HashSet::new();
+    for set in sets {
+        if set.id.trim().is_empty() {
+            return Err(AppError::new(ErrorCode::InvalidSettings, "itemSet.emptyId"));
+        }
+        if set.id == BUILTIN_SET_ID || !ids.insert(set.id.as_str()) {
+            return Err(
+                AppError::new(ErrorCode::InvalidSettings, "itemSet.duplicateId")
+                    .with("id", &set.id),
+            );
+        }
+        if set.include.is_empty() {
+            return Err(
+                AppError::new(ErrorCode::InvalidSettings, "itemSet.empty").with("name", &set.name)
+            );
+        }
+        for item in &set.include {
+            validate_pattern(&item.pattern)?;
+        }
+        for pattern in &set.exclude {
+            compile(pattern)?;
+        }
+    }
+    Ok(())
+}
+
+/// 在 `root` 下展开一个备份项，返回匹配到的路径
+fn expand(root: &Path, pattern: &str) -> Result<Vec<String>, AppError> {
+    if !has_glob_meta(pattern) {
+        let exists = fs::symlink_metadata(to_local_path(root, pattern)).is_ok();
+        return Ok(if exists {
+            vec![pattern.to_string()]
+        } else {
+            Vec::new()
+        });
+    }
+
+    let matcher = compile(pattern)?;
+    let parts: Vec<&str> = pattern.split('/').collect();
+    let recursive = parts.contains(&"**");
+    let prefix: Vec<&str> = parts
+        .iter()
+        .take_while(|p| !has_glob_meta(p))
+        .copied()
+        .collect();
+
+    // 从不含通配符的前缀目录开始逐层向下匹配，匹配到的目录不再深入
+    let mut matches = Vec::new();
+    let mut pending = vec![(prefix.join("/"), prefix.len())];
+    while let Some((rel, depth)) = pending.pop() {
+        let dir = if rel.is_empty() {
+            root.to_path_buf()
+        } else {
+            to_local_path(root, &rel)
+        };
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let child = if rel.is_empty() {
+                name
+            } else {
+                format!("{}/{}", rel, name)
+            };
+            if matcher.is_match(&child) {
+                matches.push(child);
+                continue;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if is_dir && (recursive || depth + 1 < parts.len()) {
+                pending.push((child, depth + 1));
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// 排序去重，并去掉被其他路径包含的路径
+pub fn normalize_roots(mut roots: Vec<String>) -> Vec<String> {
+    roots.sort();
+    roots.dedup();
+    let mut result: Vec<String> = Vec::with_capacity(roots.len());
+    for root in roots {
+        let nested = result.iter().any(|parent| {
+            root.starts_with(parent.as_str()) && root[parent.len()..].starts_with('/')
+        });
+        if !nested {
+            result.push(root);
+        }
+    }
+    result
+}
+
+/// 在 `root` 下解析集合，得到实际需要备份的路径
+pub fn resolve(set: &BackupItemSet, root: &Path) -> Result<ResolvedItems, AppError> {
+    let mut roots = Vec::new();
+    let mut missing = Vec::new();
+    for item in &set.include {
+        let found = expand(root, &item.pattern)?;
+        if found.is_empty() && item.required {
+            missing.push(item.pattern.clone());
+        }
+        roots.extend(found);
+    }
+
+    // 集合自身的排除规则与全局排除规则同时生效
+    let mut exclude = set.exclude.clone();
+    exclude.extend(load_settings().unwrap_or_default().exclude_patterns);
+
+    Ok(ResolvedItems {
+        set_id: Some(set.id.clone()),
+        roots: normalize_roots(roots),
+        missing,
+        exclude: ExcludeFilter::new(&exclude)?,
+    })
+}
+
+/// 备份清单覆盖的路径；早期清单没有记录时按文件的顶层条目推断
+pub fn manifest_roots(manifest: &ProfileManifest) -> Vec<String> {
+    if !manifest.roots.is_empty() {
+        return manifest.roots.clone();
+    }
+    let items = manifest
+        .dirs
+        .iter()
+        .chain(manifest.files.iter().map(|f| &f.path))
+        .map(|rel| top_level_item(rel).to_string())
+        .collect();
+    normalize_roots(items)
+}
+
+/// 检查备份是否包含其集合中的全部必需项（集合已被删除时不检查）
+pub fn check_required(manifest: &ProfileManifest) -> Result<(), AppError> {
+    let Some(set) = manifest.item_set.as_deref().and_then(find_set) else {
+        return Ok(());
+    };
+    let paths: Vec<&str> = manifest
+        .dirs
+        .iter()
+        .chain(manifest.files.iter().map(|f| &f.path))
+        .map(String::as_str)
+        .collect();
+
+    let mut missing = Vec::new();
+    for item in set.include.iter().filter(|i| i.required) {
+        let present = if has_glob_meta(&item.pattern) {
+            let matcher = compile(&item.pattern)?;
+            paths.iter().any(|p| matcher.is_match(p))
+        } else {
+            let prefix = format!("{}/", item.pattern);
+            paths
+                .iter()
+                .any(|p| *p == item.pattern || p.starts_with(&prefix))
+        };
+        if !present {
+            missing.push(item.pattern.clone());
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(
+            AppError::new(ErrorCode::BackupIncomplete, "backup.missingRequiredFor")
+                .with("user", &manifest.user_id)
+                .with("items", missing.join(", ")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::profile::set_user_backup_item_set;
+    use crate::commands::settings::save_settings;
+    use crate::commands::testing::{root, save_users, TestRoot};
+
+    fn item(pattern: &str, required: bool) -> BackupItem {
+        BackupItem {
+            pattern: pattern.to_string(),
+            required,
+        }
+    }
+
+    fn set(id: &str, include: Vec<BackupItem>) -> BackupItemSet {
+        BackupItemSet {
+            id: id.to_string(),
+            name: id.to_string(),
+            include,
+            exclude: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn resolve_expands_patterns_and_reports_missing_items() {
+        let _root = TestRoot::new("itemset-resolve");
+        let data = root().unwrap().join("data");
+        for dir in [
+            "Local Storage/leveldb",
+            "Extensions/abc/1.0",
+            "Extension State",
+        ] {
+            fs::create_dir_all(data.join(dir)).unwrap();
+        }
+        fs::write(data.join("Cookies"), "").unwrap();
+
+        let items = set(
+            "custom",
+            vec![
+                item("Cookies", true),
+                item("Local Storage/leveldb", false),
+                item("Local Storage", false),
+                item("Extension*", false),
+                item("Login Data", true),
+            ],
+        );
+        let resolved = resolve(&items, &data).unwrap();
+        assert_eq!(resolved.set_id.as_deref(), Some("custom"));
+        assert_eq!(
+            resolved.roots,
+            ["Cookies", "Extension State", "Extensions", "Local Storage"]
+        );
+        assert_eq!(resolved.missing, ["Login Data"]);
+        assert_eq!(
+            resolved.ensure_complete().unwrap_err().key,
+            "backup.missingRequired"
+        );
+    }
+
+    #[test]
+    fn invalid_sets_are_rejected() {
+        let cases = [
+            (
+                vec![set(" ", vec![item("Cookies", false)])],
+                "itemSet.emptyId",
+            ),
+            (
+                vec![set(BUILTIN_SET_ID, vec![item("Cookies", false)])],
+                "itemSet.duplicateId",
+            ),
+            (
+                vec![
+                    set("a", vec![item("Cookies", false)]),
+                    set("a", vec![item("Cookies", false)]),
+                ],
+                "itemSet.duplicateId",
+            ),
+            (vec![set("a", Vec::new())], "itemSet.empty"),
+            (
+                vec![set("a", vec![item("../Cookies", false)])],
+                "itemSet.notRelative",
+            ),
+            (
+                vec![set("a", vec![item("/Cookies", false)])],
+                "itemSet.notRelative",
+            ),
+            (
+                vec![set("a", vec![item("Local Storage/[", false)])],
+                "itemSet.invalidPattern",
+            ),
+        ];
+        for (sets, key) in cases {
+            assert_eq!(validate_sets(&sets).unwrap_err().key, key);
+        }
+        assert!(validate_sets(&[set("a", vec![item("Local Storage/**/*.ldb", true)])]).is_ok());
+    }
+
+    #[test]
+    fn user_set_takes_precedence_over_default_set() {
+        let _root = TestRoot::new("itemset-user");
+        save_users(&["a", "b"], None);
+        let mut settings = load_settings().unwrap();
+        settings.backup_item_sets = vec![
+            set("minimal", vec![item("Cookies", true)]),
+            set(
+                "full",
+                vec![item("Cookies", true), item("IndexedDB", false)],
+            ),
+        ];
+        settings.default_backup_item_set = Some("full".to_string());
+        save_settings(&settings).unwrap();
+
+        set_user_backup_item_set("a".to_string(), Some("minimal".to_string())).unwrap();
+        assert_eq!(set_for_user(Some("a")).id, "minimal");
+        assert_eq!(set_for_user(Some("b")).id, "full");
+        assert_eq!(
+            set_user_backup_item_set("a".to_string(), Some("gone".to_string()))
+                .unwrap_err()
+                .key,
+            "itemSet.notFound"
+        );
+
+        // 集合被删除后退回到默认集合
+        settings.backup_item_sets.retain(|s| s.id != "minimal");
+        save_settings(&settings).unwrap();
+        assert_eq!(set_for_user(Some("a")).id, "full");
+        settings.default_backup_item_set = None;
+        save_settings(&settings).unwrap();
+        assert_eq!(set_for_user(Some("a")).id, BUILTIN_SET_ID);
+    }
+
+    #[test]
+    fn version_set_is_chosen_from_the_detected_version() {
+        let _root = TestRoot::new("itemset-version");
+        save_users(&["a", "b"], None);
+        let bundle = root().unwrap().join("RoxyBrowser.app");
+        fs::create_dir_all(bundle.join("Contents")).unwrap();
+        let install = |version: &str| {
+            let plist = format!(
+                "<plist><dict><key>CFBundleShortVersionString</key><string>{}</string></dict></plist>",
+                version
+            );
+            fs::write(bundle.join("Contents/Info.plist"), plist).unwrap();
+        };
+        let version_set = |version: &str, set_id: &str| VersionItemSet {
+            version: version.to_string(),
+            set_id: set_id.to_string(),
+        };
+
+        let mut settings = load_settings().unwrap();
+        settings.roxy_exe_path = Some(bundle.to_string_lossy().to_string());
+        settings.backup_item_sets = vec![
+            set("v3", vec![item("Cookies", true)]),
+            set("v35", vec![item("Cookies", true), item("IndexedDB", false)]),
+            set(
+                "full",
+                vec![item("Cookies", true), item("Local Storage", false)],
+            ),
+        ];
+        settings.default_backup_item_set = Some("full".to_string());
+        settings.version_backup_item_sets = vec![version_set("3", "v3"), version_set("3.5", "v35")];
+        save_settings(&settings).unwrap();
+
+        install("3.5.2");
+        assert_eq!(set_for_user(Some("a")).id, "v35");
+        install("3.50.1");
+        assert_eq!(set_for_user(Some("a")).id, "v3");
+        install("4.0.0");
+        assert_eq!(set_for_user(Some("a")).id, "full");
+
+        // 用户单独指定的集合优先于按版本选择的集合
+        install("3.5.2");
+        set_user_backup_item_set("b".to_string(), Some("full".to_string())).unwrap();
+        assert_eq!(set_for_user(Some("b")).id, "full");
+
+        let exists = |id: &str| id == "v3";
+        let cases = [
+            (vec![version_set("3.x", "v3")], "itemSet.invalidVersion"),
+            (
+                vec![version_set("3", "v3"), version_set(" 3", "v3")],
+                "itemSet.invalidVersion",
+            ),
+            (vec![version_set("3", "gone")], "itemSet.notFound"),
+        ];
+        for (version_sets, key) in cases {
+            assert_eq!(
+                validate_version_sets(&version_sets, exists)
+                    .unwrap_err()
+                    .key,
+                key
+            );
+        }
+        assert!(validate_version_sets(&[version_set("3.5", "v3")], exists).is_ok());
+    }
+
+    #[test]
+    fn exclude_patterns_match_relative_paths() {
+        let filter = ExcludeFilter::new(&[
+            "**/LOCK".to_string(),
+            "*.log".to_string(),
+            "Cache".to_string(),
+        ])
+        .unwrap();
+        assert!(filter.is_excluded("LOCK"));
+        assert!(filter.is_excluded("Local Storage/leveldb/LOCK"));
+        assert!(filter.is_excluded("debug.log"));
+        // `*` 不跨越目录
+        assert!(!filter.is_excluded("Local Storage/leveldb/000003.log"));
+        assert!(!filter.is_excluded("Cache/data_0"));
+        assert!(filter.covers("Cache/data_0"));
+        assert!(!filter.covers("Code Cache/js/index"));
+
+        let err = ExcludeFilter::new(&["Local Storage/[".to_string()]).unwrap_err();
+        assert_eq!(err.key, "itemSet.invalidPattern");
+    }
+
+    #[test]
+    fn backup_skips_excluded_paths() {
+        use crate::commands::profile::{backup_roxy_data, get_roxy_data_dir};
+        use crate::commands::store::load_manifest;
+
+        let _root = TestRoot::new("itemset-exclude");
+        let mut settings = load_settings().unwrap();
+        let mut custom = set(
+            "custom",
+            vec![item("Local Storage", true), item("IndexedDB", false)],
+        );
+        custom.exclude = vec!["IndexedDB/*.blob".to_string()];
+        settings.backup_item_sets = vec![custom];
+        settings.default_backup_item_set = Some("custom".to_string());
+        save_settings(&settings).unwrap();
+
+        let live = get_roxy_data_dir();
+        for dir in [
+            "Local Storage/leveldb",
+            "Local Storage/Cache",
+            "IndexedDB/site.blob",
+        ] {
+            fs::create_dir_all(live.join(dir)).unwrap();
+        }
+        for file in [
+            "Local Storage/leveldb/000003.log",
+            "Local Storage/leveldb/LOCK",
+            "Local Storage/Cache/data_0",
+        ] {
+            fs::write(live.join(file), file).unwrap();
+        }
+        backup_roxy_data("a").unwrap();
+
+        let manifest = load_manifest("a").unwrap().unwrap();
+        let files: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(files, ["Local Storage/leveldb/000003.log"]);
+        let mut skipped: Vec<(&str, bool)> = manifest
+            .skipped
+            .iter()
+            .map(|s| (s.path.as_str(), s.is_dir))
+            .collect();
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            [
+                ("IndexedDB/site.blob", true),
+                ("Local Storage/Cache", true),
+                ("Local Storage/leveldb/LOCK", false),
+            ]
+        );
+    }
+}
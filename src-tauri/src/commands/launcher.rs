@@ -0,0 +1,543 @@
+use super::settings::{get_enhanced_default_paths, load_settings};
+use crate::error::{AppError, ErrorCode};
+use crate::models::user::LaunchConfig;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+/// RoxyBrowser 的启动方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchTarget {
+    /// 直接运行的可执行文件（包括 AppImage）
+    Executable(PathBuf),
+    /// macOS 应用包，通过 `open -a` 启动
+    AppBundle(PathBuf),
+    /// .desktop 文件中的 Exec 命令（包括 Flatpak 导出的启动项）
+    DesktopExec { program: String, args: Vec<String> },
+}
+
+impl LaunchTarget {
+    /// 主进程的可执行文件；AppImage 和 .desktop 启动的程序无法事先确定
+    pub fn main_executable(&self) -> Option<PathBuf> {
+        match self {
+            LaunchTarget::Executable(path) if !is_appimage(path) => Some(path.clone()),
+            LaunchTarget::AppBundle(bundle) => {
+                // 以 Info.plist 中的 CFBundleExecutable 为准，与应用包名不一定相同
+                let name = read_bundle_info(bundle, "CFBundleExecutable")
+                    .filter(|name| !name.contains('/') && name != "..")
+                    .map(PathBuf::from)
+                    .or_else(|| bundle.file_stem().map(PathBuf::from))?;
+                Some(bundle.join("Contents").join("MacOS").join(name))
+            }
+            _ => None,
+        }
+    }
+
+    /// RoxyBrowser 的版本号：应用包读取 Info.plist，可执行文件读取安装目录中的 Electron 应用包，
+    /// 读不到时取文件名中的版本号（如 `RoxyBrowser-3.6.2.AppImage`）
+    pub fn version(&self) -> Option<String> {
+        match self {
+            LaunchTarget::AppBundle(bundle) => {
+                read_bundle_info(bundle, "CFBundleShortVersionString")
+                    .or_else(|| read_bundle_info(bundle, "CFBundleVersion"))
+            }
+            LaunchTarget::Executable(path) => executable_version(path),
+            // Flatpak 等通过命令名启动的程序无法确定安装位置
+            LaunchTarget::DesktopExec { program, .. } => {
+                let path = Path::new(program);
+                path.is_absolute()
+                    .then(|| executable_version(path))
+                    .flatten()
+            }
+        }
+    }
+
+    /// 是否像是 RoxyBrowser：文件名、应用包名、Bundle ID 或启动命令中包含 roxy
+    pub fn is_roxy(&self) -> bool {
+        let file_name = |path: &Path| {
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        };
+        let names = match self {
+            LaunchTarget::Executable(path) => vec![file_name(path)],
+            LaunchTarget::AppBundle(bundle) => {
+                let mut names = vec![file_name(bundle)];
+                names.extend(read_bundle_info(bundle, "CFBundleIdentifier"));
+                names.extend(read_bundle_info(bundle, "CFBundleExecutable"));
+                names
+            }
+            LaunchTarget::DesktopExec { program, args } => {
+                std::iter::once(program).chain(args).cloned().collect()
+            }
+        };
+        names
+            .iter()
+            .any(|name| name.to_lowercase().contains("roxy"))
+    }
+
+    /// 按启动配置启动，返回直接启动的子进程（`open -a` 启动时子进程不是 RoxyBrowser 本身，返回 `None`）
+    pub fn spawn(&self, config: &LaunchConfig) -> Result<Option<Child>, AppError> {
+        let spawn_error = |e: std::io::Error| {
+            AppError::new(ErrorCode::RoxyStartFailed, "roxy.spawnFailed").caused_by(e)
+        };
+        let configure = |command: &mut Command, default_dir: Option<&Path>| {
+            command.args(&config.args).envs(&config.env);
+            if let Some(dir) = config.working_dir.as_deref().map(Path::new).or(default_dir) {
+                command.current_dir(dir);
+            }
+        };
+
+        match self {
+            LaunchTarget::Executable(path) => {
+                let mut command = Command::new(path);
+                configure(&mut command, path.parent());
+                command.spawn().map(Some).map_err(spawn_error)
+            }
+            // `open` 无法传递环境变量和工作目录，此时直接运行应用包中的主程序
+            LaunchTarget::AppBundle(_)
+                if !config.env.is_empty() || config.working_dir.is_some() =>
+            {
+                let exe = self.main_executable().ok_or_else(|| {
+                    AppError::new(ErrorCode::RoxyStartFailed, "roxy.bundleExecutableUnknown")
+                })?;
+                let mut command = Command::new(&exe);
+                configure(&mut command, None);
+                command.spawn().map(Some).map_err(spawn_error)
+            }
+            LaunchTarget::AppBundle(bundle) => {
+                let mut command = Command::new("open");
+                command.arg("-a").arg(bundle);
+                if !config.args.is_empty() {
+                    command.arg("--args").args(&config.args);
+                }
+                let mut child = command.spawn().map_err(spawn_error)?;
+                // `open` 会立即退出，等待它以免留下僵尸进程
+                let status = child.wait().map_err(spawn_error)?;
+                if !status.success() {
+                    return Err(AppError::new(ErrorCode::RoxyStartFailed, "roxy.openFailed")
+                        .with("status", status));
+                }
+                Ok(None)
+            }
+            LaunchTarget::DesktopExec { program, args } => {
+                let mut command = Command::new(program);
+                command.args(args);
+                configure(&mut command, None);
+                command.spawn().map(Some).map_err(spawn_error)
+            }
+        }
+    }
+}
+
+fn is_appimage(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("appimage"))
+}
+
+#[cfg(unix)]
+fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn is_executable_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+}
+
+/// 读取应用包 Info.plist 中的字符串值，只支持 XML 格式
+fn read_bundle_info(bundle: &Path, key: &str) -> Option<String> {
+    let content = fs::read_to_string(bundle.join("Contents").join("Info.plist")).ok()?;
+    let key_tag = format!("<key>{}</key>", key);
+    let rest = content[content.find(&key_tag)? + key_tag.len()..].trim_start();
+    let value = rest
+        .strip_prefix("<string>")?
+        .split_once("</string>")?
+        .0
+        .trim();
+    let value = value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    (!value.is_empty()).then_some(value)
+}
+
+/// asar 头部与 package.json 的大小上限，超过时不是正常的应用包
+const MAX_ASAR_HEADER: usize = 16 * 1024 * 1024;
+const MAX_PACKAGE_JSON: u64 = 1024 * 1024;
+
+/// 读取 Electron 应用包 `app.asar` 中 package.json 的版本号
+///
+/// asar 开头是 Pickle 封装的 JSON 文件表，文件内容紧跟在文件表之后，偏移量相对这里计算。
+fn read_asar_version(asar: &Path) -> Option<String> {
+    let mut file = fs::File::open(asar).ok()?;
+    let mut prefix = [0u8; 16];
+    file.read_exact(&mut prefix).ok()?;
+    let u32_at = |at: usize| {
+        u32::from_le_bytes([prefix[at], prefix[at + 1], prefix[at + 2], prefix[at + 3]])
+    };
+    let header_size = u32_at(4) as u64;
+    let json_len = u32_at(12) as usize;
+    if json_len > MAX_ASAR_HEADER {
+        return None;
+    }
+
+    let mut json = vec![0u8; json_len];
+    file.read_exact(&mut json).ok()?;
+    let header: serde_json::Value = serde_json::from_slice(&json).ok()?;
+    let entry = &header["files"]["package.json"];
+    let size = entry["size"]
+        .as_u64()
+        .filter(|size| *size <= MAX_PACKAGE_JSON)?;
+    let offset: u64 = entry["offset"].as_str()?.parse().ok()?;
+
+    file.seek(SeekFrom::Start(8 + header_size + offset)).ok()?;
+    let mut package = Vec::new();
+    file.take(size).read_to_end(&mut package).ok()?;
+    let package: serde_json::Value = serde_json::from_slice(&package).ok()?;
+    package["version"].as_str().map(str::to_string)
+}
+
+/// 文件名中形如 `3.6.2` 的版本号
+fn version_in_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    name.split(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|part| part.trim_matches('.'))
+        .find(|part| part.contains('.') && part.split('.').all(|n| !n.is_empty()))
+        .map(str::to_string)
+}
+
+/// 可执行文件所在安装目录中的版本号（链接到安装目录的启动脚本先解析到真实位置）
+fn executable_version(path: &Path) -> Option<String> {
+    let real = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    real.parent()
+        .and_then(|dir| read_asar_version(&dir.join("resources").join("app.asar")))
+        .or_else(|| version_in_name(path))
+}
+
+/// 把 Exec 行拆分为参数，去掉 `%U` 等占位符
+fn parse_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            '\\' if in_quotes => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+
+    args.into_iter()
+        .filter(|arg| !(arg.len() == 2 && arg.starts_with('%')))
+        .collect()
+}
+
+/// 读取 .desktop 文件 `[Desktop Entry]` 中的 Exec 命令
+fn read_desktop_exec(path: &Path) -> Option<LaunchTarget> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut in_entry = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if let Some(exec) = line.strip_prefix("Exec=").filter(|_| in_entry) {
+            let mut args = parse_exec(exec);
+            if args.is_empty() {
+                return None;
+            }
+            let program = args.remove(0);
+            return Some(LaunchTarget::DesktopExec { program, args });
+        }
+    }
+    None
+}
+
+/// 把用户选择的路径转换为启动方式，不是可启动的文件时返回 `None`
+pub fn target_for_path(path: &Path) -> Option<LaunchTarget> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("app") if path.join("Contents").is_dir() => {
+            Some(LaunchTarget::AppBundle(path.to_path_buf()))
+        }
+        Some("desktop") if path.is_file() => read_desktop_exec(path),
+        _ if is_executable_file(path) => Some(LaunchTarget::Executable(path.to_path_buf())),
+        _ => None,
+    }
+}
+
+/// 可能包含 RoxyBrowser 启动项的 .desktop 目录（包括 Flatpak 导出目录）
+fn desktop_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(data) = dirs::data_dir() {
+        dirs.push(data.join("applications"));
+        dirs.push(data.join("flatpak/exports/share/applications"));
+    }
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share/applications"));
+    dirs.push(PathBuf::from("/usr/local/share/applications"));
+    dirs.push(PathBuf::from("/usr/share/applications"));
+    dirs
+}
+
+/// 文件名包含 roxybrowser 的 .desktop 启动项
+fn desktop_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in desktop_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                let name = p
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase();
+                name.contains("roxybrowser") && name.ends_with(".desktop")
+            })
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    files
+}
+
+/// PATH 中的 RoxyBrowser
+fn path_executables() -> Vec<PathBuf> {
+    let names: &[&str] = if cfg!(target_os = "windows") {
+        &["RoxyBrowser.exe"]
+    } else {
+        &["roxybrowser", "RoxyBrowser"]
+    };
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    std::env::split_paths(&path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .filter(|candidate| is_executable_file(candidate))
+        .collect()
+}
+
+/// 自动检测到的可用路径：默认安装位置、.desktop 启动项（Linux）、PATH
+pub fn detect_paths() -> Vec<PathBuf> {
+    let mut paths = get_enhanced_default_paths();
+    if cfg!(target_os = "linux") {
+        paths.extend(desktop_files());
+    }
+    paths.extend(path_executables());
+
+    let mut unique: Vec<PathBuf> = Vec::with_capacity(paths.len());
+    for path in paths {
+        if target_for_path(&path).is_some() && !unique.contains(&path) {
+            unique.push(path);
+        }
+    }
+    unique
+}
+
+/// 按优先级列出全部可用的启动方式：用户配置的路径优先，其次是自动检测到的路径
+pub fn candidates() -> Vec<LaunchTarget> {
+    let configured = load_settings()
+        .ok()
+        .and_then(|s| s.roxy_exe_path)
+        .map(PathBuf::from);
+
+    configured
+        .into_iter()
+        .chain(detect_paths())
+        .filter_map(|path| target_for_path(&path))
+        .collect()
+}
+
+/// 解析出实际使用的启动方式
+pub fn resolve() -> Result<LaunchTarget, AppError> {
+    candidates()
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::new(ErrorCode::RoxyNotFound, "roxy.notFound"))
+}
+
+/// 实际使用的 RoxyBrowser 的版本号，无法确定时返回 `None`
+pub fn detect_version() -> Option<String> {
+    resolve().ok()?.version()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::{root, TestRoot};
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_exec_handles_quotes_and_placeholders() {
+        assert_eq!(
+            parse_exec("/opt/RoxyBrowser/roxybrowser %U"),
+            strings(&["/opt/RoxyBrowser/roxybrowser"])
+        );
+        assert_eq!(
+            parse_exec(r#""/opt/Roxy Browser/roxybrowser" --no-sandbox  %f"#),
+            strings(&["/opt/Roxy Browser/roxybrowser", "--no-sandbox"])
+        );
+        assert_eq!(
+            parse_exec(r#"env NAME="a \"b\"" roxybrowser """#),
+            strings(&["env", r#"NAME=a "b""#, "roxybrowser", ""])
+        );
+        assert_eq!(
+            parse_exec("/usr/bin/flatpak run --command=roxybrowser com.roxybrowser.App @@u %u @@"),
+            strings(&[
+                "/usr/bin/flatpak",
+                "run",
+                "--command=roxybrowser",
+                "com.roxybrowser.App",
+                "@@u",
+                "@@"
+            ])
+        );
+        assert!(parse_exec("   ").is_empty());
+    }
+
+    #[test]
+    fn reads_bundle_info_values() {
+        let _root = TestRoot::new("launcher-bundle");
+        let bundle = root().unwrap().join("Roxy.app");
+        fs::create_dir_all(bundle.join("Contents")).unwrap();
+        fs::write(
+            bundle.join("Contents/Info.plist"),
+            "<plist><dict>\n\t<key>CFBundleExecutable</key>\n\t<string>RoxyBrowser</string>\n\
+             \t<key>CFBundleIdentifier</key><string>com.roxy &amp; co</string>\n\
+             \t<key>CFBundleName</key><string> </string>\n</dict></plist>",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_bundle_info(&bundle, "CFBundleExecutable").as_deref(),
+            Some("RoxyBrowser")
+        );
+        assert_eq!(
+            read_bundle_info(&bundle, "CFBundleIdentifier").as_deref(),
+            Some("com.roxy & co")
+        );
+        assert_eq!(read_bundle_info(&bundle, "CFBundleName"), None);
+        assert_eq!(read_bundle_info(&bundle, "CFBundleVersion"), None);
+
+        let target = LaunchTarget::AppBundle(bundle.clone());
+        assert_eq!(
+            target.main_executable(),
+            Some(bundle.join("Contents/MacOS/RoxyBrowser"))
+        );
+        assert!(target.is_roxy());
+    }
+
+    #[test]
+    fn reads_exec_from_the_desktop_entry_section() {
+        let _root = TestRoot::new("launcher-desktop");
+        let path = root().unwrap().join("roxybrowser.desktop");
+        fs::write(
+            &path,
+            "[Desktop Action new-window]\nExec=/opt/RoxyBrowser/roxybrowser --new-window\n\n\
+             [Desktop Entry]\nName=RoxyBrowser\nExec=/opt/RoxyBrowser/roxybrowser --no-sandbox %U\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_desktop_exec(&path),
+            Some(LaunchTarget::DesktopExec {
+                program: "/opt/RoxyBrowser/roxybrowser".to_string(),
+                args: strings(&["--no-sandbox"]),
+            })
+        );
+
+        fs::write(&path, "[Desktop Entry]\nName=RoxyBrowser\nExec=%U\n").unwrap();
+        assert_eq!(read_desktop_exec(&path), None);
+        fs::write(&path, "[Desktop Action a]\nExec=roxybrowser\n").unwrap();
+        assert_eq!(read_desktop_exec(&path), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn target_for_path_recognizes_launchable_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _root = TestRoot::new("launcher-target");
+        let dir = root().unwrap();
+        let write = |name: &str, mode: u32| {
+            let path = dir.join(name);
+            fs::write(&path, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            path
+        };
+
+        let exe = write("roxybrowser", 0o755);
+        assert_eq!(
+            target_for_path(&exe),
+            Some(LaunchTarget::Executable(exe.clone()))
+        );
+        let appimage = write("RoxyBrowser-3.6.2.AppImage", 0o755);
+        let target = target_for_path(&appimage).unwrap();
+        assert_eq!(target.main_executable(), None);
+        assert!(target.is_roxy());
+        assert_eq!(target_for_path(&write("roxybrowser.sh", 0o644)), None);
+        assert_eq!(target_for_path(&dir.join("missing")), None);
+
+        let bundle = dir.join("RoxyBrowser.app");
+        fs::create_dir_all(&bundle).unwrap();
+        assert_eq!(target_for_path(&bundle), None);
+        fs::create_dir_all(bundle.join("Contents")).unwrap();
+        assert_eq!(
+            target_for_path(&bundle),
+            Some(LaunchTarget::AppBundle(bundle.clone()))
+        );
+
+        let desktop = dir.join("roxybrowser.desktop");
+        fs::write(
+            &desktop,
+            "[Desktop Entry]\nExec=flatpak run com.roxybrowser.App\n",
+        )
+        .unwrap();
+        assert_eq!(
+            target_for_path(&desktop),
+            Some(LaunchTarget::DesktopExec {
+                program: "flatpak".to_string(),
+                args: strings(&["run", "com.roxybrowser.App"]),
+            })
+        );
+        assert!(!LaunchTarget::Executable(write("chrome", 0o755)).is_roxy());
+    }
+}
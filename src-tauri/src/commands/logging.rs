@@ -0,0 +1,494 @@
+use super::crypto::{self, SecretKey};
+use super::operation::{running_operation, OperationInfo};
+use super::process::{get_roxy_status, RoxyStatus};
+use super::profile::{get_app_config_dir, load_config};
+use super::settings::load_settings;
+use super::watchdog::{get_crash_log, CrashRecord};
+use crate::error::{AppError, ErrorCode};
+use crate::models::user::UserProfile;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// 日志文件名前缀，按天滚动为 `roxy-manager.YYYY-MM-DD.log`
+const LOG_FILE_PREFIX: &str = "roxy-manager";
+/// 最多保留的日志文件数（天）
+const MAX_LOG_FILES: usize = 7;
+/// 默认返回的日志条数
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// 运行时修改日志级别
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// 日志级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LogLevel {
+    #[serde(rename = "error")]
+    Error,
+    #[serde(rename = "warn")]
+    Warn,
+    #[default]
+    #[serde(rename = "info")]
+    Info,
+    #[serde(rename = "debug")]
+    Debug,
+    #[serde(rename = "trace")]
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+
+    fn parse(level: &str) -> Option<LogLevel> {
+        match level.to_ascii_uppercase().as_str() {
+            "ERROR" => Some(LogLevel::Error),
+            "WARN" => Some(LogLevel::Warn),
+            "INFO" => Some(LogLevel::Info),
+            "DEBUG" => Some(LogLevel::Debug),
+            "TRACE" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+}
+
+/// 一条日志
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// 所在的操作，例如 `switch_user`
+    pub operation: Option<String>,
+    /// 其余结构化字段：用户、耗时（`time.busy`）、文件数、字节数、错误等
+    pub fields: Map<String, Value>,
+}
+
+pub fn get_log_dir() -> PathBuf {
+    get_app_config_dir().join("logs")
+}
+
+/// 初始化日志：以 JSON 行写入按天滚动的文件，每个操作结束时记录耗时
+pub fn init() -> Result<(), AppError> {
+    let level = load_settings().unwrap_or_default().log_level;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(get_log_dir())
+        .map_err(|e| AppError::new(ErrorCode::Io, "log.createFile").caused_by(e))?;
+
+    let (filter, handle) = reload::Layer::new(level.filter());
+    let file_layer = tracing_subscriber::fmt::layer()
+        .json()
+        .with_writer(appender)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_current_span(true)
+        .with_span_list(false);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .try_init()
+        .map_err(|e| AppError::new(ErrorCode::Internal, "log.init").caused_by(e))?;
+
+    let _ = LEVEL_HANDLE.set(handle);
+    Ok(())
+}
+
+/// 日志文件无法使用时改为输出到标准错误，日志级别仍可在运行时修改
+pub fn init_stderr() {
+    let level = load_settings().unwrap_or_default().log_level;
+    let (filter, handle) = reload::Layer::new(level.filter());
+    let stderr_layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    if tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr_layer)
+        .try_init()
+        .is_ok()
+    {
+        let _ = LEVEL_HANDLE.set(handle);
+    }
+}
+
+/// 立即应用新的日志级别
+pub fn set_level(level: LogLevel) {
+    if let Some(handle) = LEVEL_HANDLE.get() {
+        let _ = handle.reload(level.filter());
+    }
+}
+
+/// 日志文件，最新的在前
+fn log_files() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(get_log_dir()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(LOG_FILE_PREFIX) && name.ends_with(".log")
+        })
+        .collect();
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// 解析一行 JSON 日志，span 中的字段（例如用户）并入 `fields`
+fn parse_entry(line: &str) -> Option<LogEntry> {
+    let Value::Object(mut value) = serde_json::from_str::<Value>(line).ok()? else {
+        return None;
+    };
+    let text = |value: &mut Map<String, Value>, key: &str| match value.remove(key) {
+        Some(Value::String(s)) => s,
+        _ => String::new(),
+    };
+
+    let mut fields = match value.remove("fields") {
+        Some(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+    let message = text(&mut fields, "message");
+    let mut operation = None;
+    if let Some(Value::Object(mut span)) = value.remove("span") {
+        if let Some(Value::String(name)) = span.remove("name") {
+            operation = Some(name);
+        }
+        for (key, field) in span {
+            fields.entry(key).or_insert(field);
+        }
+    }
+
+    Some(LogEntry {
+        timestamp: text(&mut value, "timestamp"),
+        level: text(&mut value, "level"),
+        target: text(&mut value, "target"),
+        message,
+        operation,
+        fields,
+    })
+}
+
+/// Tauri 命令: 获取最近的日志（最新的在前），可只返回不低于 `level` 的日志
+#[tauri::command]
+pub fn get_recent_logs(
+    limit: Option<usize>,
+    level: Option<LogLevel>,
+) -> Result<Vec<LogEntry>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LOG_LIMIT);
+    let mut entries = Vec::new();
+
+    for path in log_files() {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AppError::new(ErrorCode::Io, "log.read").caused_by(e))?;
+        for entry in content.lines().rev().filter_map(parse_entry) {
+            let included = match (level, LogLevel::parse(&entry.level)) {
+                (Some(min), Some(entry_level)) => entry_level <= min,
+                _ => true,
+            };
+            if included {
+                entries.push(entry);
+            }
+            if entries.len() >= limit {
+                return Ok(entries);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// 把用户目录替换为 `~`，避免诊断包中出现本机用户名
+fn sanitize(text: &str) -> String {
+    let Some(home) = dirs::home_dir() else {
+        return text.to_string();
+    };
+    let home = home.to_string_lossy().to_string();
+    if home.len() <= 1 {
+        return text.to_string();
+    }
+    // JSON 中的 Windows 路径反斜杠会被转义
+    text.replace(&home.replace('\\', "\\\\"), "~")
+        .replace(&home, "~")
+}
+
+/// 日志字段中表示用户 ID 或邮箱的字段名
+const USER_FIELDS: &[&str] = &["user", "email"];
+
+/// 把诊断包中的用户 ID 和邮箱替换为别名
+///
+/// 别名由每个诊断包随机生成的密钥做 HMAC 得到：同一诊断包内同一用户的别名相同，便于对照日志，
+/// 但无法反推出原值，不同诊断包之间也无法关联。
+struct UserRedactor {
+    key: SecretKey,
+    /// 配置中已有的用户 ID 和邮箱（较长的在前），出现在日志正文或错误信息中时也会被替换
+    known: Vec<String>,
+}
+
+impl UserRedactor {
+    fn new(users: &[UserProfile]) -> Self {
+        let mut known: Vec<String> = users
+            .iter()
+            .flat_map(|user| [user.id.clone(), user.email.clone()])
+            // 过短的 ID 可能与普通文本重合，只在日志字段中替换
+            .filter(|value| value.contains('@') || value.chars().count() >= 8)
+            .collect();
+        known.sort_by_key(|value| std::cmp::Reverse(value.len()));
+        known.dedup();
+        UserRedactor {
+            key: crypto::generate_key(),
+            known,
+        }
+    }
+
+    fn alias(&self, value: &str) -> String {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(&self.key).expect("HMAC 接受任意长度的密钥");
+        mac.update(value.to_lowercase().as_bytes());
+        format!("user-{}", hex::encode(&mac.finalize().into_bytes()[..4]))
+    }
+
+    fn redact_fields(&self, fields: &mut Map<String, Value>) {
+        for name in USER_FIELDS {
+            if let Some(Value::String(value)) = fields.get_mut(*name) {
+                *value = self.alias(value);
+            }
+        }
+    }
+
+    /// 替换一行日志：JSON 日志中事件和 span 的用户字段，以及任意位置出现的已知用户 ID 和邮箱
+    fn redact_line(&self, line: &str) -> String {
+        let mut line = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(mut value)) => {
+                for key in ["fields", "span"] {
+                    if let Some(Value::Object(fields)) = value.get_mut(key) {
+                        self.redact_fields(fields);
+                    }
+                }
+                serde_json::to_string(&value).unwrap_or_else(|_| line.to_string())
+            }
+            _ => line.to_string(),
+        };
+        for value in &self.known {
+            if line.contains(value.as_str()) {
+                line = line.replace(value.as_str(), &self.alias(value));
+            }
+        }
+        line
+    }
+
+    fn redact(&self, text: &str) -> String {
+        let mut redacted: Vec<String> = text.lines().map(|line| self.redact_line(line)).collect();
+        if text.ends_with('\n') {
+            redacted.push(String::new());
+        }
+        redacted.join("\n")
+    }
+}
+
+/// 去掉设置中的敏感内容：启动配置中的环境变量值
+fn sanitized_settings() -> Result<String, AppError> {
+    let settings = load_settings()?;
+    let mut value = serde_json::to_value(&settings)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "config.serialize").caused_by(e))?;
+    if let Some(Value::Object(env)) = value.pointer_mut("/defaultLaunchConfig/env") {
+        for field in env.values_mut() {
+            *field = Value::String("<redacted>".to_string());
+        }
+    }
+    let content = serde_json::to_string_pretty(&value)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "config.serialize").caused_by(e))?;
+    Ok(sanitize(&content))
+}
+
+/// 诊断包中的运行环境与状态
+#[derive(Debug, Serialize)]
+struct DiagnosticsSummary {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    #[serde(rename = "generatedAt")]
+    generated_at: String,
+    #[serde(rename = "userCount")]
+    user_count: usize,
+    #[serde(rename = "roxyStatus")]
+    roxy_status: RoxyStatus,
+    #[serde(rename = "runningOperation")]
+    running_operation: Option<OperationInfo>,
+    #[serde(rename = "recentCrashes")]
+    recent_crashes: Vec<CrashRecord>,
+}
+
+/// 诊断包路径：已带 `.zip` 扩展名时直接使用，否则视为目录并生成文件名
+fn resolve_diagnostics_path(export_path: &str) -> Result<PathBuf, AppError> {
+    let path = PathBuf::from(export_path);
+    let is_zip = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false);
+    let dir = if is_zip {
+        path.parent()
+    } else {
+        Some(path.as_path())
+    };
+    if let Some(dir) = dir.filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .map_err(|e| AppError::new(ErrorCode::Io, "export.createDir").caused_by(e))?;
+    }
+    if is_zip {
+        return Ok(path);
+    }
+    let file_name = format!(
+        "roxybrowser-manager-diagnostics-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok(path.join(file_name))
+}
+
+fn write_diagnostics(path: &Path) -> Result<(), AppError> {
+    let write_error =
+        |e: &dyn std::fmt::Display| AppError::new(ErrorCode::Io, "diagnostics.write").caused_by(e);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // 用户列表决定哪些邮箱会被替换，读不到时不能导出未脱敏的日志
+    let users = load_config()?.users;
+    let redactor = UserRedactor::new(&users);
+    let summary = DiagnosticsSummary {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        user_count: users.len(),
+        roxy_status: get_roxy_status(),
+        running_operation: running_operation(),
+        recent_crashes: get_crash_log(Some(20)).unwrap_or_default(),
+    };
+    let summary = serde_json::to_string_pretty(&summary)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "diagnostics.write").caused_by(e))?;
+
+    let file = fs::File::create(path).map_err(|e| write_error(&e))?;
+    let mut zip = ZipWriter::new(file);
+    let mut add = |name: &str, content: &str| -> Result<(), AppError> {
+        zip.start_file(name, options).map_err(|e| write_error(&e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| write_error(&e))
+    };
+    add("summary.json", &redactor.redact(&sanitize(&summary)))?;
+    // 设置无法读取时写入错误说明，不影响诊断包中的其他内容
+    let settings = sanitized_settings().unwrap_or_else(|e| {
+        sanitize(&format!(
+            "{:#}",
+            serde_json::json!({ "error": e.to_string() })
+        ))
+    });
+    add("settings.json", &settings)?;
+    for log in log_files() {
+        let name = log
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let content = fs::read_to_string(&log)
+            .map_err(|e| AppError::new(ErrorCode::Io, "log.read").caused_by(e))?;
+        add(
+            &format!("logs/{}", name),
+            &redactor.redact(&sanitize(&content)),
+        )?;
+    }
+    zip.finish().map_err(|e| write_error(&e))?;
+    Ok(())
+}
+
+/// Tauri 命令: 导出诊断包（日志、去除敏感内容的设置、进程状态），返回生成的文件路径
+#[tauri::command]
+pub fn export_diagnostics(export_path: String) -> Result<String, AppError> {
+    let path = resolve_diagnostics_path(&export_path)?;
+    if let Err(e) = write_diagnostics(&path) {
+        fs::remove_file(&path).ok();
+        return Err(e);
+    }
+    tracing::info!(path = %path.display(), "已导出诊断包");
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::{root, user, TestRoot};
+    use std::io::Read;
+
+    #[test]
+    fn diagnostics_logs_hide_user_ids_and_emails() {
+        let mut alice = user("alice-0001");
+        alice.email = "alice@example.com".to_string();
+        let redactor = UserRedactor::new(&[alice, user("b")]);
+        let log = concat!(
+            r#"{"fields":{"message":"已切换用户","user":"b"},"span":{"name":"switch_user","user":"alice@example.com"}}"#,
+            "\n",
+            r#"{"fields":{"message":"备份失败","error":"profiles/alice-0001 无法写入","email":"Alice@Example.com"}}"#,
+            "\n",
+        );
+
+        let redacted = redactor.redact(log);
+        assert!(redacted.ends_with('\n'));
+        for secret in [
+            "alice-0001",
+            "alice@example.com",
+            "Alice@Example.com",
+            r#""user":"b""#,
+        ] {
+            assert!(!redacted.contains(secret), "{} 未被替换", secret);
+        }
+        let alias = redactor.alias("alice@example.com");
+        assert_eq!(redacted.matches(&alias).count(), 2);
+        assert_eq!(
+            parse_entry(redacted.lines().next().unwrap())
+                .unwrap()
+                .operation
+                .as_deref(),
+            Some("switch_user")
+        );
+    }
+
+    #[test]
+    fn diagnostics_are_exported_when_settings_cannot_be_read() {
+        let _root = TestRoot::new("diagnostics-settings");
+        let settings_dir = root().unwrap().join("roxybrowser-manager");
+        fs::create_dir_all(&settings_dir).unwrap();
+        fs::write(settings_dir.join("settings.json"), "{ not json").unwrap();
+        fs::create_dir_all(get_app_config_dir()).unwrap();
+
+        let path = get_app_config_dir().join("diagnostics.zip");
+        write_diagnostics(&path).unwrap();
+        let mut zip = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+        assert!(zip.by_name("summary.json").is_ok());
+        let mut settings = String::new();
+        zip.by_name("settings.json")
+            .unwrap()
+            .read_to_string(&mut settings)
+            .unwrap();
+        let settings: Value = serde_json::from_str(&settings).unwrap();
+        assert!(settings["error"].is_string());
+    }
+}
@@ -1,3 +1,20 @@
+pub mod archive;
+pub mod crypto;
+pub mod itemset;
+pub mod launcher;
+pub mod logging;
+pub mod operation;
 pub mod process;
 pub mod profile;
+pub mod progress;
 pub mod settings;
+pub mod snapshot;
+pub mod store;
+pub mod switch;
+#[cfg(test)]
+pub mod testing;
+pub mod tray;
+pub mod vault;
+pub mod verify;
+pub mod watchdog;
+pub mod watcher;
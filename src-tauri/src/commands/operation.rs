@@ -0,0 +1,328 @@
+use super::profile::get_app_config_dir;
+use super::progress;
+use crate::error::{AppError, ErrorCode};
+use crate::i18n;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// 本进程正在执行的操作
+static CURRENT: Mutex<Option<OperationInfo>> = Mutex::new(None);
+
+/// 当前操作是否已被请求取消
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 操作说明：消息目录中的键和参数，显示时按当前语言渲染
+#[derive(Debug, Clone)]
+pub struct Description {
+    key: &'static str,
+    params: BTreeMap<String, String>,
+}
+
+impl Description {
+    pub fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// 添加消息参数
+    pub fn with(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// 正在执行的操作，同时写入锁文件，防止另一个管理器实例同时操作同一目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationInfo {
+    /// 操作编号，与进度事件中的 `operationId` 相同
+    #[serde(default)]
+    pub id: String,
+    /// 操作类型，例如 `switch_user`
+    pub kind: String,
+    /// 按当前语言渲染的操作说明，例如“切换到 a@example.com”
+    pub description: String,
+    /// 操作说明的消息键，旧版本写入的锁文件中没有
+    #[serde(rename = "descriptionKey", default)]
+    pub description_key: String,
+    #[serde(rename = "descriptionParams", default)]
+    pub description_params: BTreeMap<String, String>,
+    /// 执行操作的管理器进程
+    pub pid: u32,
+    #[serde(rename = "startedAt")]
+    pub started_at: String,
+    /// 是否可以通过 `cancel_operation` 取消
+    #[serde(default)]
+    pub cancellable: bool,
+}
+
+impl OperationInfo {
+    /// 按当前语言重新渲染操作说明；锁文件可能由使用其他语言的实例写入
+    fn localized(mut self) -> Self {
+        if !self.description_key.is_empty() {
+            let params = self
+                .description_params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()));
+            self.description = i18n::render(&self.description_key, params);
+        }
+        self
+    }
+}
+
+/// 操作锁，离开作用域时释放
+#[derive(Debug)]
+pub struct OperationGuard {
+    _private: (),
+}
+
+impl OperationGuard {
+    /// 操作已被取消时，把底层返回的错误统一为取消错误
+    pub fn finish<T>(&self, result: Result<T, AppError>) -> Result<T, AppError> {
+        match result {
+            Err(_) if is_cancelled() => Err(AppError::cancelled()),
+            result => result,
+        }
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+        *current = None;
+        CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+        let _ = fs::remove_file(get_lock_path());
+    }
+}
+
+fn get_lock_path() -> PathBuf {
+    get_app_config_dir().join("operation.lock")
+}
+
+fn read_lock_file() -> Option<OperationInfo> {
+    let content = fs::read_to_string(get_lock_path()).ok()?;
+    serde_json::from_str::<OperationInfo>(&content)
+        .ok()
+        .map(OperationInfo::localized)
+}
+
+fn is_process_alive(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), ProcessRefreshKind::new());
+    sys.process(pid).is_some()
+}
+
+fn busy_error(info: &OperationInfo) -> AppError {
+    let info = info.clone().localized();
+    if info.pid == std::process::id() {
+        AppError::new(ErrorCode::OperationBusy, "operation.busy")
+            .with("description", &info.description)
+            .with("startedAt", &info.started_at)
+    } else {
+        AppError::new(ErrorCode::OperationBusy, "operation.busyOther")
+            .with("pid", info.pid)
+            .with("description", &info.description)
+    }
+}
+
+/// 创建锁文件；文件已存在但持有者已经退出时视为残留并替换
+fn create_lock_file(info: &OperationInfo) -> Result<(), AppError> {
+    let path = get_lock_path();
+    fs::create_dir_all(get_app_config_dir())
+        .map_err(|e| AppError::new(ErrorCode::Io, "config.createDir").caused_by(e))?;
+    let content = serde_json::to_string_pretty(info)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "operation.serialize").caused_by(e))?;
+
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                return file
+                    .write_all(content.as_bytes())
+                    .map_err(|e| AppError::new(ErrorCode::Io, "operation.writeLock").caused_by(e));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                if let Some(holder) = read_lock_file() {
+                    if holder.pid != std::process::id() && is_process_alive(holder.pid) {
+                        return Err(busy_error(&holder));
+                    }
+                }
+                let _ = fs::remove_file(&path);
+            }
+            Err(e) => return Err(AppError::new(ErrorCode::Io, "operation.createLock").caused_by(e)),
+        }
+    }
+    Err(AppError::new(
+        ErrorCode::OperationBusy,
+        "operation.lockFailed",
+    ))
+}
+
+/// 开始一个会修改 RoxyBrowser 目录或备份的操作，已有操作进行中时返回说明该操作的错误
+pub fn begin(kind: &str, description: Description) -> Result<OperationGuard, AppError> {
+    start(kind, description, false)
+}
+
+/// 开始一个可以取消的操作（导入、导出）
+///
+/// 操作应在处理每个文件前调用 [`check_cancelled`]，并在取消后清理已写入的内容。
+pub fn begin_cancellable(kind: &str, description: Description) -> Result<OperationGuard, AppError> {
+    start(kind, description, true)
+}
+
+fn start(
+    kind: &str,
+    description: Description,
+    cancellable: bool,
+) -> Result<OperationGuard, AppError> {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(ref running) = *current {
+        return Err(busy_error(running));
+    }
+
+    let info = OperationInfo {
+        // 在后台执行的操作沿用进度事件的编号，前端可据此取消
+        id: progress::current_operation_id().unwrap_or_else(progress::new_operation_id),
+        kind: kind.to_string(),
+        description: String::new(),
+        description_key: description.key.to_string(),
+        description_params: description.params,
+        pid: std::process::id(),
+        started_at: Utc::now().to_rfc3339(),
+        cancellable,
+    }
+    .localized();
+    create_lock_file(&info)?;
+    CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+    *current = Some(info);
+    Ok(OperationGuard { _private: () })
+}
+
+/// 当前操作是否已被请求取消
+pub fn is_cancelled() -> bool {
+    CANCEL_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// 当前操作已被请求取消时返回取消错误
+pub fn check_cancelled() -> Result<(), AppError> {
+    if is_cancelled() {
+        Err(AppError::cancelled())
+    } else {
+        Ok(())
+    }
+}
+
+/// 当前正在执行的操作（包括其他管理器实例）
+pub fn running_operation() -> Option<OperationInfo> {
+    let current = CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    current.map(OperationInfo::localized).or_else(|| {
+        read_lock_file().filter(|info| info.pid != std::process::id() && is_process_alive(info.pid))
+    })
+}
+
+/// Tauri 命令: 获取正在执行的操作
+#[tauri::command]
+pub fn get_running_operation() -> Option<OperationInfo> {
+    running_operation()
+}
+
+/// Tauri 命令: 取消正在执行的导入或导出，操作会在处理下一个文件前停止并清理已写入的内容
+#[tauri::command]
+pub fn cancel_operation(operation_id: String) -> Result<(), AppError> {
+    let current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(info) = current.as_ref().filter(|info| info.id == operation_id) else {
+        return Err(AppError::new(
+            ErrorCode::OperationNotFound,
+            "operation.notFound",
+        ));
+    };
+    if !info.cancellable {
+        let info = info.clone().localized();
+        return Err(
+            AppError::new(ErrorCode::NotCancellable, "operation.notCancellable")
+                .with("description", &info.description),
+        );
+    }
+    CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+    tracing::info!(operation = %info.kind, id = %info.id, "已请求取消操作");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::TestRoot;
+    use crate::i18n::{set_current_language, Language};
+
+    fn switch_to(user: &str) -> Description {
+        Description::new("activity.switchUser").with("user", user)
+    }
+
+    #[test]
+    fn second_operation_is_rejected_with_localized_description() {
+        let _root = TestRoot::new("operation-busy");
+        let operation = begin("switch_user", switch_to("a@x.com")).unwrap();
+        assert!(get_lock_path().exists());
+
+        let e = begin(
+            "delete_user",
+            Description::new("activity.deleteUser").with("user", "b"),
+        )
+        .unwrap_err();
+        assert_eq!(e.key, "operation.busy");
+        assert_eq!(e.params["description"], "切换到 a@x.com");
+
+        set_current_language(Language::EnUs);
+        let running = running_operation().unwrap();
+        let e = cancel_operation(running.id.clone()).unwrap_err();
+        set_current_language(Language::ZhCn);
+        assert_eq!(running.description, "Switch to a@x.com");
+        assert_eq!(e.params["description"], "Switch to a@x.com");
+
+        drop(operation);
+        assert!(!get_lock_path().exists());
+        assert!(running_operation().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lock_held_by_another_live_process() {
+        let _root = TestRoot::new("operation-other");
+        fs::create_dir_all(get_app_config_dir()).unwrap();
+        let mut other = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let holder = OperationInfo {
+            id: "x".to_string(),
+            kind: "import_profiles".to_string(),
+            description: String::new(),
+            description_key: "activity.importProfiles".to_string(),
+            description_params: BTreeMap::new(),
+            pid: other.id(),
+            started_at: String::new(),
+            cancellable: true,
+        };
+        fs::write(get_lock_path(), serde_json::to_string(&holder).unwrap()).unwrap();
+
+        let e = begin("switch_user", switch_to("a")).unwrap_err();
+        assert_eq!(e.key, "operation.busyOther");
+        assert_eq!(e.params["description"], "导入用户配置");
+        assert_eq!(running_operation().unwrap().pid, other.id());
+
+        // 持有者退出后残留的锁文件被替换
+        other.kill().unwrap();
+        other.wait().unwrap();
+        assert!(running_operation().is_none());
+        drop(begin("switch_user", switch_to("a")).unwrap());
+    }
+}
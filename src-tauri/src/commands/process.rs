@@ -1,134 +1,750 @@
+use super::launcher;
+use super::operation::Description;
+use super::profile::{launch_config_for, load_config};
+use super::settings::{load_settings, StartupSettings};
+use super::{operation, progress};
+use crate::error::{AppError, ErrorCode};
+use crate::models::user::LaunchConfig;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
-use sysinfo::System;
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use sysinfo::Signal;
+use sysinfo::{
+    Pid, Process, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System, UpdateKind,
+};
+use tauri::AppHandle;
+
+/// RoxyBrowser 进程信息
+#[derive(Debug, Clone, Serialize)]
+pub struct RoxyProcess {
+    pub pid: u32,
+    #[serde(rename = "parentPid")]
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    pub exe: Option<String>,
+    /// 常驻内存（字节）
+    pub memory: u64,
+    /// CPU 占用（百分比，多核时可能超过 100）
+    #[serde(rename = "cpuUsage")]
+    pub cpu_usage: f32,
+    /// 启动时间（Unix 时间戳，秒）
+    #[serde(rename = "startTime")]
+    pub start_time: u64,
+}
+
+/// 浏览器环境内核进程（RoxyBrowser 为每个打开的环境启动的浏览器）
+#[derive(Debug, Clone, Serialize)]
+pub struct RoxyKernel {
+    #[serde(flatten)]
+    pub process: RoxyProcess,
+    /// 内核自身的子进程（渲染、GPU 等）
+    pub helpers: Vec<RoxyProcess>,
+}
+
+/// RoxyBrowser 进程树
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RoxyProcessTree {
+    /// 主进程
+    pub main: Option<RoxyProcess>,
+    /// 主进程的辅助子进程（不含内核）
+    pub children: Vec<RoxyProcess>,
+    pub kernels: Vec<RoxyKernel>,
+}
+
+impl RoxyProcessTree {
+    /// 树中全部进程
+    pub fn pids(&self) -> Vec<Pid> {
+        self.all().map(|p| Pid::from_u32(p.pid)).collect()
+    }
+
+    fn all(&self) -> impl Iterator<Item = &RoxyProcess> {
+        let kernels = self
+            .kernels
+            .iter()
+            .flat_map(|k| std::iter::once(&k.process).chain(&k.helpers));
+        self.main.iter().chain(&self.children).chain(kernels)
+    }
+
+    /// 需要请求正常退出的进程：主进程和各内核，辅助进程随之退出
+    fn root_pids(&self) -> Vec<Pid> {
+        self.main
+            .iter()
+            .chain(self.kernels.iter().map(|k| &k.process))
+            .map(|p| Pid::from_u32(p.pid))
+            .collect()
+    }
+}
 
 #[derive(Debug, Serialize)]
 pub struct RoxyStatus {
     #[serde(rename = "isRunning")]
     pub is_running: bool,
     pub pid: Option<u32>,
+    /// 主进程的可执行文件路径
+    #[serde(rename = "exePath")]
+    pub exe_path: Option<String>,
+    /// 主进程启动时间
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<String>,
+    #[serde(rename = "uptimeSecs")]
+    pub uptime_secs: Option<u64>,
+    /// 整个进程树的常驻内存合计（字节）
+    #[serde(rename = "memoryBytes")]
+    pub memory_bytes: u64,
+    /// 整个进程树的 CPU 占用合计（百分比）
+    #[serde(rename = "cpuUsage")]
+    pub cpu_usage: f32,
+    /// 打开的浏览器环境内核数量
+    #[serde(rename = "kernelCount")]
+    pub kernel_count: usize,
+    #[serde(flatten)]
+    pub processes: RoxyProcessTree,
 }
 
 const ROXY_PROCESS_NAME: &str = "RoxyBrowser";
 
-/// 获取 RoxyBrowser 运行状态
-#[tauri::command]
-pub fn get_roxy_status() -> RoxyStatus {
+/// 状态查询复用的进程列表：CPU 占用需要与上一次刷新的结果比较
+static STATUS_SYSTEM: Mutex<Option<System>> = Mutex::new(None);
+
+/// 由本程序直接启动的 RoxyBrowser 进程，退出后用于获取退出码并回收
+static SPAWNED: Mutex<Vec<Child>> = Mutex::new(Vec::new());
+
+/// 是否由本程序请求停止，用于区分主动停止与 RoxyBrowser 自行退出
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 本程序启动 RoxyBrowser 后置位，主动停止时清除；自动重启前据此判断等待期间是否被主动停止
+static LAUNCHED_BY_MANAGER: AtomicBool = AtomicBool::new(false);
+
+/// 本程序启动、还没有被后台检测对应到主进程的 RoxyBrowser
+static LAUNCH_UNCLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// 已解析的 RoxyBrowser 主程序路径，避免每次查找进程都重新探测安装位置；启动设置变化时清空
+static ROXY_EXECUTABLES: Mutex<Option<Vec<PathBuf>>> = Mutex::new(None);
+
+/// 刷新进程列表，包含识别进程所需的可执行文件路径和命令行
+pub fn refresh(sys: &mut System) {
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        ProcessRefreshKind::new()
+            .with_memory()
+            .with_cpu()
+            .with_exe(UpdateKind::OnlyIfNotSet)
+            .with_cmd(UpdateKind::OnlyIfNotSet),
+    );
+}
+
+pub fn refresh_processes() -> System {
     let mut sys = System::new();
-    sys.refresh_processes(sysinfo::ProcessesToUpdate::All);
-
-    for (pid, process) in sys.processes() {
-        let name = process.name().to_string_lossy();
-        if name.contains(ROXY_PROCESS_NAME) {
-            return RoxyStatus {
-                is_running: true,
-                pid: Some(pid.as_u32()),
-            };
+    refresh(&mut sys);
+    sys
+}
+
+/// 进程是否已经退出（僵尸进程：由本程序直接启动、已退出但尚未回收的 RoxyBrowser）
+pub fn is_exited(process: &Process) -> bool {
+    process.status() == ProcessStatus::Zombie
+}
+
+/// 统一路径形式以便比较
+fn normalize_exe(path: &Path) -> PathBuf {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    #[cfg(target_os = "windows")]
+    let path = PathBuf::from(path.to_string_lossy().to_lowercase());
+    path
+}
+
+/// RoxyBrowser 主程序的可执行文件（AppImage 等无法确定主程序的启动方式不包含在内）
+///
+/// 结果会缓存；没有找到时不缓存，以便之后安装的 RoxyBrowser 能被识别。
+fn roxy_executables() -> Vec<PathBuf> {
+    roxy_executables_from(launcher::candidates)
+}
+
+/// 没有缓存时从 `candidates` 给出的启动方式中解析主程序路径
+fn roxy_executables_from(candidates: impl FnOnce() -> Vec<launcher::LaunchTarget>) -> Vec<PathBuf> {
+    let mut cached = ROXY_EXECUTABLES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(executables) = cached.as_ref() {
+        return executables.clone();
+    }
+    let executables: Vec<PathBuf> = candidates()
+        .iter()
+        .filter_map(|target| target.main_executable())
+        .filter(|path| path.is_file())
+        .map(|path| normalize_exe(&path))
+        .collect();
+    if !executables.is_empty() {
+        *cached = Some(executables.clone());
+    }
+    executables
+}
+
+/// 清空已解析的主程序路径，RoxyBrowser 路径等启动设置变化后调用
+pub fn invalidate_roxy_executables() {
+    *ROXY_EXECUTABLES.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// 进程名是否就是 RoxyBrowser（无法读取可执行文件路径时使用）
+fn is_roxy_name(process: &Process) -> bool {
+    let name = process.name().to_string_lossy();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    name.eq_ignore_ascii_case(ROXY_PROCESS_NAME)
+}
+
+/// 是否是 Chromium/Electron 的辅助进程
+fn is_helper(process: &Process) -> bool {
+    process
+        .cmd()
+        .iter()
+        .any(|arg| arg.to_string_lossy().starts_with("--type="))
+}
+
+fn to_roxy_process(pid: Pid, process: &Process) -> RoxyProcess {
+    RoxyProcess {
+        pid: pid.as_u32(),
+        parent_pid: process.parent().map(|p| p.as_u32()),
+        name: process.name().to_string_lossy().to_string(),
+        exe: process.exe().map(|p| p.to_string_lossy().to_string()),
+        memory: process.memory(),
+        cpu_usage: process.cpu_usage(),
+        start_time: process.start_time(),
+    }
+}
+
+/// 查找 RoxyBrowser 进程树
+///
+/// 按可执行文件路径识别主程序（找不到安装位置时按进程名精确匹配），再沿父子关系收集子进程；
+/// 与父进程可执行文件不同且不是辅助进程的子进程视为内核。
+pub fn find_roxy_processes(sys: &System) -> RoxyProcessTree {
+    let executables = roxy_executables();
+    let own_pid = Pid::from_u32(std::process::id());
+    let exe_of = |process: &Process| process.exe().map(normalize_exe);
+    let is_roxy = |pid: &Pid, process: &Process| {
+        *pid != own_pid
+            && !is_exited(process)
+            && match exe_of(process) {
+                Some(exe) if !executables.is_empty() => executables.contains(&exe),
+                _ => is_roxy_name(process),
+            }
+    };
+
+    let processes = sys.processes();
+    let mut children_of: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for (pid, process) in processes {
+        if let Some(parent) = process.parent() {
+            children_of.entry(parent).or_default().push(*pid);
         }
     }
 
-    RoxyStatus {
-        is_running: false,
-        pid: None,
+    // 父进程不是 RoxyBrowser 的匹配进程为根，最早启动的作为主进程
+    let mut roots: Vec<(&Pid, &Process)> = processes
+        .iter()
+        .filter(|(pid, p)| is_roxy(pid, p))
+        .filter(|(_, p)| {
+            !p.parent()
+                .and_then(|parent| processes.get(&parent).map(|pp| (parent, pp)))
+                .is_some_and(|(parent, pp)| is_roxy(&parent, pp))
+        })
+        .collect();
+    roots.sort_by_key(|(pid, p)| (p.start_time(), pid.as_u32()));
+
+    let mut tree = RoxyProcessTree::default();
+    let mut seen = HashSet::new();
+    // (进程, 所属内核在 tree.kernels 中的位置)
+    let mut pending: Vec<(Pid, Option<usize>)> = Vec::new();
+    for (index, (pid, process)) in roots.into_iter().enumerate() {
+        seen.insert(*pid);
+        if index == 0 {
+            tree.main = Some(to_roxy_process(*pid, process));
+        } else {
+            tree.children.push(to_roxy_process(*pid, process));
+        }
+        pending.push((*pid, None));
+    }
+
+    while let Some((parent, kernel)) = pending.pop() {
+        let parent_exe = processes.get(&parent).and_then(exe_of);
+        for child in children_of.get(&parent).into_iter().flatten() {
+            let Some(process) = processes.get(child).filter(|p| !is_exited(p)) else {
+                continue;
+            };
+            if !seen.insert(*child) {
+                continue;
+            }
+            let info = to_roxy_process(*child, process);
+            let child_kernel = match kernel {
+                Some(index) => {
+                    tree.kernels[index].helpers.push(info);
+                    Some(index)
+                }
+                None if !is_helper(process) && exe_of(process) != parent_exe => {
+                    tree.kernels.push(RoxyKernel {
+                        process: info,
+                        helpers: Vec::new(),
+                    });
+                    Some(tree.kernels.len() - 1)
+                }
+                None => {
+                    tree.children.push(info);
+                    None
+                }
+            };
+            pending.push((*child, child_kernel));
+        }
     }
+
+    tree
 }
 
-/// 启动 RoxyBrowser
+/// 获取 RoxyBrowser 运行状态
 #[tauri::command]
-pub fn start_roxy() -> Result<(), String> {
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg("-a")
-            .arg("RoxyBrowser")
-            .spawn()
-            .map_err(|e| format!("无法启动 RoxyBrowser: {}", e))?;
+pub fn get_roxy_status() -> RoxyStatus {
+    let mut guard = STATUS_SYSTEM.lock().unwrap_or_else(|e| e.into_inner());
+    let sys = match guard.as_mut() {
+        Some(sys) => {
+            refresh(sys);
+            sys
+        }
+        None => {
+            // 第一次查询时没有可比较的数据，间隔一段时间再刷新一次
+            let mut sys = refresh_processes();
+            std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+            refresh(&mut sys);
+            guard.insert(sys)
+        }
+    };
+
+    let processes = find_roxy_processes(sys);
+    let main = processes.main.as_ref();
+    let uptime_secs = main.and_then(|m| sys.process(Pid::from_u32(m.pid)).map(|p| p.run_time()));
+    RoxyStatus {
+        is_running: main.is_some(),
+        pid: main.map(|p| p.pid),
+        exe_path: main.and_then(|p| p.exe.clone()),
+        started_at: main
+            .and_then(|p| DateTime::<Utc>::from_timestamp(p.start_time as i64, 0))
+            .map(|t| t.to_rfc3339()),
+        uptime_secs,
+        memory_bytes: processes.all().map(|p| p.memory).sum(),
+        cpu_usage: processes.all().map(|p| p.cpu_usage).sum(),
+        kernel_count: processes.kernels.len(),
+        processes,
     }
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        use crate::commands::settings::{load_settings, get_enhanced_default_paths};
-        
-        // 优先级1: 使用用户配置的自定义路径
-        if let Ok(settings) = load_settings() {
-            if let Some(custom_path) = settings.roxy_exe_path {
-                let path_buf = std::path::PathBuf::from(&custom_path);
-                if path_buf.exists() {
-                    Command::new(&path_buf)
-                        .spawn()
-                        .map_err(|e| format!("无法启动 RoxyBrowser: {}", e))?;
-                    return Ok(());
+/// 记录直接启动的进程
+fn track(child: Child) {
+    SPAWNED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(child);
+}
+
+/// 取出已退出的直接启动进程的退出状态，不是由本程序启动或仍在运行时返回 `None`
+pub fn take_exit_status(pid: u32) -> Option<ExitStatus> {
+    let mut spawned = SPAWNED.lock().unwrap_or_else(|e| e.into_inner());
+    let index = spawned.iter().position(|c| c.id() == pid)?;
+    spawned[index].try_wait().ok()??;
+    // 已经退出，wait 直接返回退出状态
+    spawned.remove(index).wait().ok()
+}
+
+/// RoxyBrowser 是否由本程序启动且没有被主动停止
+pub fn launched_by_manager() -> bool {
+    LAUNCHED_BY_MANAGER.load(Ordering::SeqCst)
+}
+
+/// 认领本程序最近一次启动，后台检测发现新的主进程时调用
+///
+/// 每次启动只对应一个主进程：它退出后用户自行启动的 RoxyBrowser 不会被当作本程序启动的。
+/// 之前的停止请求属于已经退出的进程，一并清除，新主进程之后的崩溃不会被当作主动停止。
+pub fn claim_launch() -> bool {
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    LAUNCH_UNCLAIMED.swap(false, Ordering::SeqCst)
+}
+
+/// 取出并清除“由本程序请求停止”的标记
+pub fn take_stop_request() -> bool {
+    STOP_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// 本地 API 端口是否可以连接
+fn api_port_open(port: u16) -> bool {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&addr, Duration::from_millis(500)).is_ok()
+}
+
+/// 等待 RoxyBrowser 就绪：主进程稳定运行一段时间，且本地 API 端口可以连接
+///
+/// `spawned` 为直接启动的进程，它在主进程出现之前异常退出时立即报错。
+pub fn wait_until_ready(startup: &StartupSettings, spawned: Option<u32>) -> Result<(), AppError> {
+    let timeout = Duration::from_secs(startup.ready_timeout_secs);
+    let stable_for = Duration::from_millis(startup.stable_ms);
+    let start = Instant::now();
+    // (主进程 PID, 启动时间, 首次发现的时间)
+    let mut main: Option<(u32, u64, Instant)> = None;
+
+    loop {
+        let tree = find_roxy_processes(&refresh_processes());
+        match (tree.main.as_ref(), main) {
+            (Some(m), Some((pid, start_time, _))) if m.pid == pid && m.start_time == start_time => {
+            }
+            // 单实例转交等情况下主进程会更换，重新计时
+            (Some(m), _) => main = Some((m.pid, m.start_time, Instant::now())),
+            (None, Some(_)) => {
+                return Err(AppError::new(
+                    ErrorCode::RoxyStartFailed,
+                    "roxy.exitedDuringStartup",
+                ))
+            }
+            (None, None) => {
+                if let Some(status) = spawned.and_then(take_exit_status).filter(|s| !s.success()) {
+                    return Err(
+                        AppError::new(ErrorCode::RoxyStartFailed, "roxy.startFailed")
+                            .with("status", status),
+                    );
                 }
             }
         }
-        
-        // 优先级2: 回退到增强的默认路径检测
-        let default_paths = get_enhanced_default_paths();
-        for path in &default_paths {
-            if path.exists() {
-                Command::new(&path)
-                    .spawn()
-                    .map_err(|e| format!("无法启动 RoxyBrowser: {}", e))?;
+
+        if let Some((_, _, since)) = main {
+            if since.elapsed() >= stable_for && api_port_open(startup.api_port) {
                 return Ok(());
             }
         }
-        
-        // 未找到任何有效路径
-        return Err("未找到 RoxyBrowser 安装路径。\n\n可能的原因：\n• RoxyBrowser 未安装在默认位置\n• 需要手动配置安装路径\n\n请在设置中手动指定可执行文件位置。".to_string());
-    }
 
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("roxybrowser")
-            .spawn()
-            .map_err(|e| format!("无法启动 RoxyBrowser: {}", e))?;
+        if start.elapsed() >= timeout {
+            return Err(match main {
+                Some(_) => AppError::new(ErrorCode::RoxyNotReady, "roxy.notReady")
+                    .with("seconds", startup.ready_timeout_secs)
+                    .with("port", startup.api_port),
+                None => AppError::new(ErrorCode::RoxyNotReady, "roxy.notDetected")
+                    .with("seconds", startup.ready_timeout_secs),
+            });
+        }
+        std::thread::sleep(Duration::from_millis(200));
     }
+}
+
+/// 启动 RoxyBrowser，使用当前用户的启动配置
+///
+/// `wait` 为 `None` 时按设置决定是否等待就绪，等待期间在后台执行。
+#[tauri::command]
+pub async fn start_roxy(app: AppHandle, wait: Option<bool>) -> Result<(), AppError> {
+    progress::run_blocking(app, "start_roxy", move || start_roxy_blocking(wait)).await
+}
+
+pub fn start_roxy_blocking(wait: Option<bool>) -> Result<(), AppError> {
+    let _operation = operation::begin("start_roxy", Description::new("activity.startRoxy"))?;
+    launch_roxy(wait)
+}
+
+/// 启动 RoxyBrowser，不获取操作锁，供已持有操作锁的切换、恢复等流程调用
+pub fn launch_roxy(wait: Option<bool>) -> Result<(), AppError> {
+    // 配置无法读取时仍允许启动，使用默认启动配置
+    let current = load_config().ok().and_then(|config| config.current_user);
+    launch_roxy_with(&launch_config_for(current.as_deref()), wait)
+}
+
+/// 使用指定的启动配置启动 RoxyBrowser，不获取操作锁
+#[tracing::instrument(name = "launch_roxy", skip(launch_config), err(Display))]
+pub fn launch_roxy_with(launch_config: &LaunchConfig, wait: Option<bool>) -> Result<(), AppError> {
+    let startup = load_settings().unwrap_or_default().startup;
+    let target = launcher::resolve()?;
+    // 启动前标记，后台检测发现主进程时一定能认领到
+    LAUNCH_UNCLAIMED.store(true, Ordering::SeqCst);
+    let spawned = target
+        .spawn(launch_config)
+        .inspect_err(|_| LAUNCH_UNCLAIMED.store(false, Ordering::SeqCst))?
+        .map(|child| {
+            let pid = child.id();
+            track(child);
+            pid
+        });
 
+    LAUNCHED_BY_MANAGER.store(true, Ordering::SeqCst);
+
+    if wait.unwrap_or(startup.wait_until_ready) {
+        wait_until_ready(&startup, spawned)?;
+    }
     Ok(())
 }
 
-/// 停止 RoxyBrowser
-#[tauri::command]
-pub fn stop_roxy() -> Result<(), String> {
-    let mut sys = System::new();
-    sys.refresh_processes(sysinfo::ProcessesToUpdate::All);
+/// 停止 RoxyBrowser 时实际用到的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StopStage {
+    /// 没有运行中的进程
+    #[serde(rename = "notRunning")]
+    NotRunning,
+    /// 进程在宽限期内正常退出
+    #[serde(rename = "graceful")]
+    Graceful,
+    /// 超过宽限期后被强制结束
+    #[serde(rename = "forced")]
+    Forced,
+}
 
-    let mut found = false;
-    for (_pid, process) in sys.processes() {
-        let name = process.name().to_string_lossy();
-        if name.contains(ROXY_PROCESS_NAME) {
-            process.kill();
-            found = true;
+/// 停止结果
+#[derive(Debug, Serialize)]
+pub struct StopReport {
+    pub stage: StopStage,
+    /// 开始停止时运行中的进程数量
+    pub processes: usize,
+    #[serde(rename = "elapsedMs")]
+    pub elapsed_ms: u64,
+}
+
+/// 请求进程正常退出：Unix 发送 SIGTERM，Windows 通过 taskkill（不带 /F）发送关闭消息
+#[cfg(unix)]
+fn request_exit(sys: &System, pids: &[Pid]) {
+    for pid in pids {
+        if let Some(process) = sys.process(*pid) {
+            process.kill_with(Signal::Term);
         }
     }
+}
+
+#[cfg(windows)]
+fn request_exit(_sys: &System, pids: &[Pid]) {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let mut command = Command::new("taskkill");
+    for pid in pids {
+        command.arg("/PID").arg(pid.to_string());
+    }
+    // 没有窗口的子进程无法正常关闭，会随主进程退出，忽略 taskkill 的失败
+    let _ = command.creation_flags(CREATE_NO_WINDOW).output();
+}
 
-    if !found {
-        return Ok(()); // 没有运行的进程，直接成功
+/// 仍在运行的进程：开始停止时记录的进程（主进程退出后子进程不再挂在树上）与当前进程树
+fn remaining_pids(pids: &[Pid]) -> (System, Vec<Pid>) {
+    let sys = refresh_processes();
+    let mut remaining: Vec<Pid> = pids
+        .iter()
+        .copied()
+        .filter(|pid| sys.process(*pid).is_some_and(|p| !is_exited(p)))
+        .collect();
+    for pid in find_roxy_processes(&sys).pids() {
+        if !remaining.contains(&pid) {
+            remaining.push(pid);
+        }
     }
+    (sys, remaining)
+}
 
-    // 轮询等待进程完全退出
-    let poll_interval = std::time::Duration::from_millis(100);
-    let max_wait = std::time::Duration::from_secs(10);
-    let start = std::time::Instant::now();
+/// 轮询等待这些进程和 RoxyBrowser 进程树全部退出，超时返回 false
+fn wait_for_exit(pids: &[Pid], timeout: Duration) -> bool {
+    let poll_interval = Duration::from_millis(100);
+    let start = Instant::now();
 
     loop {
+        if remaining_pids(pids).1.is_empty() {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            return false;
+        }
         std::thread::sleep(poll_interval);
+    }
+}
 
-        let mut sys = System::new();
-        sys.refresh_processes(sysinfo::ProcessesToUpdate::All);
+/// 停止 RoxyBrowser
+///
+/// 先请求正常退出，让 LevelDB 和 Cookies 数据库完成写入；超过宽限期仍未退出时再强制结束。
+#[tauri::command]
+pub async fn stop_roxy(app: AppHandle) -> Result<StopReport, AppError> {
+    progress::run_blocking(app, "stop_roxy", stop_roxy_blocking).await
+}
 
-        let still_running = sys.processes().values().any(|p| {
-            p.name().to_string_lossy().contains(ROXY_PROCESS_NAME)
-        });
+pub fn stop_roxy_blocking() -> Result<StopReport, AppError> {
+    let _operation = operation::begin("stop_roxy", Description::new("activity.stopRoxy"))?;
+    shutdown_roxy()
+}
+
+/// 停止 RoxyBrowser，不获取操作锁，供已持有操作锁的切换、恢复等流程调用
+#[tracing::instrument(ret(Debug), err(Display))]
+pub fn shutdown_roxy() -> Result<StopReport, AppError> {
+    let shutdown = load_settings().unwrap_or_default().shutdown;
+    let start = Instant::now();
+    let report = |stage, processes| StopReport {
+        stage,
+        processes,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    };
+
+    // 主动停止后不再自动重启，直到下一次由本程序启动
+    LAUNCHED_BY_MANAGER.store(false, Ordering::SeqCst);
+    LAUNCH_UNCLAIMED.store(false, Ordering::SeqCst);
+
+    let sys = refresh_processes();
+    let tree = find_roxy_processes(&sys);
+    let pids = tree.pids();
+    if pids.is_empty() {
+        return Ok(report(StopStage::NotRunning, 0)); // 没有运行的进程，直接成功
+    }
 
-        if !still_running {
-            return Ok(());
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+    request_exit(&sys, &tree.root_pids());
+    if wait_for_exit(&pids, Duration::from_secs(shutdown.grace_period_secs)) {
+        return Ok(report(StopStage::Graceful, pids.len()));
+    }
+
+    // 宽限期内没有退出，强制结束剩余进程
+    let (sys, remaining) = remaining_pids(&pids);
+    for pid in remaining {
+        if let Some(process) = sys.process(pid) {
+            process.kill();
         }
+    }
+    if wait_for_exit(&pids, Duration::from_secs(shutdown.kill_timeout_secs)) {
+        return Ok(report(StopStage::Forced, pids.len()));
+    }
+
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    Err(
+        AppError::new(ErrorCode::StopTimeout, "roxy.stopTimeout").with(
+            "seconds",
+            shutdown.grace_period_secs + shutdown.kill_timeout_secs,
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn probes_the_local_api_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(api_port_open(port));
+
+        drop(listener);
+        assert!(!api_port_open(port));
+    }
+
+    #[test]
+    fn claiming_a_new_main_process_clears_the_stop_request() {
+        let _root = crate::commands::testing::TestRoot::new("roxy-claim");
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+        LAUNCH_UNCLAIMED.store(true, Ordering::SeqCst);
+        assert!(claim_launch());
+        assert!(!take_stop_request());
+        assert!(!claim_launch());
+    }
+
+    #[test]
+    fn executables_are_cached_until_settings_change() {
+        use crate::commands::launcher::LaunchTarget;
+        use crate::commands::settings::{save_settings, AppSettings};
+        use crate::commands::testing::{root, TestRoot};
+
+        let _root = TestRoot::new("roxy-executables");
+        let dir = root().unwrap();
+        let first = dir.join("RoxyBrowser");
+        let second = dir.join("RoxyBrowser2");
+        std::fs::write(&first, "").unwrap();
+        std::fs::write(&second, "").unwrap();
+        let detections = std::cell::Cell::new(0);
+        let resolve = |path: &Path| {
+            roxy_executables_from(|| {
+                detections.set(detections.get() + 1);
+                vec![
+                    LaunchTarget::Executable(dir.join("missing")),
+                    LaunchTarget::Executable(path.to_path_buf()),
+                ]
+            })
+        };
+
+        invalidate_roxy_executables();
+        assert_eq!(resolve(&dir.join("missing")), Vec::<PathBuf>::new());
+        // 没有找到时不缓存
+        assert_eq!(resolve(&first), vec![normalize_exe(&first)]);
+        // 缓存命中时不再探测
+        assert_eq!(resolve(&second), vec![normalize_exe(&first)]);
+        assert_eq!(detections.get(), 2);
+
+        save_settings(&AppSettings::default()).unwrap();
+        assert_eq!(resolve(&second), vec![normalize_exe(&second)]);
+        assert_eq!(detections.get(), 3);
+        invalidate_roxy_executables();
+    }
+
+    /// 把系统程序复制为测试目录下的 RoxyBrowser 并设为唯一识别的主程序，不会碰到本机的其他进程
+    #[cfg(unix)]
+    fn fake_roxy(program: &str) -> PathBuf {
+        use crate::commands::launcher::LaunchTarget;
+
+        let exe = crate::commands::testing::root()
+            .unwrap()
+            .join("RoxyBrowser");
+        std::fs::copy(program, &exe).unwrap();
+        invalidate_roxy_executables();
+        roxy_executables_from(|| vec![LaunchTarget::Executable(exe.clone())]);
+        exe
+    }
 
-        if start.elapsed() >= max_wait {
-            return Err(
-                "RoxyBrowser 进程未能在 10 秒内退出，请手动关闭后重试".to_string()
+    /// 等待进程树中出现主进程和 `kernels` 个内核
+    #[cfg(unix)]
+    fn wait_for_tree(kernels: usize) {
+        let start = Instant::now();
+        loop {
+            let tree = find_roxy_processes(&refresh_processes());
+            if tree.main.is_some() && tree.kernels.len() == kernels {
+                return;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "RoxyBrowser 进程没有出现"
             );
+            std::thread::sleep(Duration::from_millis(50));
         }
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_escalates_only_when_sigterm_is_ignored() {
+        use crate::commands::settings::{save_settings, AppSettings};
+        use crate::commands::testing::TestRoot;
+        use std::process::Command;
+
+        let _root = TestRoot::new("roxy-shutdown");
+        let mut settings = AppSettings::default();
+        settings.shutdown.grace_period_secs = 1;
+        settings.shutdown.kill_timeout_secs = 1;
+        save_settings(&settings).unwrap();
+
+        let exe = fake_roxy("/bin/sleep");
+        assert_eq!(shutdown_roxy().unwrap().stage, StopStage::NotRunning);
+
+        // 响应 SIGTERM 的进程在宽限期内退出
+        let mut child = Command::new(&exe).arg("30").spawn().unwrap();
+        wait_for_tree(0);
+        let report = shutdown_roxy().unwrap();
+        assert_eq!((report.stage, report.processes), (StopStage::Graceful, 1));
+        child.wait().unwrap();
+
+        // 忽略 SIGTERM 的进程（连同它的子进程）在宽限期后被强制结束
+        let exe = fake_roxy("/bin/sh");
+        let mut child = Command::new(&exe)
+            .args(["-c", "trap '' TERM; sleep 30; exit 0"])
+            .spawn()
+            .unwrap();
+        wait_for_tree(1);
+        let report = shutdown_roxy().unwrap();
+        assert_eq!((report.stage, report.processes), (StopStage::Forced, 2));
+        assert!(report.elapsed_ms >= 1000);
+        child.wait().unwrap();
+
+        assert!(take_stop_request());
+        invalidate_roxy_executables();
+    }
 }
@@ -1,11 +1,25 @@
-use crate::models::user::{AppConfig, UserProfile};
+use super::operation::Description;
+use super::settings::load_settings;
+use super::{archive, crypto, itemset, operation, progress, snapshot, store, vault};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n;
+use crate::models::user::{AppConfig, LaunchConfig, UserProfile};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
 use chrono::Utc;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
 
 /// RoxyBrowser 数据目录
-fn get_roxy_data_dir() -> PathBuf {
+pub fn get_roxy_data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(root) = super::testing::root() {
+        return root.join("RoxyBrowser");
+    }
     #[cfg(target_os = "macos")]
     {
         dirs::home_dir()
@@ -28,12 +42,16 @@ fn get_roxy_data_dir() -> PathBuf {
 }
 
 /// 应用配置目录
-fn get_app_config_dir() -> PathBuf {
+pub fn get_app_config_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(root) = super::testing::root() {
+        return root.join(".roxy_manager");
+    }
     dirs::home_dir().unwrap().join(".roxy_manager")
 }
 
 /// 用户配置文件备份目录
-fn get_profiles_dir() -> PathBuf {
+pub fn get_profiles_dir() -> PathBuf {
     get_app_config_dir().join("profiles")
 }
 
@@ -42,81 +60,124 @@ fn get_config_path() -> PathBuf {
     get_app_config_dir().join("config.json")
 }
 
-/// 需要备份的文件和目录列表
-const BACKUP_ITEMS: &[&str] = &[
+/// 内置备份项集合包含的文件和目录
+pub const BACKUP_ITEMS: &[&str] = &[
     "config.json",
     "Local Storage",
     "Cookies",
+    // 未完成事务的回滚日志，必须与 Cookies 一起备份和切换
+    "Cookies-journal",
     "Session Storage",
     "IndexedDB",
 ];
 
-/// 加载应用配置
-fn load_config() -> AppConfig {
+/// 加载应用配置，文件不存在时返回默认配置
+///
+/// 读取或解析失败时报错，不能当作空配置：之后的保存会用空的用户列表覆盖原文件。
+pub fn load_config() -> Result<AppConfig, AppError> {
     let config_path = get_config_path();
-    if config_path.exists() {
-        let content = fs::read_to_string(&config_path).unwrap_or_default();
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        AppConfig::default()
+    if !config_path.exists() {
+        return Ok(AppConfig::default());
     }
+    let content = fs::read_to_string(&config_path).map_err(|e| {
+        AppError::new(ErrorCode::Io, "config.readUsers")
+            .with("path", config_path.display())
+            .caused_by(e)
+    })?;
+    let mut config: AppConfig = serde_json::from_str(&content).map_err(|e| {
+        AppError::new(ErrorCode::DataCorrupted, "config.usersCorrupt")
+            .with("path", config_path.display())
+            .caused_by(e)
+    })?;
+    config.fill_missing_ids();
+    Ok(config)
 }
 
 /// 保存应用配置
-fn save_config(config: &AppConfig) -> Result<(), String> {
+pub fn save_config(config: &AppConfig) -> Result<(), AppError> {
     let config_dir = get_app_config_dir();
     fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("无法创建配置目录: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Io, "config.createDir").caused_by(e))?;
     
     let config_path = get_config_path();
     let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("无法序列化配置: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Internal, "config.serialize").caused_by(e))?;
     
-    fs::write(&config_path, content)
-        .map_err(|e| format!("无法保存配置: {}", e))?;
+    write_file_atomic(&config_path, content.as_bytes())
+        .map_err(|e| AppError::new(ErrorCode::Io, "config.save").caused_by(e))?;
     
     Ok(())
 }
 
-/// 备份 RoxyBrowser 数据到指定用户目录
-fn backup_roxy_data(email: &str) -> Result<(), String> {
+/// 先写临时文件并落盘，再重命名覆盖目标，避免写到一半留下损坏文件
+pub fn write_file_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)
+}
+
+/// 备份 RoxyBrowser 数据到指定用户
+///
+/// 文件按内容存入 blob 存储，只写入发生变化的文件；每次备份生成一个新快照，
+/// 备份失败时旧清单保持不变。
+pub fn backup_roxy_data(user_id: &str) -> Result<(), AppError> {
+    backup_roxy_data_pinned(user_id, None)
+}
+
+/// 与 [`backup_roxy_data`] 相同，清理旧快照时额外保留 `pinned`（例如即将恢复的快照）
+#[tracing::instrument(name = "backup_roxy_data", skip_all, fields(user = %user_id), err(Display))]
+pub fn backup_roxy_data_pinned(user_id: &str, pinned: Option<&str>) -> Result<(), AppError> {
     let roxy_dir = get_roxy_data_dir();
-    let profile_dir = get_profiles_dir().join(email);
-    
-    fs::create_dir_all(&profile_dir)
-        .map_err(|e| format!("无法创建用户配置目录: {}", e))?;
-    
-    for item in BACKUP_ITEMS {
-        let src = roxy_dir.join(item);
-        let dst = profile_dir.join(item);
-        
-        if src.exists() {
-            if src.is_dir() {
-                copy_dir_all(&src, &dst)?;
-            } else {
-                fs::copy(&src, &dst)
-                    .map_err(|e| format!("无法复制 {}: {}", item, e))?;
-            }
-        }
+    let previous = store::load_manifest(user_id)?;
+    // 设置无法读取时不备份：按默认保留策略清理会删掉用户要求保留的快照
+    let retention = load_settings()?.snapshot_retention;
+
+    // 按用户的备份项集合确定备份范围，必需项缺失时不生成不完整的备份
+    let items = itemset::resolve(&itemset::set_for_user(Some(user_id)), &roxy_dir)?;
+    items.ensure_complete()?;
+
+    let manifest = store::capture(user_id, &roxy_dir, &items, previous.as_ref())?;
+    store::save_manifest(&manifest)?;
+    tracing::info!(
+        files = manifest.files.len(),
+        bytes = manifest.files.iter().map(|f| f.size).sum::<u64>(),
+        "已备份用户数据"
+    );
+
+    // 按保留策略清理旧快照；本次备份的内容都在新快照中，只有删除了旧快照才可能有 blob 不再被引用
+    if snapshot::prune_snapshots(user_id, &retention, pinned)? > 0 {
+        store::collect_garbage()?;
     }
     
     Ok(())
 }
 
-/// 恢复用户数据到 RoxyBrowser 目录
-fn restore_roxy_data(email: &str) -> Result<(), String> {
-    let roxy_dir = get_roxy_data_dir();
-    let profile_dir = get_profiles_dir().join(email);
+/// 恢复用户数据到指定目录（通常是切换事务的暂存目录）
+#[tracing::instrument(skip_all, fields(user = %user_id), err(Display))]
+pub fn restore_roxy_data(user_id: &str, target_dir: &Path) -> Result<(), AppError> {
+    let manifest = store::load_manifest(user_id)?.ok_or_else(|| {
+        AppError::new(ErrorCode::BackupMissing, "backup.notFound").with("user", user_id)
+    })?;
+    itemset::check_required(&manifest)?;
     
-    if !profile_dir.exists() {
-        return Err(format!("未找到用户配置: {}", email));
-    }
+    fs::create_dir_all(target_dir).map_err(|e| {
+        AppError::new(ErrorCode::Io, "store.createDir")
+            .with("path", target_dir.display())
+            .caused_by(e)
+    })?;
     
-    for item in BACKUP_ITEMS {
-        let src = profile_dir.join(item);
-        let dst = roxy_dir.join(item);
-        
-        // 先删除目标
+    // 先删除目标
+    for item in itemset::manifest_roots(&manifest) {
+        let dst = store::to_local_path(target_dir, &item);
         if dst.exists() {
             if dst.is_dir() {
                 fs::remove_dir_all(&dst).ok();
@@ -124,41 +185,22 @@ fn restore_roxy_data(email: &str) -> Result<(), String> {
                 fs::remove_file(&dst).ok();
             }
         }
-        
-        // 复制源
-        if src.exists() {
-            if src.is_dir() {
-                copy_dir_all(&src, &dst)?;
-            } else {
-                fs::copy(&src, &dst)
-                    .map_err(|e| format!("无法复制 {}: {}", item, e))?;
-            }
-        }
-    }
-    
-    Ok(())
-}
-
-/// 递归复制目录
-fn copy_dir_all(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    fs::create_dir_all(dst)
-        .map_err(|e| format!("无法创建目录 {:?}: {}", dst, e))?;
-    
-    for entry in fs::read_dir(src).map_err(|e| format!("无法读取目录: {}", e))? {
-        let entry = entry.map_err(|e| format!("读取目录项失败: {}", e))?;
-        let ty = entry.file_type().map_err(|e| format!("获取文件类型失败: {}", e))?;
-        
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-        
-        if ty.is_dir() {
-            copy_dir_all(&src_path, &dst_path)?;
-        } else {
-            fs::copy(&src_path, &dst_path)
-                .map_err(|e| format!("复制文件失败: {}", e))?;
-        }
     }
     
+    // 旧备份中现在已被排除的锁文件、缓存不再恢复
+    let exclude = itemset::ExcludeFilter::from_settings()?;
+    let skipped = store::materialize(&manifest, target_dir, &exclude)?;
+    let restored: Vec<_> = manifest
+        .files
+        .iter()
+        .filter(|f| !exclude.covers(&f.path))
+        .collect();
+    tracing::info!(
+        files = restored.len(),
+        bytes = restored.iter().map(|f| f.size).sum::<u64>(),
+        skipped = skipped.len(),
+        "已恢复用户数据"
+    );
     Ok(())
 }
 
@@ -192,7 +234,7 @@ fn read_current_email_from_roxy() -> Option<String> {
 }
 
 /// 从内容中提取邮箱地址
-fn extract_email(content: &str) -> Option<String> {
+pub fn extract_email(content: &str) -> Option<String> {
     // 尝试匹配 "userInfo":{"email":"xxx@xxx.com"} 格式
     if let Some(pos) = content.find("\"userInfo\":{\"email\":\"") {
         let start = pos + 21;
@@ -227,107 +269,200 @@ pub struct ListUsersResult {
 
 /// 获取用户列表
 #[tauri::command]
-pub fn list_users() -> ListUsersResult {
-    let config = load_config();
-    ListUsersResult {
+pub fn list_users() -> Result<ListUsersResult, AppError> {
+    let config = load_config()?;
+    Ok(ListUsersResult {
         users: config.users,
         current_user: config.current_user,
-    }
+    })
 }
 
-/// 切换用户
+/// 切换用户，在后台执行并推送备份与还原的进度
 #[tauri::command]
-pub fn switch_user(email: String) -> Result<(), String> {
-    let mut config = load_config();
+pub async fn switch_user(app: AppHandle, user_id: String) -> Result<(), AppError> {
+    progress::run_blocking(app, "switch_user", move || switch_user_blocking(user_id)).await
+}
+
+#[tracing::instrument(name = "switch_user", skip_all, fields(user = %user_id), err(Display))]
+pub fn switch_user_blocking(user_id: String) -> Result<(), AppError> {
+    let _operation = operation::begin(
+        "switch_user",
+        Description::new("activity.switchUser").with("user", &user_id),
+    )?;
+    let config = load_config()?;
     
     // 检查用户是否存在
-    let user_exists = config.users.iter().any(|u| u.email == email);
-    if !user_exists {
-        return Err(format!("用户 {} 不存在", email));
+    if config.find_user(&user_id).is_none() {
+        return Err(AppError::user_not_found(&user_id));
     }
     
+    // 本地备份已加密但未解锁时，在停止 RoxyBrowser 之前就报错
+    vault::ensure_unlocked()?;
+
     // 停止 RoxyBrowser
-    super::process::stop_roxy()?;
+    super::process::shutdown_roxy()?;
     
     // 保存当前用户数据
     if let Some(ref current) = config.current_user {
         backup_roxy_data(current)?;
     }
     
-    // 恢复目标用户数据
-    restore_roxy_data(&email)?;
-    
-    // 更新配置
-    config.current_user = Some(email.clone());
-    for user in &mut config.users {
-        if user.email == email {
-            user.last_used = Utc::now().to_rfc3339();
-        }
-    }
-    save_config(&config)?;
+    // 暂存目标用户数据并交换到 RoxyBrowser 目录，同时更新配置
+    super::switch::run_switch(config.current_user.clone(), &user_id)?;
     
     // 启动 RoxyBrowser
-    super::process::start_roxy()?;
+    super::process::launch_roxy(None)?;
     
     Ok(())
 }
 
 /// 删除用户
 #[tauri::command]
-pub fn delete_user(email: String) -> Result<(), String> {
-    let mut config = load_config();
+#[tracing::instrument(skip_all, fields(user = %user_id), err(Display))]
+pub fn delete_user(user_id: String) -> Result<(), AppError> {
+    store::ensure_safe_name(&user_id)?;
+    let _operation = operation::begin(
+        "delete_user",
+        Description::new("activity.deleteUser").with("user", &user_id),
+    )?;
+    let mut config = load_config()?;
     
     // 不能删除当前用户
-    if config.current_user.as_ref() == Some(&email) {
-        return Err("无法删除当前活动用户".to_string());
+    if config.current_user.as_ref() == Some(&user_id) {
+        return Err(AppError::new(ErrorCode::ActiveUser, "user.deleteActive"));
     }
     
     // 从配置中移除
-    config.users.retain(|u| u.email != email);
+    config.users.retain(|u| u.id != user_id);
     save_config(&config)?;
     
-    // 删除备份目录
-    let profile_dir = get_profiles_dir().join(&email);
-    if profile_dir.exists() {
-        fs::remove_dir_all(&profile_dir).ok();
-    }
+    // 删除备份清单并回收其独占的 blob
+    store::remove_manifest(&user_id)?;
+    store::collect_garbage()?;
     
     Ok(())
 }
 
 /// 更新用户备注
 #[tauri::command]
-pub fn update_user_note(email: String, note: String) -> Result<(), String> {
-    let mut config = load_config();
+pub fn update_user_note(user_id: String, note: String) -> Result<(), AppError> {
+    let _operation = operation::begin(
+        "update_user_note",
+        Description::new("activity.updateUserNote").with("user", &user_id),
+    )?;
+    let mut config = load_config()?;
     
     // 查找并更新用户备注
-    let user_found = config.users.iter_mut().find(|u| u.email == email);
+    let user_found = config.users.iter_mut().find(|u| u.id == user_id);
     if let Some(user) = user_found {
         user.note = note;
         save_config(&config)?;
         Ok(())
     } else {
-        Err(format!("用户 {} 不存在", email))
+        Err(AppError::user_not_found(&user_id))
+    }
+}
+
+/// 为用户单独指定备份项集合，传 `None` 恢复为默认集合
+#[tauri::command]
+pub fn set_user_backup_item_set(user_id: String, set_id: Option<String>) -> Result<(), AppError> {
+    if let Some(ref id) = set_id {
+        if itemset::find_set(id).is_none() {
+            return Err(
+                AppError::new(ErrorCode::ItemSetNotFound, "itemSet.notFound").with("id", id),
+            );
+        }
+    }
+
+    let _operation = operation::begin(
+        "set_user_backup_item_set",
+        Description::new("activity.setUserBackupItemSet").with("user", &user_id),
+    )?;
+    let mut config = load_config()?;
+    let user = config
+        .users
+        .iter_mut()
+        .find(|u| u.id == user_id)
+        .ok_or_else(|| AppError::user_not_found(&user_id))?;
+    user.backup_item_set = set_id;
+    save_config(&config)?;
+    Ok(())
+}
+
+/// 设置用户的启动配置，传 `None` 恢复为默认配置
+#[tauri::command]
+pub fn set_user_launch_config(
+    user_id: String,
+    launch_config: Option<LaunchConfig>,
+) -> Result<(), AppError> {
+    if let Some(ref launch_config) = launch_config {
+        launch_config.validate()?;
+    }
+
+    let _operation = operation::begin(
+        "set_user_launch_config",
+        Description::new("activity.setUserLaunchConfig").with("user", &user_id),
+    )?;
+    let mut config = load_config()?;
+    let user = config
+        .users
+        .iter_mut()
+        .find(|u| u.id == user_id)
+        .ok_or_else(|| AppError::user_not_found(&user_id))?;
+    user.launch_config = launch_config;
+    save_config(&config)?;
+    Ok(())
+}
+
+/// 用户实际使用的启动配置：默认配置叠加用户配置
+pub fn launch_config_for(user_id: Option<&str>) -> LaunchConfig {
+    let default = load_settings().unwrap_or_default().default_launch_config;
+    // 只用于启动，配置无法读取时使用默认启动配置
+    let user = user_id.and_then(|user_id| {
+        load_config()
+            .ok()?
+            .users
+            .into_iter()
+            .find(|u| u.id == user_id)
+            .and_then(|u| u.launch_config)
+    });
+    match user {
+        Some(user) => default.merged_with(&user),
+        None => default,
     }
 }
 
 /// 准备添加新用户（保存当前用户并清空登录状态）
 #[tauri::command]
-pub fn prepare_for_new_user() -> Result<(), String> {
-    let config = load_config();
+pub async fn prepare_for_new_user(app: AppHandle) -> Result<(), AppError> {
+    progress::run_blocking(app, "prepare_for_new_user", prepare_for_new_user_blocking).await
+}
+
+#[tracing::instrument(name = "prepare_for_new_user", skip_all, err(Display))]
+pub fn prepare_for_new_user_blocking() -> Result<(), AppError> {
+    let _operation = operation::begin(
+        "prepare_for_new_user",
+        Description::new("activity.prepareNewUser"),
+    )?;
+    let config = load_config()?;
+    vault::ensure_unlocked()?;
     
     // 停止 RoxyBrowser
-    super::process::stop_roxy()?;
+    super::process::shutdown_roxy()?;
     
     // 保存当前用户数据
     if let Some(ref current) = config.current_user {
         backup_roxy_data(current)?;
     }
     
-    // 清空 RoxyBrowser 登录数据
+    // 清空 RoxyBrowser 登录数据（只清理刚刚备份过的路径）
     let roxy_dir = get_roxy_data_dir();
-    for item in BACKUP_ITEMS {
-        let path = roxy_dir.join(item);
+    let items = itemset::resolve(
+        &itemset::set_for_user(config.current_user.as_deref()),
+        &roxy_dir,
+    )?;
+    for item in &items.roots {
+        let path = store::to_local_path(&roxy_dir, item);
         if path.exists() {
             if path.is_dir() {
                 fs::remove_dir_all(&path).ok();
@@ -337,128 +472,1290 @@ pub fn prepare_for_new_user() -> Result<(), String> {
         }
     }
     
-    // 启动 RoxyBrowser 供用户登录
-    super::process::start_roxy()?;
+    // 启动 RoxyBrowser 供用户登录；新用户还没有自己的启动配置，不能沿用上一个用户的
+    super::process::launch_roxy_with(&launch_config_for(None), None)?;
     
     Ok(())
 }
 
 /// 完成新用户添加（读取新用户信息并保存）
 #[tauri::command]
-pub fn finalize_new_user() -> Result<UserProfile, String> {
+pub async fn finalize_new_user(app: AppHandle) -> Result<UserProfile, AppError> {
+    progress::run_blocking(app, "finalize_new_user", finalize_new_user_blocking).await
+}
+
+#[tracing::instrument(name = "finalize_new_user", skip_all, err(Display))]
+pub fn finalize_new_user_blocking() -> Result<UserProfile, AppError> {
+    let _operation = operation::begin(
+        "finalize_new_user",
+        Description::new("activity.finalizeNewUser"),
+    )?;
+    vault::ensure_unlocked()?;
+
     // 停止 RoxyBrowser
-    super::process::stop_roxy()?;
+    super::process::shutdown_roxy()?;
     
     // 从 RoxyBrowser 数据中读取新用户邮箱
     let email = read_current_email_from_roxy()
-        .ok_or("无法检测到登录用户，请确保已完成登录")?;
+        .ok_or_else(|| AppError::new(ErrorCode::LoginNotDetected, "user.loginNotDetected"))?;
+
+    // 已有同一邮箱的用户时沿用其 ID，不再新增重复用户
+    let mut config = load_config()?;
+    let user_id = register_logged_in_user(&mut config, &email, &Utc::now().to_rfc3339());
     
     // 保存新用户数据
-    backup_roxy_data(&email)?;
-    
-    // 更新配置
-    let mut config = load_config();
-    let now = Utc::now().to_rfc3339();
-    
-    // 检查用户是否已存在
-    if !config.users.iter().any(|u| u.email == email) {
-        config.users.push(UserProfile {
-            email: email.clone(),
-            display_name: email.split('@').next().unwrap_or(&email).to_string(),
-            created_at: now.clone(),
-            last_used: now.clone(),
-            note: String::new(),
-        });
-    }
+    backup_roxy_data(&user_id)?;
     
-    config.current_user = Some(email.clone());
+    config.current_user = Some(user_id.clone());
     save_config(&config)?;
     
     // 返回新用户信息
-    let user = config.users.iter().find(|u| u.email == email).cloned()
-        .ok_or("无法找到新用户配置")?;
+    let user = config
+        .find_user(&user_id)
+        .cloned()
+        .ok_or_else(|| AppError::new(ErrorCode::UserNotFound, "user.newProfileMissing"))?;
     
     // 启动 RoxyBrowser
-    super::process::start_roxy()?;
+    super::process::launch_roxy(None)?;
     
     Ok(user)
 }
 
-/// 导出所有用户配置到指定目录
+/// 记录登录的用户并返回其 ID：按邮箱找到已有用户时更新最后使用时间，否则新增以邮箱为 ID 的用户
+fn register_logged_in_user(config: &mut AppConfig, email: &str, now: &str) -> String {
+    if let Some(id) = config.find_user_by_email(email).map(|u| u.id.clone()) {
+        if let Some(user) = config.users.iter_mut().find(|u| u.id == id) {
+            user.last_used = now.to_string();
+        }
+        return id;
+    }
+
+    config.users.push(UserProfile {
+        id: email.to_string(),
+        email: email.to_string(),
+        display_name: email.split('@').next().unwrap_or(email).to_string(),
+        created_at: now.to_string(),
+        last_used: now.to_string(),
+        note: String::new(),
+        backup_item_set: None,
+        launch_config: None,
+    });
+    email.to_string()
+}
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum ExportFormat {
+    /// 目录：`config.json` + `profiles/<用户 ID>/...`
+    #[default]
+    #[serde(rename = "folder")]
+    Folder,
+    /// 单个 `.roxybak` 归档文件
+    #[serde(rename = "archive")]
+    Archive,
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// 设置后使用该密码加密导出（总是生成归档文件）
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    /// 只导出这些用户，不设置时导出全部
+    #[serde(rename = "userIds", default)]
+    pub user_ids: Option<Vec<String>>,
+    /// 只导出备注中包含该文本的用户（不区分大小写）
+    #[serde(rename = "noteFilter", default)]
+    pub note_filter: Option<String>,
+}
+
+/// 按导出选项筛选用户，生成导出用的配置
+///
+/// 导出的配置不包含 `currentUser`，避免泄露导出方当前使用的账号。
+fn select_export_config(
+    config: &AppConfig,
+    options: &ExportOptions,
+) -> Result<AppConfig, AppError> {
+    if let Some(ref user_ids) = options.user_ids {
+        if let Some(missing) = user_ids.iter().find(|id| config.find_user(id).is_none()) {
+            return Err(AppError::user_not_found(missing));
+        }
+    }
+
+    let note_filter = options
+        .note_filter
+        .as_deref()
+        .map(|f| f.trim().to_lowercase())
+        .filter(|f| !f.is_empty());
+
+    let users: Vec<UserProfile> = config
+        .users
+        .iter()
+        .filter(|u| {
+            options
+                .user_ids
+                .as_ref()
+                .map(|user_ids| user_ids.contains(&u.id))
+                .unwrap_or(true)
+        })
+        .filter(|u| {
+            note_filter
+                .as_ref()
+                .map(|f| u.note.to_lowercase().contains(f.as_str()))
+                .unwrap_or(true)
+        })
+        .cloned()
+        .collect();
+
+    if users.is_empty() {
+        return Err(AppError::new(ErrorCode::NothingToExport, "export.noMatch"));
+    }
+
+    Ok(AppConfig {
+        version: config.version,
+        current_user: None,
+        users,
+    })
+}
+
+/// 导入来源类型，供前端决定是否需要提示输入密码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ImportSourceKind {
+    #[serde(rename = "folder")]
+    Folder,
+    #[serde(rename = "archive")]
+    Archive,
+    #[serde(rename = "encrypted")]
+    Encrypted,
+}
+
+/// 私有临时目录的根目录
+fn get_private_temp_root() -> PathBuf {
+    get_app_config_dir().join("tmp")
+}
+
+/// 创建只有当前用户可以访问的目录（Unix 下权限为 0700）
+fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new().mode(0o700).create(path)
+    }
+    #[cfg(not(unix))]
+    fs::create_dir(path)
+}
+
+/// 只有当前用户可以访问的临时目录，离开作用域时连同内容一起删除
+///
+/// 明文归档、解密和解压的中间文件都写在这里，不经过系统共享的临时目录；
+/// 目录总是新建的，其中的文件对其他用户不可见。
+struct PrivateTempDir(PathBuf);
+
+impl PrivateTempDir {
+    fn create(prefix: &str) -> Result<Self, AppError> {
+        let create_failed = |e| AppError::new(ErrorCode::Io, "temp.createDir").caused_by(e);
+        let root = get_private_temp_root();
+        fs::create_dir_all(get_app_config_dir()).map_err(create_failed)?;
+        match create_private_dir(&root) {
+            Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+                return Err(create_failed(e))
+            }
+            _ => {}
+        }
+        // 目录可能由旧版本创建，统一收紧权限
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&root, fs::Permissions::from_mode(0o700)).map_err(create_failed)?;
+        }
+
+        let path = root.join(format!(
+            "{}-{}-{}",
+            prefix,
+            std::process::id(),
+            progress::new_operation_id()
+        ));
+        create_private_dir(&path).map_err(create_failed)?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for PrivateTempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// 删除上次运行（崩溃或强制退出）残留的私有临时文件
+pub fn clean_private_temp() {
+    fs::remove_dir_all(get_private_temp_root()).ok();
+}
+
+/// 归档导出路径：已带 `.roxybak` 扩展名时直接使用，否则视为目录并生成文件名
+fn resolve_archive_path(export_path: &str) -> Result<PathBuf, AppError> {
+    let path = PathBuf::from(export_path);
+    let is_archive = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case(archive::ARCHIVE_EXTENSION))
+        .unwrap_or(false);
+    if is_archive {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::new(ErrorCode::Io, "export.createDir").caused_by(e))?;
+        }
+        return Ok(path);
+    }
+
+    fs::create_dir_all(&path)
+        .map_err(|e| AppError::new(ErrorCode::Io, "export.createDir").caused_by(e))?;
+    let file_name = format!(
+        "roxybrowser-profiles-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        archive::ARCHIVE_EXTENSION
+    );
+    Ok(path.join(file_name))
+}
+
+/// 导出结果
+#[derive(Debug, Serialize)]
+pub struct ExportSummary {
+    /// 导出的用户数
+    pub users: usize,
+    /// 导出目录或归档文件路径
+    pub destination: String,
+    /// 因排除规则跳过的锁文件、缓存文件数
+    pub skipped: usize,
+    /// 按当前语言渲染的提示
+    pub message: String,
+}
+
+impl ExportSummary {
+    fn new(users: usize, destination: String, skipped: usize) -> Self {
+        let key = if skipped > 0 {
+            "export.doneWithSkipped"
+        } else {
+            "export.done"
+        };
+        let message = i18n::render(
+            key,
+            [
+                ("count", users.to_string().as_str()),
+                ("destination", destination.as_str()),
+                ("skipped", skipped.to_string().as_str()),
+            ],
+        );
+        Self {
+            users,
+            destination,
+            skipped,
+            message,
+        }
+    }
+}
+
+/// 导出用户配置到指定目录或归档文件，可按用户或备注只导出部分用户
+///
+/// 在后台执行，进度以 [`progress::EVENT_PROGRESS`] 事件推送，可用进度中的编号取消。
 #[tauri::command]
-pub fn export_profiles(export_path: String) -> Result<String, String> {
-    let profiles_dir = get_profiles_dir();
-    let config = load_config();
+pub async fn export_profiles(
+    app: AppHandle,
+    export_path: String,
+    options: Option<ExportOptions>,
+) -> Result<ExportSummary, AppError> {
+    progress::run_blocking(app, "export_profiles", move || {
+        export_profiles_blocking(export_path, options)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "export_profiles", skip_all, fields(path = %export_path), ret(Debug), err(Display))]
+pub fn export_profiles_blocking(
+    export_path: String,
+    options: Option<ExportOptions>,
+) -> Result<ExportSummary, AppError> {
+    let operation = operation::begin_cancellable(
+        "export_profiles",
+        Description::new("activity.exportProfiles"),
+    )?;
+    operation.finish(export_selected(&export_path, options.unwrap_or_default()))
+}
+
+/// 按导出选项写出归档或目录，失败时清理已写入的内容
+fn export_selected(export_path: &str, options: ExportOptions) -> Result<ExportSummary, AppError> {
+    let config = load_config()?;
+
+    if config.users.is_empty() {
+        return Err(AppError::new(ErrorCode::NothingToExport, "export.nothing"));
+    }
     
-    if !profiles_dir.exists() || config.users.is_empty() {
-        return Err("没有可导出的用户配置".to_string());
+    let config = select_export_config(&config, &options)?;
+    let passphrase = options.passphrase.filter(|p| !p.is_empty());
+    let exclude = itemset::ExcludeFilter::from_settings()?;
+
+    if options.format == ExportFormat::Archive || passphrase.is_some() {
+        // 归档先写入临时文件，失败或取消时不会留下不完整的文件
+        let archive_path = resolve_archive_path(export_path)?;
+        let mut manifests = Vec::new();
+        for user in &config.users {
+            if let Some(manifest) = store::load_manifest(&user.id)? {
+                manifests.push(manifest);
+            }
+        }
+
+        let skipped = match passphrase {
+            None => archive::write_archive(&archive_path, &config, &manifests, &exclude)?,
+            Some(passphrase) => {
+                // 明文归档只写在私有临时目录，加密后的文件才写到导出位置
+                let temp_dir = PrivateTempDir::create("export")?;
+                let plain_path = temp_dir
+                    .path()
+                    .join(format!("plain.{}", archive::ARCHIVE_EXTENSION));
+                let skipped = archive::write_archive(&plain_path, &config, &manifests, &exclude)?;
+                crypto::encrypt_export(&plain_path, &archive_path, &passphrase)?;
+                skipped
+            }
+        };
+        return Ok(ExportSummary::new(
+            config.users.len(),
+            archive_path.display().to_string(),
+            skipped,
+        ));
     }
     
-    let export_dir = PathBuf::from(&export_path);
-    fs::create_dir_all(&export_dir)
-        .map_err(|e| format!("无法创建导出目录: {}", e))?;
+    // 目录导出先写入同级的临时目录，完成后再移入导出目录；
+    // 失败或取消时只删除临时目录，不会动到导出目录中已有的内容
+    let export_dir = PathBuf::from(export_path);
+    let staging_dir = create_staging_dir(&export_dir)?;
+    let result = write_export_dir(&staging_dir, &config, &exclude)
+        .and_then(|skipped| promote_export(&staging_dir, &export_dir, &config).map(|_| skipped));
+    fs::remove_dir_all(&staging_dir).ok();
     
+    let skipped = result?;
+    Ok(ExportSummary::new(
+        config.users.len(),
+        export_path.to_string(),
+        skipped,
+    ))
+}
+
+/// 在导出目录旁创建本次导出专用的临时目录，与导出目录位于同一文件系统以便直接移动
+fn create_staging_dir(export_dir: &Path) -> Result<PathBuf, AppError> {
+    let name = export_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "export".to_string());
+    let parent = export_dir.parent().unwrap_or(export_dir);
+    let staging_dir = parent.join(format!(
+        ".{}.partial-{}-{}",
+        name,
+        std::process::id(),
+        progress::new_operation_id()
+    ));
+    fs::create_dir_all(&staging_dir)
+        .map_err(|e| AppError::new(ErrorCode::Io, "export.createDir").caused_by(e))?;
+    Ok(staging_dir)
+}
+
+/// 将配置和每个用户的完整目录写入导出目录，返回因排除规则跳过的文件数
+fn write_export_dir(
+    export_dir: &Path,
+    config: &AppConfig,
+    exclude: &itemset::ExcludeFilter,
+) -> Result<usize, AppError> {
     // 导出配置文件
-    let config_content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("序列化配置失败: {}", e))?;
+    let config_content = serde_json::to_string_pretty(config)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "export.serialize").caused_by(e))?;
     fs::write(export_dir.join("config.json"), config_content)
-        .map_err(|e| format!("写入配置失败: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Io, "export.write").caused_by(e))?;
     
     // 导出每个用户的配置目录
     let export_profiles_dir = export_dir.join("profiles");
     fs::create_dir_all(&export_profiles_dir)
-        .map_err(|e| format!("无法创建profiles目录: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Io, "export.createProfilesDir").caused_by(e))?;
     
+    // 从 blob 存储还原成完整目录，保持与旧版本兼容的导出结构
+    let mut skipped = 0;
     for user in &config.users {
-        let src = profiles_dir.join(&user.email);
-        let dst = export_profiles_dir.join(&user.email);
-        if src.exists() {
-            copy_dir_all(&src, &dst)?;
+        if let Some(manifest) = store::load_manifest(&user.id)? {
+            let target = export_profiles_dir.join(&user.id);
+            skipped += store::materialize(&manifest, &target, exclude)?.len();
         }
     }
     
-    Ok(format!("成功导出 {} 个用户配置到 {}", config.users.len(), export_path))
+    Ok(skipped)
+}
+
+/// 把临时目录中写好的导出移入导出目录：导出目录不存在时直接改名，
+/// 否则逐个替换同名的用户目录，最后写入配置文件，保留目录中原有的其他内容
+fn promote_export(
+    staging_dir: &Path,
+    export_dir: &Path,
+    config: &AppConfig,
+) -> Result<(), AppError> {
+    let move_failed = |e| AppError::new(ErrorCode::Io, "export.write").caused_by(e);
+    if !export_dir.exists() {
+        return fs::rename(staging_dir, export_dir).map_err(move_failed);
+    }
+
+    let export_profiles_dir = export_dir.join("profiles");
+    fs::create_dir_all(&export_profiles_dir)
+        .map_err(|e| AppError::new(ErrorCode::Io, "export.createProfilesDir").caused_by(e))?;
+    for user in &config.users {
+        let staged = staging_dir.join("profiles").join(&user.id);
+        if !staged.exists() {
+            continue;
+        }
+        let target = export_profiles_dir.join(&user.id);
+        if target.exists() {
+            fs::remove_dir_all(&target).map_err(move_failed)?;
+        }
+        fs::rename(&staged, &target).map_err(move_failed)?;
+    }
+    fs::rename(
+        staging_dir.join("config.json"),
+        export_dir.join("config.json"),
+    )
+    .map_err(move_failed)
 }
 
-/// 从指定目录导入用户配置
+/// 判断导入来源是目录、归档还是加密归档
 #[tauri::command]
-pub fn import_profiles(import_path: String) -> Result<String, String> {
-    let import_dir = PathBuf::from(&import_path);
+pub fn inspect_import_source(import_path: String) -> Result<ImportSourceKind, AppError> {
+    let path = PathBuf::from(&import_path);
+    if !path.exists() {
+        return Err(AppError::new(
+            ErrorCode::ImportSourceNotFound,
+            "import.sourceNotFound",
+        ));
+    }
+    if !path.is_file() {
+        Ok(ImportSourceKind::Folder)
+    } else if crypto::is_encrypted_export(&path) {
+        Ok(ImportSourceKind::Encrypted)
+    } else {
+        Ok(ImportSourceKind::Archive)
+    }
+}
+
+/// 导入用户相对本地的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ImportStatus {
+    /// 本地没有该用户
+    #[serde(rename = "new")]
+    New,
+    /// 备份内容与本地完全相同
+    #[serde(rename = "identical")]
+    Identical,
+    /// 内容不同，且导入的最后使用时间不晚于本地
+    #[serde(rename = "older")]
+    Older,
+    /// 内容不同，且导入的最后使用时间晚于本地
+    #[serde(rename = "newer")]
+    Newer,
+}
+
+/// 导入预览中的单个用户
+#[derive(Debug, Serialize)]
+pub struct ImportPreviewEntry {
+    pub id: String,
+    pub email: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    pub note: String,
+    #[serde(rename = "lastUsed")]
+    pub last_used: String,
+    pub status: ImportStatus,
+    /// 本地同一用户的最后使用时间
+    #[serde(rename = "localLastUsed")]
+    pub local_last_used: Option<String>,
+    /// 本地同一用户是否为当前活动用户
+    #[serde(rename = "isCurrent")]
+    pub is_current: bool,
+    #[serde(rename = "fileCount")]
+    pub file_count: usize,
+    #[serde(rename = "totalSize")]
+    pub total_size: u64,
+}
+
+/// 对单个导入用户的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ImportAction {
+    /// 跳过
+    #[serde(rename = "skip")]
+    Skip,
+    /// 新用户直接导入；已有用户用导入的数据覆盖（旧数据保留为快照）
+    #[serde(rename = "overwrite")]
+    Overwrite,
+    /// 保留本地用户，导入的数据另存为使用新 ID 的用户
+    #[serde(rename = "keepBoth")]
+    KeepBoth,
+    /// 保留本地数据，只合并备注
+    #[serde(rename = "mergeNotes")]
+    MergeNotes,
+}
+
+/// 单个用户的导入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ImportOutcome {
+    #[serde(rename = "imported")]
+    Imported,
+    #[serde(rename = "overwritten")]
+    Overwritten,
+    #[serde(rename = "renamed")]
+    Renamed,
+    #[serde(rename = "notesMerged")]
+    NotesMerged,
+    #[serde(rename = "skipped")]
+    Skipped,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+/// 单个用户的导入记录
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub id: String,
+    pub email: String,
+    pub outcome: ImportOutcome,
+    /// 实际保存使用的用户 ID（“保留两份”时为新生成的 ID）
+    #[serde(rename = "importedAs")]
+    pub imported_as: Option<String>,
+    /// 导入失败的原因
+    pub error: Option<AppError>,
+}
+
+/// 导入报告
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub results: Vec<ImportResult>,
+}
+
+impl ImportReport {
+    fn count(&self, outcome: ImportOutcome) -> usize {
+        self.results.iter().filter(|r| r.outcome == outcome).count()
+    }
+}
+
+/// 导入结果汇总
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub failed: usize,
+    /// 按当前语言渲染的提示
+    pub message: String,
+}
+
+impl ImportSummary {
+    fn new(imported: usize, failed: usize) -> Self {
+        let key = if failed > 0 {
+            "import.doneWithFailed"
+        } else {
+            "import.done"
+        };
+        let message = i18n::render(
+            key,
+            [
+                ("count", imported.to_string().as_str()),
+                ("failed", failed.to_string().as_str()),
+            ],
+        );
+        Self {
+            imported,
+            failed,
+            message,
+        }
+    }
+}
+
+/// 打开导入来源（目录、归档或加密归档），以解出的目录调用 `f`，结束后清理临时文件
+fn with_import_source<T>(
+    import_path: &str,
+    passphrase: Option<&str>,
+    f: impl FnOnce(&Path) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let import_path = PathBuf::from(import_path);
     
-    if !import_dir.exists() {
-        return Err("导入目录不存在".to_string());
+    if !import_path.exists() {
+        return Err(AppError::new(
+            ErrorCode::ImportSourceNotFound,
+            "import.sourceNotFound",
+        ));
     }
     
+    if !import_path.is_file() {
+        return f(&import_path);
+    }
+
+    // 归档：先解压到私有临时目录并校验全部文件哈希，通过后才交给调用方
+    let temp_dir = PrivateTempDir::create("import")?;
+    let dir = extract_import_archive(&import_path, passphrase, temp_dir.path())?;
+    f(&dir)
+}
+
+/// 解密（如需要）并解压归档到 `temp_dir`，返回解出的目录
+fn extract_import_archive(
+    archive_path: &Path,
+    passphrase: Option<&str>,
+    temp_dir: &Path,
+) -> Result<PathBuf, AppError> {
+    let plain_path = if crypto::is_encrypted_export(archive_path) {
+        let passphrase = passphrase.filter(|p| !p.is_empty()).ok_or_else(|| {
+            AppError::new(ErrorCode::PassphraseRequired, "import.passphraseRequired")
+        })?;
+        let plain_path = temp_dir.join(format!("decrypted.{}", archive::ARCHIVE_EXTENSION));
+        crypto::decrypt_export(archive_path, &plain_path, passphrase)?;
+        plain_path
+    } else {
+        archive_path.to_path_buf()
+    };
+
+    let extract_dir = temp_dir.join("extracted");
+    archive::extract_archive(&plain_path, &extract_dir)?;
+    Ok(extract_dir)
+}
+
+/// 读取导入目录中的配置，同一用户 ID 只保留第一次出现的记录
+///
+/// 用户 ID 会被用作存储和导入目录中的路径，任何一个不是合法名称时拒绝整个导入。
+fn read_import_config(import_dir: &Path) -> Result<AppConfig, AppError> {
     let config_path = import_dir.join("config.json");
     if !config_path.exists() {
-        return Err("导入目录中未找到 config.json".to_string());
+        return Err(AppError::new(
+            ErrorCode::InvalidImport,
+            "import.configMissing",
+        ));
+    }
+
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| AppError::new(ErrorCode::Io, "import.readConfig").caused_by(e))?;
+    let mut config: AppConfig = serde_json::from_str(&content)
+        .map_err(|e| AppError::new(ErrorCode::InvalidImport, "import.parseConfig").caused_by(e))?;
+
+    config.fill_missing_ids();
+    if let Some(user) = config
+        .users
+        .iter()
+        .find(|u| store::ensure_safe_name(&u.id).is_err())
+    {
+        return Err(
+            AppError::new(ErrorCode::InvalidImport, "import.invalidUserId").with("user", &user.id),
+        );
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    config.users.retain(|u| seen.insert(u.id.clone()));
+    Ok(config)
+}
+
+/// 导入目录中某个用户的数据目录
+fn import_profile_dir(import_dir: &Path, user_id: &str) -> PathBuf {
+    import_dir.join("profiles").join(user_id)
+}
+
+/// 解析 RFC 3339 时间，无法解析时视为最早
+fn parse_time(value: &str) -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .unwrap_or_else(|_| chrono::DateTime::UNIX_EPOCH.fixed_offset())
+}
+
+/// 比较导入用户与本地同一 ID 的用户
+fn compare_import_user(
+    import_dir: &Path,
+    user: &UserProfile,
+    local: Option<&UserProfile>,
+) -> Result<(ImportStatus, store::ProfileManifest), AppError> {
+    let src = import_profile_dir(import_dir, &user.id);
+    let incoming = store::scan(&user.id, &src, &itemset::ResolvedItems::all_entries(&src)?)?;
+
+    let Some(local) = local else {
+        return Ok((ImportStatus::New, incoming));
+    };
+
+    let same_content = match store::load_manifest(&local.id)? {
+        Some(existing) => store::content_digest(&existing) == store::content_digest(&incoming),
+        None => incoming.files.is_empty(),
+    };
+
+    let status = if same_content {
+        ImportStatus::Identical
+    } else if parse_time(&user.last_used) > parse_time(&local.last_used) {
+        ImportStatus::Newer
+    } else {
+        ImportStatus::Older
+    };
+    Ok((status, incoming))
+}
+
+/// 预览导入内容：列出每个用户相对本地是新增、相同、较旧还是较新
+///
+/// 需要解密、解压并扫描整个导入来源，在后台执行并推送进度。
+#[tauri::command]
+pub async fn preview_import(
+    app: AppHandle,
+    import_path: String,
+    passphrase: Option<String>,
+) -> Result<Vec<ImportPreviewEntry>, AppError> {
+    progress::run_blocking(app, "preview_import", move || {
+        preview_import_blocking(import_path, passphrase)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "preview_import", skip_all, fields(path = %import_path), err(Display))]
+pub fn preview_import_blocking(
+    import_path: String,
+    passphrase: Option<String>,
+) -> Result<Vec<ImportPreviewEntry>, AppError> {
+    with_import_source(&import_path, passphrase.as_deref(), |import_dir| {
+        let import_config = read_import_config(import_dir)?;
+        let config = load_config()?;
+
+        let mut entries = Vec::new();
+        for user in import_config.users {
+            let local = config.find_user(&user.id);
+            let (status, incoming) = compare_import_user(import_dir, &user, local)?;
+            entries.push(ImportPreviewEntry {
+                status,
+                local_last_used: local.map(|u| u.last_used.clone()),
+                is_current: config.current_user.as_deref() == Some(user.id.as_str()),
+                file_count: incoming.files.len(),
+                total_size: incoming.files.iter().map(|f| f.size).sum(),
+                id: user.id,
+                email: user.email,
+                display_name: user.display_name,
+                note: user.note,
+                last_used: user.last_used,
+            });
+        }
+        Ok(entries)
+    })
+}
+
+/// 按每个用户的处理方式导入，返回每个用户的处理结果
+///
+/// `decisions` 以导入用户的 ID 为键；未给出处理方式的用户：新用户直接导入，已有用户跳过。
+#[tauri::command]
+pub async fn import_selected_profiles(
+    app: AppHandle,
+    import_path: String,
+    passphrase: Option<String>,
+    decisions: HashMap<String, ImportAction>,
+) -> Result<ImportReport, AppError> {
+    progress::run_blocking(app, "import_profiles", move || {
+        import_selected_profiles_blocking(import_path, passphrase, decisions)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "import_selected_profiles", skip_all, fields(path = %import_path), err(Display))]
+pub fn import_selected_profiles_blocking(
+    import_path: String,
+    passphrase: Option<String>,
+    decisions: HashMap<String, ImportAction>,
+) -> Result<ImportReport, AppError> {
+    let operation = operation::begin_cancellable(
+        "import_profiles",
+        Description::new("activity.importProfiles"),
+    )?;
+    vault::ensure_unlocked()?;
+    operation.finish(with_import_source(
+        &import_path,
+        passphrase.as_deref(),
+        |import_dir| import_from_dir(import_dir, &decisions),
+    ))
+}
+
+/// 从指定目录或 `.roxybak` 归档导入用户配置
+///
+/// 加密归档需要提供 `passphrase`；密码错误或文件被篡改时不会写入任何数据。
+/// 已存在的用户会被跳过，需要覆盖或合并时使用 [`import_selected_profiles`]。
+#[tauri::command]
+pub async fn import_profiles(
+    app: AppHandle,
+    import_path: String,
+    passphrase: Option<String>,
+) -> Result<ImportSummary, AppError> {
+    progress::run_blocking(app, "import_profiles", move || {
+        import_profiles_blocking(import_path, passphrase)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "import_profiles", skip_all, fields(path = %import_path), ret(Debug), err(Display))]
+pub fn import_profiles_blocking(
+    import_path: String,
+    passphrase: Option<String>,
+) -> Result<ImportSummary, AppError> {
+    let operation = operation::begin_cancellable(
+        "import_profiles",
+        Description::new("activity.importProfiles"),
+    )?;
+    vault::ensure_unlocked()?;
+    let report = operation.finish(with_import_source(
+        &import_path,
+        passphrase.as_deref(),
+        |import_dir| import_from_dir(import_dir, &HashMap::new()),
+    ))?;
+
+    Ok(ImportSummary::new(
+        report.count(ImportOutcome::Imported),
+        report.count(ImportOutcome::Failed),
+    ))
+}
+
+/// 为“保留两份”生成不冲突的新用户 ID，邮箱保持不变
+fn new_user_id(config: &AppConfig) -> String {
+    loop {
+        let mut bytes = [0u8; 8];
+        OsRng.fill_bytes(&mut bytes);
+        let id = format!("user-{}", hex::encode(bytes));
+        if config.find_user(&id).is_none() && store::load_manifest(&id).ok().flatten().is_none() {
+            return id;
+        }
+    }
+}
+
+/// “保留两份”时新用户的显示名称：在原名称后加序号，与同邮箱的其他用户区分
+fn copy_display_name(config: &AppConfig, user: &UserProfile) -> String {
+    let base = if user.display_name.is_empty() {
+        user.email.split('@').next().unwrap_or(&user.email)
+    } else {
+        user.display_name.as_str()
+    };
+    (2..)
+        .map(|n| format!("{} ({})", base, n))
+        .find(|candidate| !config.users.iter().any(|u| &u.display_name == candidate))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// 合并备注：导入的备注不为空且本地备注中没有时追加到末尾
+fn merge_notes(local: &str, incoming: &str) -> String {
+    let incoming = incoming.trim();
+    if incoming.is_empty() || local.contains(incoming) {
+        local.to_string()
+    } else if local.trim().is_empty() {
+        incoming.to_string()
+    } else {
+        format!("{}\n{}", local.trim_end(), incoming)
+    }
+}
+
+/// 导入时写入存储的用户备份，导入未完成时据此撤销
+struct CapturedImport {
+    user_id: String,
+    /// 导入前的当前清单，新用户为 `None`
+    previous: Option<store::ProfileManifest>,
+    snapshot_id: String,
+}
+
+/// 将导入用户的数据存入 blob 存储（没有数据目录时跳过）
+fn capture_import_user(
+    import_dir: &Path,
+    source_id: &str,
+    user_id: &str,
+    captured: &mut Vec<CapturedImport>,
+) -> Result<(), AppError> {
+    let src = import_profile_dir(import_dir, source_id);
+    if src.exists() {
+        let previous = store::load_manifest(user_id)?;
+        let items = itemset::ResolvedItems::all_entries(&src)?;
+        let manifest = store::capture(user_id, &src, &items, None)?;
+        let snapshot_id = store::save_manifest(&manifest)?;
+        captured.push(CapturedImport {
+            user_id: user_id.to_string(),
+            previous,
+            snapshot_id,
+        });
     }
+    Ok(())
+}
+
+/// 撤销本次导入写入的备份：覆盖的用户恢复原来的清单，新用户的清单整个删除
+fn undo_captured_imports(captured: &[CapturedImport]) {
+    for c in captured.iter().rev() {
+        let result = match c.previous {
+            Some(ref previous) => store::set_head(previous)
+                .and_then(|_| store::remove_snapshot(&c.user_id, &c.snapshot_id)),
+            None => store::remove_manifest(&c.user_id),
+        };
+        if let Err(e) = result {
+            tracing::error!(user = %c.user_id, error = %e, "撤销导入失败");
+        }
+    }
+    // 回收只被撤销的备份引用的 blob，包括取消时写了一半的用户
+    if let Err(e) = store::collect_garbage() {
+        tracing::error!(error = %e, "回收备份数据失败");
+    }
+}
+
+/// 处理单个导入用户，返回结果与实际保存使用的用户 ID
+fn apply_import_action(
+    import_dir: &Path,
+    config: &mut AppConfig,
+    user: UserProfile,
+    action: ImportAction,
+    captured: &mut Vec<CapturedImport>,
+) -> Result<(ImportOutcome, Option<String>), AppError> {
+    let local_index = config.users.iter().position(|u| u.id == user.id);
     
-    // 读取导入的配置
-    let import_config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("读取配置失败: {}", e))?;
-    let import_config: AppConfig = serde_json::from_str(&import_config_content)
-        .map_err(|e| format!("解析配置失败: {}", e))?;
+    let Some(index) = local_index else {
+        if action == ImportAction::Skip {
+            return Ok((ImportOutcome::Skipped, None));
+        }
+        capture_import_user(import_dir, &user.id, &user.id, captured)?;
+        let id = user.id.clone();
+        config.users.push(user);
+        return Ok((ImportOutcome::Imported, Some(id)));
+    };
     
+    match action {
+        ImportAction::Skip => Ok((ImportOutcome::Skipped, None)),
+        ImportAction::Overwrite => {
+            // 当前用户的实时数据在 RoxyBrowser 目录中，覆盖备份会在下次切换时被实时数据冲掉
+            if config.current_user.as_deref() == Some(user.id.as_str()) {
+                return Err(AppError::new(
+                    ErrorCode::ActiveUser,
+                    "import.overwriteActive",
+                ));
+            }
+            capture_import_user(import_dir, &user.id, &user.id, captured)?;
+            let local = &mut config.users[index];
+            local.display_name = user.display_name;
+            local.last_used = user.last_used;
+            local.note = user.note;
+            Ok((ImportOutcome::Overwritten, Some(user.id)))
+        }
+        ImportAction::KeepBoth => {
+            let id = new_user_id(config);
+            capture_import_user(import_dir, &user.id, &id, captured)?;
+            let display_name = copy_display_name(config, &user);
+            config.users.push(UserProfile {
+                id: id.clone(),
+                display_name,
+                ..user
+            });
+            Ok((ImportOutcome::Renamed, Some(id)))
+        }
+        ImportAction::MergeNotes => {
+            let local = &mut config.users[index];
+            local.note = merge_notes(&local.note, &user.note);
+            Ok((ImportOutcome::NotesMerged, Some(user.id)))
+        }
+    }
+}
+
+/// 从导出目录结构导入用户配置
+///
+/// 配置只在全部用户处理完后保存一次；取消或保存失败时撤销已写入的备份，
+/// 不会出现配置中没有、存储中却有一半数据的用户。
+fn import_from_dir(
+    import_dir: &Path,
+    decisions: &HashMap<String, ImportAction>,
+) -> Result<ImportReport, AppError> {
+    let import_config = read_import_config(import_dir)?;
+    let mut captured = Vec::new();
+    let result = import_users(import_dir, import_config, decisions, &mut captured);
+    if result.is_err() {
+        undo_captured_imports(&captured);
+    }
+    result
+}
+
+fn import_users(
+    import_dir: &Path,
+    import_config: AppConfig,
+    decisions: &HashMap<String, ImportAction>,
+    captured: &mut Vec<CapturedImport>,
+) -> Result<ImportReport, AppError> {
     // 合并到现有配置
-    let mut current_config = load_config();
-    let mut imported_count = 0;
+    let mut current_config = load_config()?;
+    let mut results = Vec::new();
+    let mut touched = Vec::new();
     
     for import_user in import_config.users {
-        // 检查用户是否已存在
-        if !current_config.users.iter().any(|u| u.email == import_user.email) {
-            // 复制用户配置目录
-            let src = import_dir.join("profiles").join(&import_user.email);
-            let dst = get_profiles_dir().join(&import_user.email);
-            if src.exists() {
-                copy_dir_all(&src, &dst)?;
+        operation::check_cancelled()?;
+        let id = import_user.id.clone();
+        let email = import_user.email.clone();
+        let exists = current_config.find_user(&id).is_some();
+        let action = decisions.get(&id).copied().unwrap_or(if exists {
+            ImportAction::Skip
+        } else {
+            ImportAction::Overwrite
+        });
+
+        // 单个用户失败不影响其他用户，取消则停止整个导入
+        let result = match apply_import_action(
+            import_dir,
+            &mut current_config,
+            import_user,
+            action,
+            captured,
+        ) {
+            Err(_) if operation::is_cancelled() => return Err(AppError::cancelled()),
+            Ok((outcome, imported_as)) => {
+                touched.extend(imported_as.clone());
+                ImportResult {
+                    id,
+                    email,
+                    outcome,
+                    imported_as,
+                    error: None,
+                }
             }
-            current_config.users.push(import_user);
-            imported_count += 1;
-        }
+            Err(e) => ImportResult {
+                id,
+                email,
+                outcome: ImportOutcome::Failed,
+                imported_as: None,
+                error: Some(e),
+            },
+        };
+        results.push(result);
     }
     
-    save_config(&current_config)?;
+    operation::check_cancelled()?;
     
-    Ok(format!("成功导入 {} 个用户配置", imported_count))
+    // 处理期间配置可能已被修改，保存前重新读取，只写回本次导入涉及的用户
+    let mut latest = load_config()?;
+    for id in &touched {
+        let Some(user) = current_config.find_user(id) else {
+            continue;
+        };
+        match latest.users.iter_mut().find(|u| &u.id == id) {
+            Some(local) => *local = user.clone(),
+            None => latest.users.push(user.clone()),
+        }
+    }
+    save_config(&latest)?;
+
+    Ok(ImportReport { results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::{save_users, TestRoot};
+
+    fn backup_user(id: &str, cookies: &str) {
+        let live = get_roxy_data_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), cookies).unwrap();
+        backup_roxy_data(id).unwrap();
+    }
+
+    /// 按导出目录结构写入待导入的用户，每个用户的 Cookies 内容为 `imported-<id>`
+    fn write_import_dir(users: Vec<UserProfile>) -> PathBuf {
+        let import_dir = get_app_config_dir().join("imports/in");
+        for user in &users {
+            let dir = import_profile_dir(&import_dir, &user.id);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("Cookies"), format!("imported-{}", user.id)).unwrap();
+        }
+        let config = AppConfig {
+            users,
+            ..AppConfig::default()
+        };
+        fs::write(
+            import_dir.join("config.json"),
+            serde_json::to_string(&config).unwrap(),
+        )
+        .unwrap();
+        import_dir
+    }
+
+    fn backed_up_cookies(id: &str) -> String {
+        let target = get_app_config_dir().join("restored").join(id);
+        restore_roxy_data(id, &target).unwrap();
+        fs::read_to_string(target.join("Cookies")).unwrap()
+    }
+
+    fn staging_dirs(parent: &Path) -> Vec<String> {
+        fs::read_dir(parent)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".partial-"))
+            .collect()
+    }
+
+    #[test]
+    fn logged_in_email_reuses_existing_user() {
+        let mut config = AppConfig::default();
+        let mut copy = crate::commands::testing::user("user-1a2b");
+        copy.email = "a@x.com".to_string();
+        config.users = vec![copy, crate::commands::testing::user("a@x.com")];
+
+        assert_eq!(
+            register_logged_in_user(&mut config, "A@x.com", "t1"),
+            "a@x.com"
+        );
+        assert_eq!(config.users.len(), 2);
+        assert_eq!(config.find_user("a@x.com").unwrap().last_used, "t1");
+
+        // 只有“保留两者”产生的副本时沿用副本
+        config.users.retain(|u| u.id != "a@x.com");
+        assert_eq!(
+            register_logged_in_user(&mut config, "a@x.com", "t2"),
+            "user-1a2b"
+        );
+        assert_eq!(config.users.len(), 1);
+
+        assert_eq!(
+            register_logged_in_user(&mut config, "b@x.com", "t3"),
+            "b@x.com"
+        );
+        assert_eq!(config.users.len(), 2);
+    }
+
+    #[test]
+    fn backup_fails_without_pruning_when_settings_cannot_be_read() {
+        let _root = TestRoot::new("backup-settings");
+        save_users(&["a"], Some("a"));
+        backup_user("a", "old");
+        let snapshots = store::list_snapshot_ids("a");
+
+        let settings = crate::commands::testing::root()
+            .unwrap()
+            .join("roxybrowser-manager/settings.json");
+        fs::write(&settings, "{\"snapshotRetention\": ").unwrap();
+        fs::write(get_roxy_data_dir().join("Cookies"), "new").unwrap();
+        assert_eq!(backup_roxy_data("a").unwrap_err().key, "config.parse");
+        assert_eq!(store::list_snapshot_ids("a"), snapshots);
+        assert_eq!(backed_up_cookies("a"), "old");
+    }
+
+    #[test]
+    fn folder_export_replaces_users_and_keeps_other_content() {
+        let _root = TestRoot::new("export-ok");
+        save_users(&["a"], Some("a"));
+        backup_user("a", "new");
+
+        let export_dir = get_app_config_dir().join("exports/out");
+        fs::create_dir_all(export_dir.join("profiles/a")).unwrap();
+        fs::write(export_dir.join("profiles/a/Stale"), "old").unwrap();
+        fs::write(export_dir.join("notes.txt"), "keep").unwrap();
+
+        let summary =
+            export_selected(&export_dir.to_string_lossy(), ExportOptions::default()).unwrap();
+        assert_eq!(summary.users, 1);
+        assert_eq!(
+            fs::read_to_string(export_dir.join("profiles/a/Cookies")).unwrap(),
+            "new"
+        );
+        assert!(!export_dir.join("profiles/a/Stale").exists());
+        assert_eq!(
+            fs::read_to_string(export_dir.join("notes.txt")).unwrap(),
+            "keep"
+        );
+        assert!(export_dir.join("config.json").exists());
+        assert!(staging_dirs(export_dir.parent().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn failed_folder_export_leaves_earlier_export_untouched() {
+        let _root = TestRoot::new("export-fail");
+        save_users(&["a"], Some("a"));
+        backup_user("a", "new");
+        // 删除 blob 让还原失败
+        fs::remove_dir_all(store::get_store_dir().join("blobs")).unwrap();
+
+        let export_dir = get_app_config_dir().join("exports/out");
+        fs::create_dir_all(export_dir.join("profiles/a")).unwrap();
+        fs::write(export_dir.join("profiles/a/Cookies"), "earlier").unwrap();
+        fs::write(export_dir.join("config.json"), "earlier").unwrap();
+
+        assert!(export_selected(&export_dir.to_string_lossy(), ExportOptions::default()).is_err());
+        assert_eq!(
+            fs::read_to_string(export_dir.join("profiles/a/Cookies")).unwrap(),
+            "earlier"
+        );
+        assert_eq!(
+            fs::read_to_string(export_dir.join("config.json")).unwrap(),
+            "earlier"
+        );
+        assert!(staging_dirs(export_dir.parent().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn selective_import_applies_each_decision() {
+        use crate::commands::testing::user;
+
+        let _root = TestRoot::new("import-select");
+        save_users(&["a", "c", "e"], Some("e"));
+        backup_user("a", "local-a");
+        let mut incoming_c = user("c");
+        incoming_c.note = "hello".to_string();
+        let import_dir =
+            write_import_dir(vec![user("a"), user("b"), incoming_c, user("d"), user("e")]);
+
+        let decisions = HashMap::from([
+            ("a".to_string(), ImportAction::KeepBoth),
+            ("b".to_string(), ImportAction::Skip),
+            ("c".to_string(), ImportAction::MergeNotes),
+            ("e".to_string(), ImportAction::Overwrite),
+        ]);
+        let report = import_from_dir(&import_dir, &decisions).unwrap();
+        let outcomes: Vec<ImportOutcome> = report.results.iter().map(|r| r.outcome).collect();
+        assert_eq!(
+            outcomes,
+            [
+                ImportOutcome::Renamed,
+                ImportOutcome::Skipped,
+                ImportOutcome::NotesMerged,
+                ImportOutcome::Imported,
+                ImportOutcome::Failed,
+            ]
+        );
+        assert_eq!(
+            report.results[4].error.as_ref().unwrap().key,
+            "import.overwriteActive"
+        );
+
+        let config = load_config().unwrap();
+        let copy_id = report.results[0].imported_as.clone().unwrap();
+        assert_ne!(copy_id, "a");
+        assert_eq!(config.find_user(&copy_id).unwrap().display_name, "a (2)");
+        assert_eq!(backed_up_cookies("a"), "local-a");
+        assert_eq!(backed_up_cookies(&copy_id), "imported-a");
+        assert!(config.find_user("b").is_none());
+        assert_eq!(config.find_user("c").unwrap().note, "hello");
+        assert_eq!(backed_up_cookies("d"), "imported-d");
+        assert!(store::load_manifest("e").unwrap().is_none());
+    }
+
+    #[test]
+    fn undoing_an_import_restores_previous_backups() {
+        use crate::commands::testing::user;
+
+        let _root = TestRoot::new("import-undo");
+        save_users(&["a"], None);
+        backup_user("a", "local-a");
+        let before = store::load_manifest("a").unwrap().unwrap();
+        let import_dir = write_import_dir(vec![user("a"), user("n")]);
+
+        let mut captured = Vec::new();
+        capture_import_user(&import_dir, "a", "a", &mut captured).unwrap();
+        capture_import_user(&import_dir, "n", "n", &mut captured).unwrap();
+        assert_eq!(backed_up_cookies("a"), "imported-a");
+
+        undo_captured_imports(&captured);
+        let after = store::load_manifest("a").unwrap().unwrap();
+        assert_eq!(after.snapshot_id, before.snapshot_id);
+        assert_eq!(
+            store::list_snapshot_ids("a"),
+            [before.snapshot_id.clone().unwrap()]
+        );
+        assert!(store::load_manifest("n").unwrap().is_none());
+        assert_eq!(backed_up_cookies("a"), "local-a");
+        // 导入写入的 blob 已被回收
+        let imported_hash =
+            store::hash_file(&import_profile_dir(&import_dir, "n").join("Cookies")).unwrap();
+        assert!(!store::get_blob_path(&imported_hash, None).exists());
+    }
 }
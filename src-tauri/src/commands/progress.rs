@@ -0,0 +1,258 @@
+use super::operation;
+use crate::error::{AppError, ErrorCode};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// 长时间操作的进度事件
+pub const EVENT_PROGRESS: &str = "operation://progress";
+
+/// 同一阶段内两次进度事件的最短间隔，避免大量小文件时事件过多
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 本进程内的操作序号，与启动时间一起组成操作编号
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 操作当前所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ProgressPhase {
+    /// 把文件存入 blob 存储（备份当前用户、导入用户）
+    #[serde(rename = "capture")]
+    Capture,
+    /// 从 blob 存储还原文件（切换用户、导出为目录）
+    #[serde(rename = "materialize")]
+    Materialize,
+    /// 写入 `.roxybak` 归档
+    #[serde(rename = "archive")]
+    Archive,
+    /// 加密导出文件
+    #[serde(rename = "encrypt")]
+    Encrypt,
+    /// 解密导入文件
+    #[serde(rename = "decrypt")]
+    Decrypt,
+    /// 解压并校验导入归档
+    #[serde(rename = "extract")]
+    Extract,
+    /// 校验备份中的文件（校验用户备份、查找可用于修复的快照）
+    #[serde(rename = "verify")]
+    Verify,
+}
+
+/// 推送给前端的进度
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    #[serde(rename = "operationId")]
+    pub operation_id: String,
+    /// 操作类型，例如 `export_profiles`
+    pub operation: &'static str,
+    pub phase: Option<ProgressPhase>,
+    /// 当前阶段处理的用户
+    pub user: Option<String>,
+    /// 最近处理完的文件
+    #[serde(rename = "currentItem")]
+    pub current_item: Option<String>,
+    #[serde(rename = "filesDone")]
+    pub files_done: usize,
+    #[serde(rename = "filesTotal")]
+    pub files_total: usize,
+    #[serde(rename = "bytesDone")]
+    pub bytes_done: u64,
+    #[serde(rename = "bytesTotal")]
+    pub bytes_total: u64,
+    /// 按当前阶段的处理速度估算的剩余秒数
+    #[serde(rename = "etaSecs")]
+    pub eta_secs: Option<u64>,
+    #[serde(rename = "elapsedMs")]
+    pub elapsed_ms: u64,
+}
+
+/// 当前线程上正在汇报进度的操作
+struct Reporter {
+    event: ProgressEvent,
+    emit: Box<dyn Fn(&ProgressEvent)>,
+    started: Instant,
+    phase_started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl Reporter {
+    fn send(&mut self, force: bool) {
+        let now = Instant::now();
+        if !force
+            && self
+                .last_emit
+                .is_some_and(|last| now - last < EMIT_INTERVAL)
+        {
+            return;
+        }
+        self.last_emit = Some(now);
+        self.event.elapsed_ms = (now - self.started).as_millis() as u64;
+        self.event.eta_secs = self.eta(now - self.phase_started);
+        (self.emit)(&self.event);
+    }
+
+    /// 优先按字节估算，没有字节总数时按文件数估算
+    fn eta(&self, elapsed: Duration) -> Option<u64> {
+        let e = &self.event;
+        let (done, total) = if e.bytes_total > 0 {
+            (e.bytes_done, e.bytes_total)
+        } else {
+            (e.files_done as u64, e.files_total as u64)
+        };
+        if done == 0 || total == 0 {
+            return None;
+        }
+        let remaining = total.saturating_sub(done) as f64;
+        Some((elapsed.as_secs_f64() * remaining / done as f64).ceil() as u64)
+    }
+}
+
+thread_local! {
+    static REPORTER: RefCell<Option<Reporter>> = const { RefCell::new(None) };
+}
+
+/// 离开作用域时移除当前线程的进度汇报（包括 panic）
+struct ReporterGuard;
+
+impl Drop for ReporterGuard {
+    fn drop(&mut self) {
+        REPORTER.with(|r| r.borrow_mut().take());
+    }
+}
+
+fn with_reporter(f: impl FnOnce(&mut Reporter)) {
+    REPORTER.with(|r| {
+        if let Some(reporter) = r.borrow_mut().as_mut() {
+            f(reporter);
+        }
+    });
+}
+
+/// 当前线程上正在汇报进度的操作编号
+pub fn current_operation_id() -> Option<String> {
+    REPORTER.with(|r| r.borrow().as_ref().map(|r| r.event.operation_id.clone()))
+}
+
+/// 生成新的操作编号
+pub fn new_operation_id() -> String {
+    format!(
+        "{}-{}",
+        chrono::Utc::now().timestamp_millis(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// 在当前线程上执行 `f`，期间 [`phase`] 和 [`advance`] 的进度通过 `emit` 发出
+///
+/// 已在汇报进度时直接执行 `f`，进度归入外层操作。
+pub fn run<T>(
+    operation_id: String,
+    operation: &'static str,
+    emit: impl Fn(&ProgressEvent) + 'static,
+    f: impl FnOnce() -> T,
+) -> T {
+    let active = REPORTER.with(|r| r.borrow().is_some());
+    if active {
+        return f();
+    }
+
+    let now = Instant::now();
+    REPORTER.with(|r| {
+        *r.borrow_mut() = Some(Reporter {
+            event: ProgressEvent {
+                operation_id,
+                operation,
+                phase: None,
+                user: None,
+                current_item: None,
+                files_done: 0,
+                files_total: 0,
+                bytes_done: 0,
+                bytes_total: 0,
+                eta_secs: None,
+                elapsed_ms: 0,
+            },
+            emit: Box::new(emit),
+            started: now,
+            phase_started: now,
+            last_emit: None,
+        })
+    });
+    let _guard = ReporterGuard;
+    f()
+}
+
+/// 开始新阶段并立即发出进度；没有在汇报进度时什么也不做
+pub fn phase(phase: ProgressPhase, user: Option<&str>, files_total: usize, bytes_total: u64) {
+    with_reporter(|r| {
+        r.event.phase = Some(phase);
+        r.event.user = user.map(str::to_string);
+        r.event.current_item = None;
+        r.event.files_done = 0;
+        r.event.files_total = files_total;
+        r.event.bytes_done = 0;
+        r.event.bytes_total = bytes_total;
+        r.phase_started = Instant::now();
+        r.send(true);
+    });
+}
+
+/// 记录处理完一个文件，阶段的最后一个文件总会发出进度
+pub fn advance(item: &str, bytes: u64) {
+    with_reporter(|r| {
+        r.event.current_item = Some(item.to_string());
+        r.event.files_done += 1;
+        r.event.bytes_done += bytes;
+        let last = r.event.files_done >= r.event.files_total;
+        r.send(last);
+    });
+}
+
+/// 记录处理了一部分字节（加解密等按字节推进的阶段）
+pub fn advance_bytes(bytes: u64) {
+    with_reporter(|r| {
+        r.event.bytes_done += bytes;
+        r.send(false);
+    });
+}
+
+/// 读取时按字节汇报进度，操作被取消时返回错误
+pub struct ProgressReader<R> {
+    inner: R,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R) -> Self {
+        ProgressReader { inner }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        operation::check_cancelled().map_err(|e| io::Error::other(e.to_string()))?;
+        let n = self.inner.read(buf)?;
+        advance_bytes(n as u64);
+        Ok(n)
+    }
+}
+
+/// 在后台线程执行耗时操作，进度以 [`EVENT_PROGRESS`] 事件推送给前端，结果由返回的 future 给出
+pub async fn run_blocking<T: Send + 'static>(
+    app: AppHandle,
+    operation: &'static str,
+    f: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    let operation_id = new_operation_id();
+    tauri::async_runtime::spawn_blocking(move || {
+        let emit = move |event: &ProgressEvent| {
+            let _ = app.emit(EVENT_PROGRESS, event);
+        };
+        run(operation_id, operation, emit, f)
+    })
+    .await
+    .map_err(|e| AppError::new(ErrorCode::Internal, "operation.aborted").caused_by(e))?
+}
@@ -1,5 +1,9 @@
+use super::logging::{self, LogLevel};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::{self, Language};
+use crate::models::user::LaunchConfig;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppSettings {
@@ -7,39 +11,264 @@ pub struct AppSettings {
     pub roxy_exe_path: Option<String>,
     #[serde(default = "default_auto_detect")]
     pub auto_detect_enabled: bool,
+    /// 用户快照保留策略
+    #[serde(rename = "snapshotRetention", default)]
+    pub snapshot_retention: SnapshotRetention,
+    /// 自定义备份项集合
+    #[serde(rename = "backupItemSets", default)]
+    pub backup_item_sets: Vec<BackupItemSet>,
+    /// 默认使用的备份项集合，未设置时使用内置集合
+    #[serde(rename = "defaultBackupItemSet", default)]
+    pub default_backup_item_set: Option<String>,
+    /// 按 RoxyBrowser 版本选择的备份项集合，优先于默认集合
+    #[serde(rename = "versionBackupItemSets", default)]
+    pub version_backup_item_sets: Vec<VersionItemSet>,
+    /// 备份、恢复和导出时排除的路径（glob，匹配相对数据目录的路径）
+    #[serde(rename = "excludePatterns", default = "default_exclude_patterns")]
+    pub exclude_patterns: Vec<String>,
+    /// 停止 RoxyBrowser 时的等待时间
+    #[serde(rename = "shutdown", default)]
+    pub shutdown: ShutdownSettings,
+    /// 启动 RoxyBrowser 后是否等待就绪及等待时间
+    #[serde(rename = "startup", default)]
+    pub startup: StartupSettings,
+    /// 所有用户共用的 RoxyBrowser 启动配置
+    #[serde(rename = "defaultLaunchConfig", default)]
+    pub default_launch_config: LaunchConfig,
+    /// RoxyBrowser 崩溃后的自动重启策略
+    #[serde(rename = "watchdog", default)]
+    pub watchdog: WatchdogSettings,
+    /// 后台检测 RoxyBrowser 进程状态的间隔（毫秒）
+    #[serde(
+        rename = "processWatchIntervalMs",
+        default = "default_process_watch_interval"
+    )]
+    pub process_watch_interval_ms: u64,
+    /// 托盘菜单、对话框和错误消息使用的语言
+    #[serde(rename = "language", default)]
+    pub language: Language,
+    /// 写入日志文件的最低级别
+    #[serde(rename = "logLevel", default)]
+    pub log_level: LogLevel,
 }
 
 fn default_auto_detect() -> bool {
     true
 }
 
+fn default_process_watch_interval() -> u64 {
+    2000
+}
+
+/// 默认排除的锁文件、日志、临时文件和缓存目录
+pub fn default_exclude_patterns() -> Vec<String> {
+    [
+        // LevelDB / Chromium 锁文件与日志
+        "**/LOCK",
+        "**/lockfile",
+        "**/LOG",
+        "**/LOG.old",
+        "**/Singleton*",
+        // 临时文件；SQLite 的 -journal 是未完成事务的回滚日志，缺失会使数据库损坏，不能排除
+        "**/*.tmp",
+        "**/*.temp",
+        // 可重建的缓存
+        "**/Cache",
+        "**/Code Cache",
+        "**/GPUCache",
+        "**/DawnCache",
+        "**/DawnGraphiteCache",
+        "**/GrShaderCache",
+        "**/ShaderCache",
+        // 系统生成的文件
+        "**/.DS_Store",
+        "**/Thumbs.db",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             roxy_exe_path: None,
             auto_detect_enabled: true,
+            snapshot_retention: SnapshotRetention::default(),
+            backup_item_sets: Vec::new(),
+            default_backup_item_set: None,
+            version_backup_item_sets: Vec::new(),
+            exclude_patterns: default_exclude_patterns(),
+            shutdown: ShutdownSettings::default(),
+            startup: StartupSettings::default(),
+            default_launch_config: LaunchConfig::default(),
+            watchdog: WatchdogSettings::default(),
+            process_watch_interval_ms: default_process_watch_interval(),
+            language: Language::default(),
+            log_level: LogLevel::default(),
+        }
+    }
+}
+
+/// 快照保留策略：保留最近 N 个，另外在最近 M 天内每天保留最新的一个
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotRetention {
+    #[serde(rename = "keepLast")]
+    pub keep_last: u32,
+    #[serde(rename = "keepDailyDays")]
+    pub keep_daily_days: u32,
+}
+
+impl Default for SnapshotRetention {
+    fn default() -> Self {
+        Self {
+            keep_last: 10,
+            keep_daily_days: 7,
         }
     }
 }
 
+/// 停止 RoxyBrowser 的等待时间：先请求正常退出，超过宽限期后强制结束
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShutdownSettings {
+    /// 请求正常退出后等待的秒数
+    #[serde(rename = "gracePeriodSecs")]
+    pub grace_period_secs: u64,
+    /// 强制结束后等待进程退出的秒数
+    #[serde(rename = "killTimeoutSecs")]
+    pub kill_timeout_secs: u64,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: 5,
+            kill_timeout_secs: 10,
+        }
+    }
+}
+
+/// 启动 RoxyBrowser 后的就绪检测：主进程稳定运行且本地 API 端口可以连接
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StartupSettings {
+    /// 启动后是否等待就绪再返回
+    #[serde(rename = "waitUntilReady")]
+    pub wait_until_ready: bool,
+    #[serde(rename = "readyTimeoutSecs")]
+    pub ready_timeout_secs: u64,
+    /// 主进程需要持续运行的毫秒数
+    #[serde(rename = "stableMs")]
+    pub stable_ms: u64,
+    /// RoxyBrowser 本地 API 端口
+    #[serde(rename = "apiPort")]
+    pub api_port: u16,
+}
+
+impl Default for StartupSettings {
+    fn default() -> Self {
+        Self {
+            wait_until_ready: false,
+            ready_timeout_secs: 60,
+            stable_ms: 2000,
+            api_port: 50000,
+        }
+    }
+}
+
+/// 崩溃自动重启策略：时间窗口内最多重启 N 次，每次重启前的等待时间按次数翻倍
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchdogSettings {
+    pub enabled: bool,
+    /// 时间窗口内最多重启的次数
+    #[serde(rename = "maxRestarts")]
+    pub max_restarts: u32,
+    #[serde(rename = "windowSecs")]
+    pub window_secs: u64,
+    /// 第一次重启前的等待时间
+    #[serde(rename = "backoffSecs")]
+    pub backoff_secs: u64,
+    #[serde(rename = "maxBackoffSecs")]
+    pub max_backoff_secs: u64,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_restarts: 3,
+            window_secs: 600,
+            backoff_secs: 5,
+            max_backoff_secs: 60,
+        }
+    }
+}
+
+/// 备份项：相对 RoxyBrowser 数据目录的路径或 glob 模式
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupItem {
+    /// 例如 `Cookies`、`browser-cache/*`、`Service Worker`
+    pub pattern: String,
+    /// 必需项缺失时备份和恢复都会失败，可选项缺失时跳过
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// 备份项集合：决定备份和恢复哪些文件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupItemSet {
+    pub id: String,
+    pub name: String,
+    pub include: Vec<BackupItem>,
+    /// 排除的 glob 模式，匹配相对 RoxyBrowser 数据目录的文件或目录路径
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// 某些 RoxyBrowser 版本使用的备份项集合
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VersionItemSet {
+    /// 版本号前缀，按点分段匹配：`3.5` 匹配 3.5 和 3.5.2，不匹配 3.50
+    pub version: String,
+    #[serde(rename = "setId")]
+    pub set_id: String,
+}
+
+/// 备份项集合配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupItemSetsConfig {
+    /// 内置集合在前，自定义集合在后
+    pub sets: Vec<BackupItemSet>,
+    #[serde(rename = "defaultSetId")]
+    pub default_set_id: String,
+    #[serde(rename = "versionSets")]
+    pub version_sets: Vec<VersionItemSet>,
+    /// 当前 RoxyBrowser 的版本号，无法确定时为空
+    #[serde(rename = "detectedVersion")]
+    pub detected_version: Option<String>,
+}
+
 /// 获取配置文件路径
-fn get_settings_path() -> Result<PathBuf, String> {
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| "无法获取配置目录".to_string())?;
+fn get_settings_path() -> Result<PathBuf, AppError> {
+    #[cfg(test)]
+    let config_dir = super::testing::root();
+    #[cfg(not(test))]
+    let config_dir = dirs::config_dir();
+    let config_dir =
+        config_dir.ok_or_else(|| AppError::new(ErrorCode::Io, "config.dirUnavailable"))?;
     
     let app_config_dir = config_dir.join("roxybrowser-manager");
     
     // 确保目录存在
     if !app_config_dir.exists() {
         std::fs::create_dir_all(&app_config_dir)
-            .map_err(|e| format!("无法创建配置目录: {}", e))?;
+            .map_err(|e| AppError::new(ErrorCode::Io, "config.createDir").caused_by(e))?;
     }
     
     Ok(app_config_dir.join("settings.json"))
 }
 
 /// 加载设置
-pub fn load_settings() -> Result<AppSettings, String> {
+pub fn load_settings() -> Result<AppSettings, AppError> {
     let settings_path = get_settings_path()?;
     
     if !settings_path.exists() {
@@ -48,60 +277,39 @@ pub fn load_settings() -> Result<AppSettings, String> {
     }
     
     let content = std::fs::read_to_string(&settings_path)
-        .map_err(|e| format!("无法读取配置文件: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Io, "config.read").caused_by(e))?;
     
     let settings: AppSettings = serde_json::from_str(&content)
-        .map_err(|e| format!("配置文件格式错误: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::InvalidSettings, "config.parse").caused_by(e))?;
     
     Ok(settings)
 }
 
 /// 保存设置
-pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
+pub fn save_settings(settings: &AppSettings) -> Result<(), AppError> {
     let settings_path = get_settings_path()?;
     
     let content = serde_json::to_string_pretty(settings)
-        .map_err(|e| format!("无法序列化配置: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Internal, "config.serialize").caused_by(e))?;
+
+    super::profile::write_file_atomic(&settings_path, content.as_bytes())
+        .map_err(|e| AppError::new(ErrorCode::Io, "config.write").caused_by(e))?;
     
-    std::fs::write(&settings_path, content)
-        .map_err(|e| format!("无法写入配置文件: {}", e))?;
+    // 启动设置可能改变了 RoxyBrowser 的位置
+    super::process::invalidate_roxy_executables();
     
     Ok(())
 }
 
-/// 验证 RoxyBrowser 可执行文件路径
-pub fn validate_path(path: &str) -> Result<bool, String> {
-    let path_buf = PathBuf::from(path);
-    
-    // 检查文件是否存在
-    if !path_buf.exists() {
+/// 验证 RoxyBrowser 路径：可执行文件、AppImage、macOS 应用包或 .desktop 启动项，且名称或 Bundle ID 表明是 RoxyBrowser
+pub fn validate_path(path: &str) -> Result<bool, AppError> {
+    let Some(target) = super::launcher::target_for_path(Path::new(path)) else {
         return Ok(false);
-    }
-    
-    // 检查是否是文件
-    if !path_buf.is_file() {
+    };
+    if !target.is_roxy() {
+        tracing::warn!(path, "所选程序不是 RoxyBrowser");
         return Ok(false);
     }
-    
-    // 检查文件名是否为 RoxyBrowser.exe (Windows) 或 RoxyBrowser (其他平台)
-    if let Some(file_name) = path_buf.file_name() {
-        let name = file_name.to_string_lossy();
-        #[cfg(target_os = "windows")]
-        {
-            if name.to_lowercase() != "roxybrowser.exe" {
-                return Ok(false);
-            }
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            if name != "RoxyBrowser" {
-                return Ok(false);
-            }
-        }
-    } else {
-        return Ok(false);
-    }
-    
     Ok(true)
 }
 
@@ -130,12 +338,40 @@ pub fn get_enhanced_default_paths() -> Vec<PathBuf> {
     #[cfg(target_os = "macos")]
     {
         paths.push(PathBuf::from("/Applications/RoxyBrowser.app"));
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join("Applications").join("RoxyBrowser.app"));
+        }
     }
     
     #[cfg(target_os = "linux")]
     {
+        paths.push(PathBuf::from("/opt/RoxyBrowser/roxybrowser"));
         paths.push(PathBuf::from("/usr/bin/roxybrowser"));
         paths.push(PathBuf::from("/usr/local/bin/roxybrowser"));
+
+        // AppImage 通常放在 ~/Applications 或 ~/.local/bin
+        if let Some(home) = dirs::home_dir() {
+            for dir in [home.join("Applications"), home.join(".local").join("bin")] {
+                let Ok(entries) = std::fs::read_dir(&dir) else {
+                    continue;
+                };
+                let mut appimages: Vec<PathBuf> = entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| {
+                        let name = p
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_lowercase();
+                        name.starts_with("roxybrowser") && name.ends_with(".appimage")
+                    })
+                    .collect();
+                appimages.sort();
+                paths.extend(appimages);
+            }
+            paths.push(home.join(".local").join("bin").join("roxybrowser"));
+        }
     }
     
     paths
@@ -143,21 +379,24 @@ pub fn get_enhanced_default_paths() -> Vec<PathBuf> {
 
 /// Tauri 命令: 获取当前配置的 RoxyBrowser 可执行文件路径
 #[tauri::command]
-pub fn get_roxy_exe_path() -> Result<Option<String>, String> {
+pub fn get_roxy_exe_path() -> Result<Option<String>, AppError> {
     let settings = load_settings()?;
     Ok(settings.roxy_exe_path)
 }
 
 /// Tauri 命令: 设置 RoxyBrowser 可执行文件路径
 #[tauri::command]
-pub fn set_roxy_exe_path(path: String) -> Result<(), String> {
+pub fn set_roxy_exe_path(path: String) -> Result<(), AppError> {
     // 验证路径
     if !validate_path(&path)? {
-        return Err("无效的 RoxyBrowser 可执行文件路径".to_string());
+        return Err(AppError::new(
+            ErrorCode::InvalidPath,
+            "path.invalidExecutable",
+        ));
     }
     
-    // 加载现有设置
-    let mut settings = load_settings().unwrap_or_default();
+    // 加载现有设置，读取失败时不能用默认值覆盖
+    let mut settings = load_settings()?;
     
     // 更新路径
     settings.roxy_exe_path = Some(path);
@@ -170,34 +409,34 @@ pub fn set_roxy_exe_path(path: String) -> Result<(), String> {
 
 /// Tauri 命令: 验证 RoxyBrowser 可执行文件路径
 #[tauri::command]
-pub fn validate_roxy_exe_path(path: String) -> Result<bool, String> {
+pub fn validate_roxy_exe_path(path: String) -> Result<bool, AppError> {
     validate_path(&path)
 }
 
 /// Tauri 命令: 自动检测 RoxyBrowser 路径
 #[tauri::command]
-pub fn auto_detect_roxy_path() -> Result<Option<String>, String> {
-    let default_paths = get_enhanced_default_paths();
-    
-    for path in default_paths {
-        if path.exists() && path.is_file() {
-            return Ok(Some(path.to_string_lossy().to_string()));
-        }
-    }
-    
-    Ok(None)
+pub fn auto_detect_roxy_path() -> Result<Option<String>, AppError> {
+    let detected = super::launcher::detect_paths().into_iter().next();
+    Ok(detected.map(|path| path.to_string_lossy().to_string()))
 }
 
 /// Tauri 命令: 使用文件对话框选择可执行文件
 #[tauri::command]
-pub async fn browse_for_exe(app: tauri::AppHandle) -> Result<Option<String>, String> {
+pub async fn browse_for_exe(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
     use tauri_plugin_dialog::DialogExt;
     
-    let file_path = app.dialog()
+    let dialog = app
+        .dialog()
         .file()
-        .set_title("选择 RoxyBrowser 可执行文件")
-        .add_filter("可执行文件", &["exe"])
-        .blocking_pick_file();
+        .set_title(i18n::text("dialog.selectExecutable"));
+
+    // Linux 的可执行文件通常没有扩展名，不设置过滤
+    #[cfg(target_os = "windows")]
+    let dialog = dialog.add_filter(i18n::text("dialog.executableFilter"), &["exe"]);
+    #[cfg(target_os = "macos")]
+    let dialog = dialog.add_filter(i18n::text("dialog.applicationFilter"), &["app"]);
+
+    let file_path = dialog.blocking_pick_file();
     
     if let Some(path) = file_path {
         if let Some(path_ref) = path.as_path() {
@@ -207,31 +446,72 @@ pub async fn browse_for_exe(app: tauri::AppHandle) -> Result<Option<String>, Str
             if validate_path(&path_str)? {
                 Ok(Some(path_str))
             } else {
-                Err("所选文件不是有效的 RoxyBrowser 可执行文件".to_string())
+                Err(AppError::new(
+                    ErrorCode::InvalidPath,
+                    "path.invalidSelection",
+                ))
             }
         } else {
-            Err("无法获取文件路径".to_string())
+            Err(AppError::new(
+                ErrorCode::InvalidPath,
+                "path.fileUnavailable",
+            ))
         }
     } else {
         Ok(None)
     }
 }
 
+/// Tauri 命令: 使用文件对话框选择 `.roxybak` 备份归档
+#[tauri::command]
+pub async fn browse_for_archive(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let file_path = app
+        .dialog()
+        .file()
+        .set_title(i18n::text("dialog.selectArchive"))
+        .add_filter(
+            i18n::text("dialog.archiveFilter"),
+            &[super::archive::ARCHIVE_EXTENSION],
+        )
+        .blocking_pick_file();
+
+    match file_path {
+        Some(path) => match path.as_path() {
+            Some(path_ref) => Ok(Some(path_ref.to_string_lossy().to_string())),
+            None => Err(AppError::new(
+                ErrorCode::InvalidPath,
+                "path.fileUnavailable",
+            )),
+        },
+        None => Ok(None),
+    }
+}
+
 /// Tauri 命令: 使用文件对话框选择文件夹
+///
+/// `title_key` 是对话框标题在消息目录中的键，按当前界面语言显示。
 #[tauri::command]
-pub async fn browse_for_folder(app: tauri::AppHandle, title: String) -> Result<Option<String>, String> {
+pub async fn browse_for_folder(
+    app: tauri::AppHandle,
+    title_key: String,
+) -> Result<Option<String>, AppError> {
     use tauri_plugin_dialog::DialogExt;
     
     let folder_path = app.dialog()
         .file()
-        .set_title(&title)
+        .set_title(i18n::text(&title_key))
         .blocking_pick_folder();
     
     if let Some(path) = folder_path {
         if let Some(path_ref) = path.as_path() {
             Ok(Some(path_ref.to_string_lossy().to_string()))
         } else {
-            Err("无法获取文件夹路径".to_string())
+            Err(AppError::new(
+                ErrorCode::InvalidPath,
+                "path.folderUnavailable",
+            ))
         }
     } else {
         Ok(None)
@@ -240,9 +520,331 @@ pub async fn browse_for_folder(app: tauri::AppHandle, title: String) -> Result<O
 
 /// Tauri 命令: 清除配置的路径
 #[tauri::command]
-pub fn clear_roxy_exe_path() -> Result<(), String> {
-    let mut settings = load_settings().unwrap_or_default();
+pub fn clear_roxy_exe_path() -> Result<(), AppError> {
+    let mut settings = load_settings()?;
     settings.roxy_exe_path = None;
     save_settings(&settings)?;
     Ok(())
 }
+
+/// Tauri 命令: 获取快照保留策略
+#[tauri::command]
+pub fn get_snapshot_retention() -> Result<SnapshotRetention, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.snapshot_retention)
+}
+
+/// Tauri 命令: 设置快照保留策略
+#[tauri::command]
+pub fn set_snapshot_retention(retention: SnapshotRetention) -> Result<(), AppError> {
+    if !(1..=1000).contains(&retention.keep_last) {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.keepLastRange",
+        ));
+    }
+    if retention.keep_daily_days > 3650 {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.keepDailyDaysRange",
+        ));
+    }
+
+    let mut settings = load_settings()?;
+    settings.snapshot_retention = retention;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取备份项集合（包含内置集合）
+#[tauri::command]
+pub fn get_backup_item_sets() -> Result<BackupItemSetsConfig, AppError> {
+    let settings = load_settings()?;
+    let mut sets = vec![super::itemset::builtin_set()];
+    sets.extend(settings.backup_item_sets);
+    Ok(BackupItemSetsConfig {
+        sets,
+        default_set_id: settings
+            .default_backup_item_set
+            .unwrap_or_else(|| super::itemset::BUILTIN_SET_ID.to_string()),
+        version_sets: settings.version_backup_item_sets,
+        detected_version: super::launcher::detect_version(),
+    })
+}
+
+/// Tauri 命令: 保存自定义备份项集合、默认集合和按版本选择的集合（`version_sets` 为 `None` 时保持不变）
+#[tauri::command]
+pub fn set_backup_item_sets(
+    sets: Vec<BackupItemSet>,
+    default_set_id: Option<String>,
+    version_sets: Option<Vec<VersionItemSet>>,
+) -> Result<(), AppError> {
+    super::itemset::validate_sets(&sets)?;
+    let set_exists =
+        |id: &str| id == super::itemset::BUILTIN_SET_ID || sets.iter().any(|s| s.id == id);
+    if let Some(ref id) = default_set_id {
+        if !set_exists(id) {
+            return Err(
+                AppError::new(ErrorCode::ItemSetNotFound, "itemSet.notFound").with("id", id),
+            );
+        }
+    }
+    if let Some(ref version_sets) = version_sets {
+        super::itemset::validate_version_sets(version_sets, set_exists)?;
+    }
+
+    let mut settings = load_settings()?;
+    settings.backup_item_sets = sets;
+    settings.default_backup_item_set = default_set_id;
+    if let Some(version_sets) = version_sets {
+        settings.version_backup_item_sets = version_sets;
+    }
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取排除规则
+#[tauri::command]
+pub fn get_exclude_patterns() -> Result<Vec<String>, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.exclude_patterns)
+}
+
+/// Tauri 命令: 设置排除规则，传 `None` 恢复默认规则
+#[tauri::command]
+pub fn set_exclude_patterns(patterns: Option<Vec<String>>) -> Result<(), AppError> {
+    let patterns = patterns.unwrap_or_else(default_exclude_patterns);
+    super::itemset::ExcludeFilter::new(&patterns)?;
+
+    let mut settings = load_settings()?;
+    settings.exclude_patterns = patterns;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取停止 RoxyBrowser 的等待时间
+#[tauri::command]
+pub fn get_shutdown_settings() -> Result<ShutdownSettings, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.shutdown)
+}
+
+/// Tauri 命令: 设置停止 RoxyBrowser 的等待时间
+#[tauri::command]
+pub fn set_shutdown_settings(shutdown: ShutdownSettings) -> Result<(), AppError> {
+    if shutdown.grace_period_secs > 300 || shutdown.kill_timeout_secs > 300 {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.shutdownTooLong",
+        ));
+    }
+    if shutdown.kill_timeout_secs == 0 {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.killTimeoutTooShort",
+        ));
+    }
+
+    let mut settings = load_settings()?;
+    settings.shutdown = shutdown;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取进程状态检测间隔（毫秒）
+#[tauri::command]
+pub fn get_process_watch_interval() -> Result<u64, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.process_watch_interval_ms)
+}
+
+/// Tauri 命令: 设置进程状态检测间隔（毫秒），下一次检测时生效
+#[tauri::command]
+pub fn set_process_watch_interval(interval_ms: u64) -> Result<(), AppError> {
+    if !(500..=60_000).contains(&interval_ms) {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.watchIntervalRange",
+        ));
+    }
+
+    let mut settings = load_settings()?;
+    settings.process_watch_interval_ms = interval_ms;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取启动就绪检测设置
+#[tauri::command]
+pub fn get_startup_settings() -> Result<StartupSettings, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.startup)
+}
+
+/// Tauri 命令: 设置启动就绪检测
+#[tauri::command]
+pub fn set_startup_settings(startup: StartupSettings) -> Result<(), AppError> {
+    if startup.ready_timeout_secs == 0 || startup.ready_timeout_secs > 600 {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.readyTimeoutRange",
+        ));
+    }
+    if startup.api_port == 0 {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.invalidPort",
+        ));
+    }
+
+    let mut settings = load_settings()?;
+    settings.startup = startup;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取默认启动配置
+#[tauri::command]
+pub fn get_default_launch_config() -> Result<LaunchConfig, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.default_launch_config)
+}
+
+/// Tauri 命令: 设置默认启动配置
+#[tauri::command]
+pub fn set_default_launch_config(launch_config: LaunchConfig) -> Result<(), AppError> {
+    launch_config.validate()?;
+
+    let mut settings = load_settings()?;
+    settings.default_launch_config = launch_config;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取崩溃自动重启策略
+#[tauri::command]
+pub fn get_watchdog_settings() -> Result<WatchdogSettings, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.watchdog)
+}
+
+/// Tauri 命令: 设置崩溃自动重启策略
+#[tauri::command]
+pub fn set_watchdog_settings(watchdog: WatchdogSettings) -> Result<(), AppError> {
+    if watchdog.window_secs == 0 {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.watchdogWindow",
+        ));
+    }
+    if watchdog.backoff_secs > watchdog.max_backoff_secs {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "settings.watchdogBackoff",
+        ));
+    }
+
+    let mut settings = load_settings()?;
+    settings.watchdog = watchdog;
+    save_settings(&settings)?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取界面语言
+#[tauri::command]
+pub fn get_language() -> Result<Language, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.language)
+}
+
+/// Tauri 命令: 设置界面语言，立即重建托盘菜单，之后的错误消息和对话框使用新语言
+#[tauri::command]
+pub fn set_language(app: tauri::AppHandle, language: Language) -> Result<(), AppError> {
+    let mut settings = load_settings()?;
+    settings.language = language;
+    save_settings(&settings)?;
+    i18n::set_current_language(language);
+
+    super::tray::rebuild_menu(&app).map_err(|e| {
+        AppError::new(ErrorCode::Internal, "settings.trayUpdateFailed").caused_by(e)
+    })?;
+    Ok(())
+}
+
+/// Tauri 命令: 获取日志级别
+#[tauri::command]
+pub fn get_log_level() -> Result<LogLevel, AppError> {
+    let settings = load_settings()?;
+    Ok(settings.log_level)
+}
+
+/// Tauri 命令: 设置日志级别，立即生效
+#[tauri::command]
+pub fn set_log_level(level: LogLevel) -> Result<(), AppError> {
+    let mut settings = load_settings()?;
+    settings.log_level = level;
+    save_settings(&settings)?;
+    logging::set_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::TestRoot;
+
+    #[test]
+    fn setters_keep_settings_that_fail_to_load() {
+        let _root = TestRoot::new("settings-corrupt");
+        let path = get_settings_path().unwrap();
+        std::fs::write(&path, "{\"excludePatterns\": [").unwrap();
+
+        let err = set_snapshot_retention(SnapshotRetention::default()).unwrap_err();
+        assert_eq!(
+            (err.code, err.key),
+            (ErrorCode::InvalidSettings, "config.parse")
+        );
+        assert!(set_log_level(LogLevel::default()).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"excludePatterns\": ["
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        set_snapshot_retention(SnapshotRetention {
+            keep_last: 3,
+            keep_daily_days: 0,
+        })
+        .unwrap();
+        assert_eq!(load_settings().unwrap().snapshot_retention.keep_last, 3);
+        assert!(!path.with_file_name(".settings.json.tmp").exists());
+    }
+
+    #[test]
+    fn rejects_out_of_range_snapshot_retention() {
+        let _root = TestRoot::new("settings-retention");
+        let retention = |keep_last, keep_daily_days| SnapshotRetention {
+            keep_last,
+            keep_daily_days,
+        };
+
+        let err = set_snapshot_retention(retention(0, 7)).unwrap_err();
+        assert_eq!(
+            (err.code, err.key),
+            (ErrorCode::InvalidSettings, "settings.keepLastRange")
+        );
+        let err = set_snapshot_retention(retention(10, 100_000_000)).unwrap_err();
+        assert_eq!(
+            (err.code, err.key),
+            (ErrorCode::InvalidSettings, "settings.keepDailyDaysRange")
+        );
+        assert!(!get_settings_path().unwrap().exists());
+
+        set_snapshot_retention(retention(1000, 3650)).unwrap();
+        assert_eq!(
+            load_settings().unwrap().snapshot_retention.keep_daily_days,
+            3650
+        );
+    }
+}
+
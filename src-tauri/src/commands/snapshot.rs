@@ -0,0 +1,306 @@
+use super::operation::Description;
+use super::profile::{backup_roxy_data_pinned, load_config};
+use super::settings::SnapshotRetention;
+use super::store::{self, ProfileManifest, SkippedEntry};
+use super::{operation, progress};
+use crate::error::AppError;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+/// 快照摘要
+#[derive(Debug, Serialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "fileCount")]
+    pub file_count: usize,
+    #[serde(rename = "totalSize")]
+    pub total_size: u64,
+    /// 备份时使用的备份项集合
+    #[serde(rename = "itemSet")]
+    pub item_set: Option<String>,
+    /// 因排除规则未备份的路径
+    pub skipped: Vec<SkippedEntry>,
+    /// 是否为该用户当前使用的备份
+    #[serde(rename = "isCurrent")]
+    pub is_current: bool,
+}
+
+/// 快照恢复目标
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum RestoreTarget {
+    /// 只替换管理器中保存的用户备份
+    #[serde(rename = "profile")]
+    Profile,
+    /// 直接恢复到 RoxyBrowser 数据目录并切换到该用户
+    #[serde(rename = "live")]
+    Live,
+}
+
+/// 按保留策略清理用户的旧快照，返回删除数量；`pinned` 指定的快照始终保留
+pub fn prune_snapshots(
+    user_id: &str,
+    retention: &SnapshotRetention,
+    pinned: Option<&str>,
+) -> Result<usize, AppError> {
+    let ids = store::list_snapshot_ids(user_id);
+    let head = store::load_manifest(user_id)?.and_then(|m| m.snapshot_id);
+    let keep = snapshots_to_keep(&ids, head.as_deref(), retention, Utc::now());
+
+    let mut removed = 0;
+    for id in &ids {
+        if !keep.contains(id.as_str()) && pinned != Some(id.as_str()) {
+            store::remove_snapshot(user_id, id)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// 需要保留的快照（`ids` 从旧到新）
+///
+/// 保留最近 `keep_last` 个，以及最近 `keep_daily_days` 天内每天最新的一个；
+/// 当前清单对应的快照始终保留。
+fn snapshots_to_keep<'a>(
+    ids: &'a [String],
+    head: Option<&'a str>,
+    retention: &SnapshotRetention,
+    now: DateTime<Utc>,
+) -> HashSet<&'a str> {
+    let mut keep: HashSet<&str> = ids
+        .iter()
+        .rev()
+        .take(retention.keep_last.max(1) as usize)
+        .map(String::as_str)
+        .collect();
+
+    // 编号以 UTC 日期开头，可直接按字符串比较；天数超出日期范围时不限制日期
+    let cutoff = now
+        .checked_sub_signed(Duration::days(retention.keep_daily_days as i64))
+        .map(|cutoff| cutoff.format("%Y%m%d").to_string())
+        .unwrap_or_default();
+    let mut days = HashSet::new();
+    for id in ids.iter().rev() {
+        let day = id.get(..8).unwrap_or(id);
+        if retention.keep_daily_days > 0 && day > cutoff.as_str() && days.insert(day) {
+            keep.insert(id);
+        }
+    }
+
+    keep.extend(head);
+    keep
+}
+
+/// 备份当前用户后把快照换入 RoxyBrowser 数据目录，调用前 RoxyBrowser 必须已停止
+///
+/// 当前实时数据也先留一份快照，恢复后仍可找回；恢复的是当前用户自己的旧快照时，
+/// 这次备份清理旧快照不能删掉它，否则它的 blob 会被回收。
+fn swap_in_snapshot(
+    current_user: Option<String>,
+    snapshot: &ProfileManifest,
+) -> Result<(), AppError> {
+    if let Some(ref current) = current_user {
+        let pinned = snapshot
+            .snapshot_id
+            .as_deref()
+            .filter(|_| *current == snapshot.user_id);
+        backup_roxy_data_pinned(current, pinned)?;
+    }
+
+    store::set_head(snapshot)?;
+    super::switch::run_switch(current_user, &snapshot.user_id)
+}
+
+/// 列出用户的快照，最新的在前
+#[tauri::command]
+pub fn list_snapshots(user_id: String) -> Result<Vec<SnapshotInfo>, AppError> {
+    store::ensure_safe_name(&user_id)?;
+    let head = store::load_manifest(&user_id)?.and_then(|m| m.snapshot_id);
+
+    let mut snapshots = Vec::new();
+    for id in store::list_snapshot_ids(&user_id).into_iter().rev() {
+        let manifest = store::load_snapshot(&user_id, &id)?;
+        snapshots.push(SnapshotInfo {
+            is_current: head.as_deref() == Some(id.as_str()),
+            user_id: user_id.clone(),
+            created_at: manifest.created_at,
+            file_count: manifest.files.len(),
+            total_size: manifest.files.iter().map(|f| f.size).sum(),
+            item_set: manifest.item_set,
+            skipped: manifest.skipped,
+            id,
+        });
+    }
+
+    Ok(snapshots)
+}
+
+/// 将用户恢复到指定快照
+///
+/// `profile` 只替换管理器中的备份，下次切换到该用户时生效；
+/// `live` 会先备份当前用户，再把快照换入 RoxyBrowser 数据目录并重新启动。
+#[tauri::command]
+pub async fn restore_snapshot(
+    app: AppHandle,
+    user_id: String,
+    snapshot_id: String,
+    target: RestoreTarget,
+) -> Result<(), AppError> {
+    progress::run_blocking(app, "restore_snapshot", move || {
+        restore_snapshot_blocking(user_id, snapshot_id, target)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "restore_snapshot", skip_all, fields(user = %user_id, snapshot = %snapshot_id), err(Display))]
+pub fn restore_snapshot_blocking(
+    user_id: String,
+    snapshot_id: String,
+    target: RestoreTarget,
+) -> Result<(), AppError> {
+    store::ensure_safe_name(&user_id)?;
+    store::ensure_safe_name(&snapshot_id)?;
+    let _operation = operation::begin(
+        "restore_snapshot",
+        Description::new("activity.restoreSnapshot")
+            .with("user", &user_id)
+            .with("id", &snapshot_id),
+    )?;
+    let config = load_config()?;
+    if config.find_user(&user_id).is_none() {
+        return Err(AppError::user_not_found(&user_id));
+    }
+
+    let snapshot = store::load_snapshot(&user_id, &snapshot_id)?;
+
+    match target {
+        RestoreTarget::Profile => {
+            store::set_head(&snapshot)?;
+        }
+        RestoreTarget::Live => {
+            super::vault::ensure_unlocked()?;
+            super::process::shutdown_roxy()?;
+            swap_in_snapshot(config.current_user, &snapshot)?;
+            super::process::launch_roxy(None)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::profile::{backup_roxy_data, get_roxy_data_dir};
+    use crate::commands::settings::{load_settings, save_settings};
+    use crate::commands::testing::{save_users, TestRoot};
+    use chrono::TimeZone;
+    use std::fs;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn sorted(keep: HashSet<&str>) -> Vec<&str> {
+        let mut keep: Vec<_> = keep.into_iter().collect();
+        keep.sort();
+        keep
+    }
+
+    #[test]
+    fn keeps_recent_daily_and_head() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let ids = ids(&[
+            "20261010T080000000Z",
+            "20261014T090000000Z",
+            "20261016T080000000Z",
+            "20261016T090000000Z",
+            "20261017T100000000Z",
+            "20261018T080000000Z",
+            "20261018T090000000Z",
+        ]);
+        let retention = SnapshotRetention {
+            keep_last: 2,
+            keep_daily_days: 3,
+        };
+        let keep = snapshots_to_keep(&ids, Some("20261010T080000000Z"), &retention, now);
+        assert_eq!(
+            sorted(keep),
+            [
+                "20261010T080000000Z",
+                "20261016T090000000Z",
+                "20261017T100000000Z",
+                "20261018T080000000Z",
+                "20261018T090000000Z",
+            ]
+        );
+    }
+
+    #[test]
+    fn always_keeps_the_latest() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let ids = ids(&["20261017T080000000Z", "20261018T080000000Z"]);
+        let retention = SnapshotRetention {
+            keep_last: 0,
+            keep_daily_days: 0,
+        };
+        let keep = snapshots_to_keep(&ids, None, &retention, now);
+        assert_eq!(sorted(keep), ["20261018T080000000Z"]);
+        assert!(snapshots_to_keep(&[], None, &retention, now).is_empty());
+    }
+
+    #[test]
+    fn keeps_every_day_when_the_window_exceeds_the_calendar() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let ids = ids(&[
+            "19700101T080000000Z",
+            "20261017T080000000Z",
+            "20261018T080000000Z",
+        ]);
+        let retention = SnapshotRetention {
+            keep_last: 1,
+            keep_daily_days: u32::MAX,
+        };
+        assert_eq!(snapshots_to_keep(&ids, None, &retention, now).len(), 3);
+    }
+
+    #[test]
+    fn live_restore_keeps_the_restored_snapshot() {
+        let _root = TestRoot::new("restore");
+        let mut settings = load_settings().unwrap();
+        settings.snapshot_retention = SnapshotRetention {
+            keep_last: 3,
+            keep_daily_days: 0,
+        };
+        save_settings(&settings).unwrap();
+        save_users(&["a"], Some("a"));
+
+        // 每次内容长度不同，避免按大小和修改时间复用上一次的哈希
+        let live = get_roxy_data_dir();
+        fs::create_dir_all(&live).unwrap();
+        for n in 1..=3 {
+            fs::write(live.join("Cookies"), "x".repeat(n)).unwrap();
+            backup_roxy_data("a").unwrap();
+        }
+        fs::write(live.join("Cookies"), "x".repeat(4)).unwrap();
+        let ids = store::list_snapshot_ids("a");
+        assert_eq!(ids.len(), 3);
+
+        // 恢复前的备份产生第 4 个快照，超出 keep_last，正在恢复的最旧快照仍须保留
+        let oldest = store::load_snapshot("a", &ids[0]).unwrap();
+        swap_in_snapshot(Some("a".to_string()), &oldest).unwrap();
+
+        assert_eq!(fs::read_to_string(live.join("Cookies")).unwrap(), "x");
+        let head = store::load_manifest("a").unwrap().unwrap();
+        assert_eq!(head.snapshot_id, oldest.snapshot_id);
+        let ids_after = store::list_snapshot_ids("a");
+        assert!(ids_after.contains(&ids[0]), "{:?}", ids_after);
+        assert_eq!(ids_after.len(), 4, "{:?}", ids_after);
+    }
+}
@@ -0,0 +1,1104 @@
+use super::crypto::{self, SecretKey};
+use super::itemset::{self, ExcludeFilter, ResolvedItems};
+use super::profile::{get_app_config_dir, get_profiles_dir, write_file_atomic};
+use super::progress::{self, ProgressPhase};
+use super::{operation, vault};
+use crate::error::{AppError, ErrorCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// 当前清单格式版本
+const MANIFEST_VERSION: u32 = 1;
+
+/// 清单中的单个文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// 相对 RoxyBrowser 数据目录的路径，统一使用 `/` 分隔
+    pub path: String,
+    pub size: u64,
+    /// 文件内容的 SHA-256（十六进制）；未启用存储加密时同时也是 blob 的文件名
+    pub hash: String,
+    /// 修改时间（Unix 毫秒），用于跳过未变化文件的重新哈希
+    #[serde(default)]
+    pub modified: Option<i64>,
+}
+
+/// 备份时因排除规则跳过的路径
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub path: String,
+    #[serde(rename = "isDir")]
+    pub is_dir: bool,
+}
+
+/// 某个用户备份的清单：记录文件列表及其内容哈希
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileManifest {
+    pub version: u32,
+    /// 用户 ID，旧版本的清单中记为邮箱
+    #[serde(rename = "userId", alias = "email")]
+    pub user_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    /// 对应的快照编号，保存时分配
+    #[serde(rename = "snapshotId", default)]
+    pub snapshot_id: Option<String>,
+    /// 备份时使用的备份项集合
+    #[serde(rename = "itemSet", default)]
+    pub item_set: Option<String>,
+    /// 备份覆盖的路径，恢复时这些路径会被整体替换
+    #[serde(default)]
+    pub roots: Vec<String>,
+    /// 需要重建的目录（包括空目录）
+    #[serde(default)]
+    pub dirs: Vec<String>,
+    pub files: Vec<ManifestEntry>,
+    /// 因排除规则未备份的路径（被排除的目录只记录目录本身）
+    #[serde(default)]
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// 内容寻址存储根目录
+pub fn get_store_dir() -> PathBuf {
+    get_app_config_dir().join("store")
+}
+
+fn get_blobs_dir() -> PathBuf {
+    get_store_dir().join("blobs")
+}
+
+fn get_manifests_dir() -> PathBuf {
+    get_store_dir().join("manifests")
+}
+
+/// 检查用作存储路径一段的名称（用户邮箱、快照编号）
+///
+/// 只接受单个普通路径段，`..`、绝对路径和含分隔符的名称都会被拒绝，
+/// 防止导入的配置或前端参数让读写、删除落到存储目录之外。
+pub fn ensure_safe_name(name: &str) -> Result<(), AppError> {
+    let mut components = Path::new(name).components();
+    let single = matches!(components.next(), Some(Component::Normal(c)) if c == name)
+        && components.next().is_none();
+    if !single || name.contains(['/', '\\', ':', '\0']) {
+        return Err(AppError::new(ErrorCode::InvalidPath, "store.invalidName").with("name", name));
+    }
+    Ok(())
+}
+
+fn get_manifest_path(user_id: &str) -> Result<PathBuf, AppError> {
+    ensure_safe_name(user_id)?;
+    Ok(get_manifests_dir().join(format!("{}.json", user_id)))
+}
+
+fn get_snapshots_dir(user_id: &str) -> Result<PathBuf, AppError> {
+    ensure_safe_name(user_id)?;
+    Ok(get_store_dir().join("snapshots").join(user_id))
+}
+
+fn get_snapshot_path(user_id: &str, snapshot_id: &str) -> Result<PathBuf, AppError> {
+    ensure_safe_name(snapshot_id)?;
+    Ok(get_snapshots_dir(user_id)?.join(format!("{}.json", snapshot_id)))
+}
+
+/// blob 文件路径：按文件名前两位分桶，避免单目录文件过多
+fn blob_file(name: &str) -> PathBuf {
+    let bucket = name.get(..2).unwrap_or("00");
+    get_blobs_dir().join(bucket).join(name)
+}
+
+/// 内容哈希对应的 blob 路径
+///
+/// 启用存储加密时文件名为 [`crypto::blob_name`]，加密迁移未完成时回退到以哈希命名的旧文件。
+pub fn get_blob_path(hash: &str, key: Option<&SecretKey>) -> PathBuf {
+    let Some(key) = key else {
+        return blob_file(hash);
+    };
+    let keyed = blob_file(&crypto::blob_name(key, hash));
+    if !keyed.exists() && blob_file(hash).exists() {
+        return blob_file(hash);
+    }
+    keyed
+}
+
+/// 读取清单或快照文件，加密的文件需要存储已解锁
+fn read_manifest_file(
+    path: &Path,
+    read_failed: impl FnOnce(io::Error) -> AppError,
+) -> Result<Vec<u8>, AppError> {
+    let data = fs::read(path).map_err(read_failed)?;
+    if !crypto::is_sealed_data(&data) {
+        return Ok(data);
+    }
+    let key = vault::current_key()?
+        .ok_or_else(|| AppError::new(ErrorCode::StoreLocked, "vault.locked"))?;
+    open_manifest_data(&data, &key)
+}
+
+fn open_manifest_data(data: &[u8], key: &SecretKey) -> Result<Vec<u8>, AppError> {
+    let mut plain = Vec::new();
+    crypto::open_blob(key, &mut &data[..], &mut plain)?;
+    Ok(plain)
+}
+
+/// 读取用户清单，不存在时返回 `None`
+pub fn load_manifest(user_id: &str) -> Result<Option<ProfileManifest>, AppError> {
+    let path = get_manifest_path(user_id)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = read_manifest_file(&path, |e| {
+        AppError::new(ErrorCode::Io, "store.readManifest")
+            .with("user", user_id)
+            .caused_by(e)
+    })?;
+    let manifest = serde_json::from_slice(&content).map_err(|e| {
+        AppError::new(ErrorCode::DataCorrupted, "store.manifestCorrupt")
+            .with("user", user_id)
+            .caused_by(e)
+    })?;
+    Ok(Some(manifest))
+}
+
+/// 写入清单或快照，启用存储加密时写入密文
+fn write_manifest(path: &Path, manifest: &ProfileManifest) -> Result<(), AppError> {
+    let content = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "store.serializeManifest").caused_by(e))?;
+    write_manifest_data(path, &content, vault::current_key()?.as_ref())
+}
+
+fn write_manifest_data(
+    path: &Path,
+    content: &[u8],
+    key: Option<&SecretKey>,
+) -> Result<(), AppError> {
+    let write_failed =
+        |e: io::Error| AppError::new(ErrorCode::Io, "store.writeManifest").caused_by(e);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_failed)?;
+    }
+    match key {
+        Some(key) => {
+            let mut sealed = Vec::new();
+            crypto::seal_blob(key, &mut &content[..], &mut sealed)?;
+            write_file_atomic(path, &sealed).map_err(write_failed)
+        }
+        None => write_file_atomic(path, content).map_err(write_failed),
+    }
+}
+
+/// 保存一份新备份：记录为新快照并设为该用户的当前清单，返回快照编号
+pub fn save_manifest(manifest: &ProfileManifest) -> Result<String, AppError> {
+    // 按时间生成可排序的编号，同一毫秒内重复时追加序号
+    let base = Utc::now().format("%Y%m%dT%H%M%S%3fZ").to_string();
+    let mut snapshot_id = base.clone();
+    let mut n = 1;
+    while get_snapshot_path(&manifest.user_id, &snapshot_id)?.exists() {
+        snapshot_id = format!("{}-{}", base, n);
+        n += 1;
+    }
+
+    let mut manifest = manifest.clone();
+    manifest.snapshot_id = Some(snapshot_id.clone());
+    write_manifest(
+        &get_snapshot_path(&manifest.user_id, &snapshot_id)?,
+        &manifest,
+    )?;
+    set_head(&manifest)?;
+    Ok(snapshot_id)
+}
+
+/// 将某个快照设为用户的当前清单
+///
+/// 当前清单必须始终有对应的快照文件，快照若已被清理会重新写回。
+pub fn set_head(manifest: &ProfileManifest) -> Result<(), AppError> {
+    if let Some(ref snapshot_id) = manifest.snapshot_id {
+        let snapshot_path = get_snapshot_path(&manifest.user_id, snapshot_id)?;
+        if !snapshot_path.exists() {
+            write_manifest(&snapshot_path, manifest)?;
+        }
+    }
+    write_manifest(&get_manifest_path(&manifest.user_id)?, manifest)
+}
+
+/// 删除用户的当前清单及全部快照（blob 由 [`collect_garbage`] 回收）
+pub fn remove_manifest(user_id: &str) -> Result<(), AppError> {
+    let path = get_manifest_path(user_id)?;
+    let snapshots = get_snapshots_dir(user_id)?;
+    if path.exists() {
+        fs::remove_file(&path)
+            .map_err(|e| AppError::new(ErrorCode::Io, "store.removeManifest").caused_by(e))?;
+    }
+    if snapshots.exists() {
+        fs::remove_dir_all(&snapshots)
+            .map_err(|e| AppError::new(ErrorCode::Io, "store.removeSnapshots").caused_by(e))?;
+    }
+    Ok(())
+}
+
+/// 列出用户的所有快照编号，按时间从旧到新排序
+pub fn list_snapshot_ids(user_id: &str) -> Vec<String> {
+    let Some(entries) = get_snapshots_dir(user_id)
+        .ok()
+        .and_then(|dir| fs::read_dir(dir).ok())
+    else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_suffix(".json").map(|s| s.to_string())
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// 读取指定快照
+pub fn load_snapshot(user_id: &str, snapshot_id: &str) -> Result<ProfileManifest, AppError> {
+    let path = get_snapshot_path(user_id, snapshot_id)?;
+    let content = read_manifest_file(&path, |_| {
+        AppError::new(ErrorCode::SnapshotNotFound, "snapshot.notFound")
+            .with("user", user_id)
+            .with("id", snapshot_id)
+    })?;
+    serde_json::from_slice(&content).map_err(|e| {
+        AppError::new(ErrorCode::DataCorrupted, "snapshot.corrupt")
+            .with("id", snapshot_id)
+            .caused_by(e)
+    })
+}
+
+/// 删除指定快照（blob 由 [`collect_garbage`] 回收）
+pub fn remove_snapshot(user_id: &str, snapshot_id: &str) -> Result<(), AppError> {
+    fs::remove_file(get_snapshot_path(user_id, snapshot_id)?).map_err(|e| {
+        AppError::new(ErrorCode::Io, "snapshot.remove")
+            .with("id", snapshot_id)
+            .caused_by(e)
+    })
+}
+
+/// 列出存储中所有有清单的用户
+pub fn list_manifest_users() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(get_manifests_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_suffix(".json").map(|s| s.to_string())
+        })
+        .collect()
+}
+
+/// 计算文件的 SHA-256
+pub fn hash_file(path: &Path) -> Result<String, AppError> {
+    let mut file = fs::File::open(path).map_err(|e| read_file_failed(path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| read_file_failed(path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn read_file_failed(path: &Path, e: io::Error) -> AppError {
+    AppError::new(ErrorCode::Io, "store.readFile")
+        .with("path", path.display())
+        .caused_by(e)
+}
+
+fn create_dir_failed(path: &Path, e: io::Error) -> AppError {
+    AppError::new(ErrorCode::Io, "store.createDir")
+        .with("path", path.display())
+        .caused_by(e)
+}
+
+fn write_blob_failed(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorCode::Io, "store.writeBlob").caused_by(e)
+}
+
+/// 读取的同时计算内容的 SHA-256
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// 将文件写入 blob 存储（已存在相同内容时跳过），返回是否实际写入
+///
+/// 启用存储加密时写入密文，blob 名为 [`crypto::blob_name`]。写入的内容会重新计算哈希，
+/// 与 `hash` 不一致（文件在哈希之后被修改）时放弃写入，避免 blob 内容与名称不符。
+fn store_blob(src: &Path, hash: &str, key: Option<&SecretKey>) -> Result<bool, AppError> {
+    let blob_path = get_blob_path(hash, key);
+    if blob_path.exists() {
+        return Ok(false);
+    }
+    let bucket = blob_path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(bucket).map_err(|e| create_dir_failed(bucket, e))?;
+
+    // 先写临时文件再重命名，中断时不会留下内容与哈希不符的 blob
+    let name = blob_path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = bucket.join(format!(".{}.tmp", name));
+    let mut reader = HashingReader::new(io::BufReader::new(
+        fs::File::open(src).map_err(|e| read_file_failed(src, e))?,
+    ));
+    let written = match key {
+        Some(key) => write_sealed(&mut reader, &tmp_path, key),
+        None => write_plain(&mut reader, &tmp_path),
+    };
+    let checked = written.and_then(|_| {
+        if reader.finish() == hash {
+            Ok(())
+        } else {
+            Err(AppError::new(ErrorCode::Io, "store.fileChanged").with("path", src.display()))
+        }
+    });
+    if let Err(e) = checked {
+        fs::remove_file(&tmp_path).ok();
+        return Err(e);
+    }
+    fs::rename(&tmp_path, &blob_path).map_err(write_blob_failed)?;
+    Ok(true)
+}
+
+/// 把 `reader` 的内容原样写入 `dst`
+fn write_plain<R: Read>(reader: &mut R, dst: &Path) -> Result<(), AppError> {
+    let mut file = fs::File::create(dst).map_err(write_blob_failed)?;
+    io::copy(reader, &mut file).map_err(write_blob_failed)?;
+    file.sync_all().map_err(write_blob_failed)
+}
+
+/// 把 `reader` 的内容加密写入 `dst`
+fn write_sealed<R: Read>(reader: &mut R, dst: &Path, key: &SecretKey) -> Result<(), AppError> {
+    let mut writer = io::BufWriter::new(fs::File::create(dst).map_err(write_blob_failed)?);
+    crypto::seal_blob(key, reader, &mut writer)?;
+    let file = writer.into_inner().map_err(write_blob_failed)?;
+    file.sync_all().map_err(write_blob_failed)
+}
+
+/// 读出 blob 的明文内容写入 `writer`，加密的 blob 需要提供密钥
+pub fn copy_blob_to<W: Write>(
+    hash: &str,
+    key: Option<&SecretKey>,
+    writer: &mut W,
+) -> Result<(), AppError> {
+    let path = get_blob_path(hash, key);
+    let mut reader = io::BufReader::new(fs::File::open(&path).map_err(|e| {
+        AppError::new(ErrorCode::BackupMissing, "store.blobMissing")
+            .with("hash", hash)
+            .caused_by(e)
+    })?);
+
+    if crypto::is_sealed_blob(&path) {
+        let key = key.ok_or_else(|| AppError::new(ErrorCode::StoreLocked, "vault.locked"))?;
+        crypto::open_blob(key, &mut reader, writer)
+    } else {
+        io::copy(&mut reader, writer).map(|_| ()).map_err(|e| {
+            AppError::new(ErrorCode::Io, "store.readBlob")
+                .with("hash", hash)
+                .caused_by(e)
+        })
+    }
+}
+
+fn modified_millis(meta: &fs::Metadata) -> Option<i64> {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+}
+
+/// 递归收集目录下的文件与子目录，被排除的路径记入 `skipped`，符号链接不备份
+fn walk(
+    root: &Path,
+    rel: &str,
+    items: &ResolvedItems,
+    dirs: &mut Vec<String>,
+    files: &mut Vec<(String, PathBuf)>,
+    skipped: &mut Vec<SkippedEntry>,
+) -> Result<(), AppError> {
+    let abs = to_local_path(root, rel);
+    if items.is_excluded(rel) {
+        skipped.push(SkippedEntry {
+            path: rel.to_string(),
+            is_dir: abs.is_dir(),
+        });
+        return Ok(());
+    }
+    // 不跟随符号链接：链接可能成环，也可能指向数据目录以外
+    let meta = fs::symlink_metadata(&abs).map_err(|e| read_file_failed(&abs, e))?;
+    if meta.file_type().is_symlink() {
+        tracing::warn!(path = rel, "跳过符号链接");
+        return Ok(());
+    }
+    if !meta.is_dir() {
+        files.push((rel.to_string(), abs));
+        return Ok(());
+    }
+
+    dirs.push(rel.to_string());
+    let read_dir_failed = |e: io::Error| {
+        AppError::new(ErrorCode::Io, "store.readDir")
+            .with("path", abs.display())
+            .caused_by(e)
+    };
+    for entry in fs::read_dir(&abs).map_err(read_dir_failed)? {
+        let entry = entry.map_err(read_dir_failed)?;
+        let child = format!("{}/{}", rel, entry.file_name().to_string_lossy());
+        walk(root, &child, items, dirs, files, skipped)?;
+    }
+    Ok(())
+}
+
+/// 将 `root` 下解析出的备份路径存入 blob 存储并生成清单
+///
+/// 大小和修改时间与上一份清单一致且 blob 仍在的文件直接沿用旧哈希，
+/// 不会重新读取；内容已存在的 blob 也不会重复写入。
+pub fn capture(
+    user_id: &str,
+    root: &Path,
+    items: &ResolvedItems,
+    previous: Option<&ProfileManifest>,
+) -> Result<ProfileManifest, AppError> {
+    build_manifest(user_id, root, items, previous, true)
+}
+
+/// 只计算 `root` 下备份路径的清单，不写入 blob 存储（用于导入预览等比较）
+pub fn scan(
+    user_id: &str,
+    root: &Path,
+    items: &ResolvedItems,
+) -> Result<ProfileManifest, AppError> {
+    build_manifest(user_id, root, items, None, false)
+}
+
+fn build_manifest(
+    user_id: &str,
+    root: &Path,
+    items: &ResolvedItems,
+    previous: Option<&ProfileManifest>,
+    write_blobs: bool,
+) -> Result<ProfileManifest, AppError> {
+    let key = if write_blobs {
+        vault::current_key()?
+    } else {
+        None
+    };
+    let known: HashMap<&str, &ManifestEntry> = previous
+        .map(|m| m.files.iter().map(|f| (f.path.as_str(), f)).collect())
+        .unwrap_or_default();
+
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut roots = Vec::new();
+    for item in &items.roots {
+        if let Ok(meta) = fs::symlink_metadata(to_local_path(root, item)) {
+            walk(root, item, items, &mut dirs, &mut files, &mut skipped)?;
+            if !items.is_excluded(item) && !meta.file_type().is_symlink() {
+                roots.push(item.clone());
+            }
+        }
+    }
+
+    let files = files
+        .into_iter()
+        .map(|(rel, abs)| {
+            let meta = fs::metadata(&abs).map_err(|e| read_file_failed(&abs, e))?;
+            Ok((rel, abs, meta))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    if write_blobs {
+        let bytes_total = files.iter().map(|(_, _, meta)| meta.len()).sum();
+        progress::phase(
+            ProgressPhase::Capture,
+            Some(user_id),
+            files.len(),
+            bytes_total,
+        );
+    }
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (rel, abs, meta) in files {
+        operation::check_cancelled()?;
+        let size = meta.len();
+        let modified = modified_millis(&meta);
+
+        let unchanged = known.get(rel.as_str()).filter(|prev| {
+            prev.size == size
+                && prev.modified.is_some()
+                && prev.modified == modified
+                && get_blob_path(&prev.hash, key.as_ref()).exists()
+        });
+
+        let hash = match unchanged {
+            Some(prev) => prev.hash.clone(),
+            None => {
+                let hash = hash_file(&abs)?;
+                if write_blobs {
+                    store_blob(&abs, &hash, key.as_ref())?;
+                }
+                hash
+            }
+        };
+        if write_blobs {
+            progress::advance(&rel, size);
+        }
+
+        entries.push(ManifestEntry {
+            path: rel,
+            size,
+            hash,
+            modified,
+        });
+    }
+
+    Ok(ProfileManifest {
+        version: MANIFEST_VERSION,
+        user_id: user_id.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        snapshot_id: None,
+        item_set: items.set_id.clone(),
+        roots,
+        dirs,
+        files: entries,
+        skipped,
+    })
+}
+
+/// 清单内容摘要：只由文件路径和内容哈希决定，用于判断两份备份是否相同
+pub fn content_digest(manifest: &ProfileManifest) -> String {
+    let mut files: Vec<&ManifestEntry> = manifest.files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut hasher = Sha256::new();
+    for f in files {
+        hasher.update(f.path.as_bytes());
+        hasher.update([0]);
+        hasher.update(f.hash.as_bytes());
+        hasher.update([b'\n']);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// 将相对路径转换为本地路径
+pub fn to_local_path(root: &Path, rel: &str) -> PathBuf {
+    rel.split('/')
+        .fold(root.to_path_buf(), |p, part| p.join(part))
+}
+
+/// 取相对路径的第一段，即所属的备份条目
+pub fn top_level_item(rel: &str) -> &str {
+    rel.split('/').next().unwrap_or(rel)
+}
+
+/// 按清单在 `target_dir` 下还原文件，跳过被排除的路径并返回这些路径
+///
+/// 早期备份中可能含有现在已被排除的锁文件或缓存，恢复时一并跳过。
+pub fn materialize(
+    manifest: &ProfileManifest,
+    target_dir: &Path,
+    exclude: &ExcludeFilter,
+) -> Result<Vec<String>, AppError> {
+    let key = vault::current_key()?;
+    fs::create_dir_all(target_dir).map_err(|e| create_dir_failed(target_dir, e))?;
+
+    let mut skipped = Vec::new();
+
+    for dir in &manifest.dirs {
+        if exclude.covers(dir) {
+            continue;
+        }
+        let path = to_local_path(target_dir, dir);
+        fs::create_dir_all(&path).map_err(|e| create_dir_failed(&path, e))?;
+    }
+
+    let restored: Vec<&ManifestEntry> = manifest
+        .files
+        .iter()
+        .filter(|entry| !exclude.covers(&entry.path))
+        .collect();
+    progress::phase(
+        ProgressPhase::Materialize,
+        Some(&manifest.user_id),
+        restored.len(),
+        restored.iter().map(|entry| entry.size).sum(),
+    );
+
+    for entry in &manifest.files {
+        if exclude.covers(&entry.path) {
+            skipped.push(entry.path.clone());
+            continue;
+        }
+        operation::check_cancelled()?;
+        let blob = get_blob_path(&entry.hash, key.as_ref());
+        if !blob.exists() {
+            return Err(AppError::new(ErrorCode::BackupMissing, "store.fileMissing")
+                .with("path", &entry.path)
+                .with("hash", &entry.hash));
+        }
+        let dst = to_local_path(target_dir, &entry.path);
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(|e| create_dir_failed(parent, e))?;
+        }
+        let mut out = fs::File::create(&dst).map_err(|e| {
+            AppError::new(ErrorCode::Io, "store.restoreFile")
+                .with("path", &entry.path)
+                .caused_by(e)
+        })?;
+        copy_blob_to(&entry.hash, key.as_ref(), &mut out)?;
+        progress::advance(&entry.path, entry.size);
+    }
+
+    Ok(skipped)
+}
+
+/// 所有清单和快照引用的内容哈希
+fn referenced_hashes() -> Result<HashSet<String>, AppError> {
+    let mut referenced = HashSet::new();
+    for user_id in list_manifest_users() {
+        if let Some(manifest) = load_manifest(&user_id)? {
+            referenced.extend(manifest.files.into_iter().map(|f| f.hash));
+        }
+        for snapshot_id in list_snapshot_ids(&user_id) {
+            let snapshot = load_snapshot(&user_id, &snapshot_id)?;
+            referenced.extend(snapshot.files.into_iter().map(|f| f.hash));
+        }
+    }
+    Ok(referenced)
+}
+
+/// 删除不再被任何清单或快照引用的 blob，返回删除数量
+pub fn collect_garbage() -> Result<usize, AppError> {
+    let key = vault::current_key()?;
+    let mut referenced = referenced_hashes()?;
+    // 加密迁移未完成时两种文件名都可能存在
+    if let Some(key) = key {
+        let keyed: Vec<String> = referenced
+            .iter()
+            .map(|hash| crypto::blob_name(&key, hash))
+            .collect();
+        referenced.extend(keyed);
+    }
+
+    let mut removed = 0;
+    for (name, path) in list_blob_files() {
+        // 残留的临时文件一并清理
+        let orphaned = name.ends_with(".tmp") || !referenced.contains(&name);
+        if orphaned && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// 列出 blob 目录下的所有文件（含临时文件）
+fn list_blob_files() -> Vec<(String, PathBuf)> {
+    let Ok(buckets) = fs::read_dir(get_blobs_dir()) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for bucket in buckets.flatten() {
+        let Ok(blobs) = fs::read_dir(bucket.path()) else {
+            continue;
+        };
+        for blob in blobs.flatten() {
+            files.push((blob.file_name().to_string_lossy().to_string(), blob.path()));
+        }
+    }
+    files
+}
+
+/// 统计尚未加密的 blob 数量
+pub fn count_plaintext_blobs() -> usize {
+    list_blob_files()
+        .into_iter()
+        .filter(|(name, path)| !name.ends_with(".tmp") && !crypto::is_sealed_blob(path))
+        .count()
+}
+
+/// 统计仍为加密状态的 blob、清单和快照数量
+pub fn count_sealed_files() -> usize {
+    let blobs = list_blob_files()
+        .into_iter()
+        .filter(|(name, path)| !name.ends_with(".tmp") && crypto::is_sealed_blob(path))
+        .count();
+    let manifests = list_manifest_files()
+        .into_iter()
+        .filter(|path| crypto::is_sealed_blob(path))
+        .count();
+    blobs + manifests
+}
+
+/// 列出所有清单和快照文件
+fn list_manifest_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = list_manifest_users()
+        .iter()
+        .filter_map(|user_id| get_manifest_path(user_id).ok())
+        .collect();
+    // 按快照目录列出，已没有当前清单的用户也可能留有快照
+    if let Ok(dirs) = fs::read_dir(get_store_dir().join("snapshots")) {
+        for dir in dirs.flatten() {
+            let user_id = dir.file_name().to_string_lossy().to_string();
+            for snapshot_id in list_snapshot_ids(&user_id) {
+                files.extend(get_snapshot_path(&user_id, &snapshot_id).ok());
+            }
+        }
+    }
+    files
+}
+
+/// 用 `key` 重新写入所有清单和快照，`key` 为 `None` 时写回明文
+fn reseal_manifests(key: Option<&SecretKey>, open_key: &SecretKey) -> Result<(), AppError> {
+    for path in list_manifest_files() {
+        let data = fs::read(&path).map_err(|e| read_file_failed(&path, e))?;
+        let sealed = crypto::is_sealed_data(&data);
+        if sealed == key.is_some() {
+            continue;
+        }
+        let plain = if sealed {
+            open_manifest_data(&data, open_key)?
+        } else {
+            data
+        };
+        write_manifest_data(&path, &plain, key)?;
+    }
+    Ok(())
+}
+
+/// 启用存储加密：加密全部清单和快照，再把 blob 加密并改为 [`crypto::blob_name`] 文件名，返回加密的 blob 数量
+///
+/// 每个文件先写临时文件再重命名，可随时中断后重新执行。
+pub fn encrypt_plaintext_blobs(key: &SecretKey) -> Result<usize, AppError> {
+    reseal_manifests(Some(key), key)?;
+
+    let mut encrypted = 0;
+    for hash in referenced_hashes()? {
+        let plain_path = blob_file(&hash);
+        if !plain_path.exists() {
+            continue;
+        }
+        let keyed_path = blob_file(&crypto::blob_name(key, &hash));
+        let bucket = keyed_path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(bucket).map_err(|e| create_dir_failed(bucket, e))?;
+        // 旧版本加密的 blob 仍以哈希命名，只需改名
+        if crypto::is_sealed_blob(&plain_path) {
+            fs::rename(&plain_path, &keyed_path).map_err(write_blob_failed)?;
+            continue;
+        }
+        let name = keyed_path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp_path = bucket.join(format!(".{}.tmp", name));
+        let mut reader = io::BufReader::new(
+            fs::File::open(&plain_path).map_err(|e| read_file_failed(&plain_path, e))?,
+        );
+        write_sealed(&mut reader, &tmp_path, key)?;
+        fs::rename(&tmp_path, &keyed_path).map_err(write_blob_failed)?;
+        fs::remove_file(&plain_path).map_err(write_blob_failed)?;
+        encrypted += 1;
+    }
+    Ok(encrypted)
+}
+
+/// 关闭存储加密：把 blob 解密回以哈希命名的明文文件，再写回明文清单和快照，返回解密的 blob 数量
+///
+/// 调用前应先回收未引用的 blob，否则其中加密的部分无法找回对应的哈希。
+pub fn decrypt_sealed_blobs(key: &SecretKey) -> Result<usize, AppError> {
+    let mut decrypted = 0;
+    for hash in referenced_hashes()? {
+        let plain_path = blob_file(&hash);
+        let keyed_path = blob_file(&crypto::blob_name(key, &hash));
+        let source = if keyed_path.exists() {
+            keyed_path
+        } else if crypto::is_sealed_blob(&plain_path) {
+            plain_path.clone()
+        } else {
+            continue;
+        };
+        let bucket = plain_path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(bucket).map_err(|e| create_dir_failed(bucket, e))?;
+        let tmp_path = bucket.join(format!(".{}.tmp", hash));
+        let mut reader = io::BufReader::new(fs::File::open(&source).map_err(write_blob_failed)?);
+        let mut out = fs::File::create(&tmp_path).map_err(write_blob_failed)?;
+        crypto::open_blob(key, &mut reader, &mut out)?;
+        out.sync_all().map_err(write_blob_failed)?;
+        fs::rename(&tmp_path, &plain_path).map_err(write_blob_failed)?;
+        if source != plain_path {
+            fs::remove_file(&source).map_err(write_blob_failed)?;
+        }
+        decrypted += 1;
+    }
+
+    reseal_manifests(None, key)?;
+    Ok(decrypted)
+}
+
+/// `manifest` 是否包含 `other` 的全部文件且内容相同
+fn covers(manifest: &ProfileManifest, other: &ProfileManifest) -> bool {
+    let known: HashMap<&str, &str> = manifest
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f.hash.as_str()))
+        .collect();
+    other
+        .files
+        .iter()
+        .all(|f| known.get(f.path.as_str()) == Some(&f.hash.as_str()))
+}
+
+/// 将旧版 `profiles/{email}` 完整备份目录迁移到 blob 存储
+///
+/// 迁移成功的目录会被删除；已存在清单的用户不会被覆盖，旧目录的文件都已在清单中时才删除，
+/// 其他条目保持原样。返回迁移的用户数。
+pub fn migrate_legacy_profiles() -> Result<usize, AppError> {
+    let profiles_dir = get_profiles_dir();
+    let Ok(entries) = fs::read_dir(&profiles_dir) else {
+        return Ok(0);
+    };
+
+    let mut migrated = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        // 旧版只按邮箱建目录，隐藏目录不是旧版备份，保持原样
+        if name.starts_with('.') {
+            tracing::warn!(path = %path.display(), "跳过不是旧版备份的目录");
+            continue;
+        }
+
+        let items = itemset::resolve(&itemset::builtin_set(), &path)?;
+        match load_manifest(&name)? {
+            None => {
+                let manifest = capture(&name, &path, &items, None)?;
+                save_manifest(&manifest)?;
+                migrated += 1;
+            }
+            // 已有清单时只在旧目录的文件都已在清单中时删除，否则保留给用户自行处理
+            Some(manifest) if !covers(&manifest, &scan(&name, &path, &items)?) => {
+                tracing::warn!(path = %path.display(), "旧版备份与现有清单不一致，保留目录");
+                continue;
+            }
+            Some(_) => {}
+        }
+        fs::remove_dir_all(&path).map_err(|e| {
+            AppError::new(ErrorCode::Io, "store.removeLegacy")
+                .with("path", path.display())
+                .caused_by(e)
+        })?;
+    }
+
+    // 目录清空后一并移除
+    fs::remove_dir(&profiles_dir).ok();
+
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::profile::{backup_roxy_data, get_roxy_data_dir, restore_roxy_data};
+    use crate::commands::testing::TestRoot;
+
+    #[test]
+    fn encryption_hides_content_hashes() {
+        let _root = TestRoot::new("store-encrypt");
+        let live = get_roxy_data_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), "secret").unwrap();
+        backup_roxy_data("a").unwrap();
+        let hash = load_manifest("a").unwrap().unwrap().files[0].hash.clone();
+
+        assert_eq!(
+            vault::enable_store_encryption_blocking("pw".into(), None).unwrap(),
+            1
+        );
+        let key = vault::current_key().unwrap().unwrap();
+        assert!(!blob_file(&hash).exists());
+        assert!(crypto::is_sealed_blob(&blob_file(&crypto::blob_name(
+            &key, &hash
+        ))));
+        let manifests = list_manifest_files();
+        assert_eq!(manifests.len(), 2);
+        for path in manifests {
+            let data = fs::read(path).unwrap();
+            assert!(crypto::is_sealed_data(&data));
+            assert!(!String::from_utf8_lossy(&data).contains(&hash));
+        }
+        let restored = get_app_config_dir().join("restored");
+        restore_roxy_data("a", &restored).unwrap();
+        assert_eq!(
+            fs::read_to_string(restored.join("Cookies")).unwrap(),
+            "secret"
+        );
+
+        assert_eq!(
+            vault::disable_store_encryption_blocking("pw".into()).unwrap(),
+            1
+        );
+        assert!(!crypto::is_sealed_blob(&blob_file(&hash)));
+        assert_eq!(count_sealed_files(), 0);
+        assert_eq!(load_manifest("a").unwrap().unwrap().files[0].hash, hash);
+    }
+
+    #[test]
+    fn store_blob_rejects_content_changed_after_hashing() {
+        let _root = TestRoot::new("store-changed");
+        let src = get_app_config_dir().join("Cookies");
+        fs::create_dir_all(get_app_config_dir()).unwrap();
+        fs::write(&src, "before").unwrap();
+        let hash = hash_file(&src).unwrap();
+        fs::write(&src, "after!").unwrap();
+
+        let key = crypto::generate_key();
+        for key in [None, Some(&key)] {
+            let err = store_blob(&src, &hash, key).unwrap_err();
+            assert_eq!(err.key, "store.fileChanged");
+            let blob_path = get_blob_path(&hash, key);
+            assert!(!blob_path.exists());
+            assert_eq!(
+                fs::read_dir(blob_path.parent().unwrap()).unwrap().count(),
+                0
+            );
+        }
+
+        fs::write(&src, "before").unwrap();
+        assert!(store_blob(&src, &hash, None).unwrap());
+        assert_eq!(fs::read(get_blob_path(&hash, None)).unwrap(), b"before");
+    }
+
+    #[test]
+    fn backup_collects_garbage_only_after_pruning() {
+        use crate::commands::settings::{load_settings, save_settings};
+
+        let _root = TestRoot::new("store-gc");
+        let mut settings = load_settings().unwrap();
+        settings.snapshot_retention.keep_last = 1;
+        settings.snapshot_retention.keep_daily_days = 0;
+        save_settings(&settings).unwrap();
+        let live = get_roxy_data_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), "first").unwrap();
+        backup_roxy_data("a").unwrap();
+
+        // 中断的备份留下的 blob，没有清单引用
+        let orphan = get_app_config_dir().join("orphan");
+        fs::write(&orphan, "orphan").unwrap();
+        let orphan_hash = hash_file(&orphan).unwrap();
+        store_blob(&orphan, &orphan_hash, None).unwrap();
+
+        fs::write(live.join("Cookies"), "second").unwrap();
+        backup_roxy_data("a").unwrap();
+        assert_eq!(list_snapshot_ids("a").len(), 1);
+        assert!(!get_blob_path(&orphan_hash, None).exists());
+
+        store_blob(&orphan, &orphan_hash, None).unwrap();
+        settings.snapshot_retention.keep_last = 10;
+        save_settings(&settings).unwrap();
+        backup_roxy_data("a").unwrap();
+        assert_eq!(list_snapshot_ids("a").len(), 2);
+        assert!(get_blob_path(&orphan_hash, None).exists());
+    }
+
+    #[test]
+    fn legacy_migration_leaves_unknown_entries_alone() {
+        let _root = TestRoot::new("store-legacy");
+        let profiles = get_profiles_dir();
+        fs::create_dir_all(profiles.join("a@example.com")).unwrap();
+        fs::write(profiles.join("a@example.com/Cookies"), "legacy").unwrap();
+        fs::create_dir_all(profiles.join(".keep")).unwrap();
+        fs::write(profiles.join(".keep/notes"), "mine").unwrap();
+
+        assert_eq!(migrate_legacy_profiles().unwrap(), 1);
+        assert!(!profiles.join("a@example.com").exists());
+        assert_eq!(
+            fs::read_to_string(profiles.join(".keep/notes")).unwrap(),
+            "mine"
+        );
+        assert_eq!(
+            load_manifest("a@example.com").unwrap().unwrap().files.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn legacy_migration_keeps_directories_that_differ_from_the_manifest() {
+        let _root = TestRoot::new("store-legacy-manifest");
+        let live = get_roxy_data_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), "current").unwrap();
+        backup_roxy_data("a@example.com").unwrap();
+        backup_roxy_data("b@example.com").unwrap();
+
+        let profiles = get_profiles_dir();
+        fs::create_dir_all(profiles.join("a@example.com")).unwrap();
+        fs::write(profiles.join("a@example.com/Cookies"), "current").unwrap();
+        fs::create_dir_all(profiles.join("b@example.com")).unwrap();
+        fs::write(profiles.join("b@example.com/Cookies"), "older").unwrap();
+
+        assert_eq!(migrate_legacy_profiles().unwrap(), 0);
+        assert!(!profiles.join("a@example.com").exists());
+        assert_eq!(
+            fs::read_to_string(profiles.join("b@example.com/Cookies")).unwrap(),
+            "older"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backup_skips_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let _root = TestRoot::new("store-symlink");
+        let live = get_roxy_data_dir();
+        let outside = get_app_config_dir().join("outside");
+        fs::create_dir_all(live.join("IndexedDB")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(live.join("IndexedDB/data"), "idb").unwrap();
+        fs::write(outside.join("secret"), "secret").unwrap();
+        symlink(&live, live.join("IndexedDB/loop")).unwrap();
+        symlink(&outside, live.join("IndexedDB/outside")).unwrap();
+        symlink(outside.join("secret"), live.join("Cookies")).unwrap();
+
+        backup_roxy_data("a").unwrap();
+        let manifest = load_manifest("a").unwrap().unwrap();
+        let files: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(files, ["IndexedDB/data"]);
+        assert_eq!(manifest.roots, ["IndexedDB"]);
+    }
+}
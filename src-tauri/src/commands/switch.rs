@@ -0,0 +1,502 @@
+use super::itemset;
+use super::profile::{
+    get_app_config_dir, get_roxy_data_dir, load_config, restore_roxy_data, save_config,
+    write_file_atomic, BACKUP_ITEMS,
+};
+use super::store::{self, to_local_path};
+use crate::error::{AppError, ErrorCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 切换事务所处阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum SwitchPhase {
+    /// 正在将目标用户数据复制到暂存目录，实时目录尚未改动
+    #[serde(rename = "staging")]
+    Staging,
+    /// 暂存已完成，正在用重命名交换实时目录
+    #[serde(rename = "swapping")]
+    Swapping,
+    /// 交换失败，正在把原数据放回实时目录
+    #[serde(rename = "rollingBack")]
+    RollingBack,
+    /// 交换与配置更新均已完成，只剩清理工作
+    #[serde(rename = "committed")]
+    Committed,
+}
+
+/// 切换日志，写在 `~/.roxy_manager/switch_journal.json`
+#[derive(Debug, Serialize, Deserialize)]
+struct SwitchJournal {
+    #[serde(rename = "fromUser")]
+    from_user: Option<String>,
+    #[serde(rename = "toUser")]
+    to_user: String,
+    phase: SwitchPhase,
+    /// 参与交换的路径：原用户备份项集合覆盖的路径与目标用户备份中的路径
+    #[serde(default)]
+    items: Vec<String>,
+    /// 暂存目录中实际存在的条目，用于恢复时区分“已交换”与“目标本就没有”
+    #[serde(rename = "stagedItems", default)]
+    staged_items: Vec<String>,
+    #[serde(rename = "startedAt")]
+    started_at: String,
+}
+
+/// 启动时恢复的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    /// 没有未完成的切换
+    Clean,
+    /// 切换已向前完成
+    RolledForward,
+    /// 切换已回滚到切换前的状态
+    RolledBack,
+}
+
+/// 切换日志路径
+fn get_journal_path() -> PathBuf {
+    get_app_config_dir().join("switch_journal.json")
+}
+
+/// 暂存目录：与 RoxyBrowser 数据目录同级，保证重命名不跨文件系统
+fn get_staging_dir() -> PathBuf {
+    sibling_dir(&get_roxy_data_dir(), "switch-staging")
+}
+
+/// 回滚目录：存放被换下的原实时数据
+fn get_rollback_dir() -> PathBuf {
+    sibling_dir(&get_roxy_data_dir(), "switch-rollback")
+}
+
+fn sibling_dir(dir: &Path, suffix: &str) -> PathBuf {
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "RoxyBrowser".to_string());
+    dir.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+fn read_journal() -> Result<Option<SwitchJournal>, AppError> {
+    let path = get_journal_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| AppError::new(ErrorCode::Io, "switch.readJournal").caused_by(e))?;
+    let journal = serde_json::from_str(&content).map_err(|e| {
+        AppError::new(ErrorCode::DataCorrupted, "switch.journalCorrupt").caused_by(e)
+    })?;
+    Ok(Some(journal))
+}
+
+fn write_journal(journal: &SwitchJournal) -> Result<(), AppError> {
+    fs::create_dir_all(get_app_config_dir())
+        .map_err(|e| AppError::new(ErrorCode::Io, "config.createDir").caused_by(e))?;
+    let content = serde_json::to_string_pretty(journal)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "switch.serializeJournal").caused_by(e))?;
+    write_file_atomic(&get_journal_path(), content.as_bytes())
+        .map_err(|e| AppError::new(ErrorCode::Io, "switch.writeJournal").caused_by(e))
+}
+
+/// 交换或回滚某个条目失败
+fn item_failed(key: &'static str, item: &str, e: std::io::Error) -> AppError {
+    AppError::new(ErrorCode::Io, key)
+        .with("item", item)
+        .caused_by(e)
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+/// 参与交换的路径，早期版本的日志没有记录时使用内置条目
+fn journal_items(journal: &SwitchJournal) -> Vec<String> {
+    if journal.items.is_empty() {
+        BACKUP_ITEMS.iter().map(|s| s.to_string()).collect()
+    } else {
+        journal.items.clone()
+    }
+}
+
+/// 重命名前确保目标的上级目录存在（备份路径可能是多级的）
+fn rename_into(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)
+}
+
+/// 删除暂存/回滚目录和日志，结束一次事务
+fn finish_transaction() {
+    remove_path(&get_staging_dir()).ok();
+    remove_path(&get_rollback_dir()).ok();
+    fs::remove_file(get_journal_path()).ok();
+}
+
+/// 用重命名将暂存条目换入实时目录，原数据移入回滚目录
+///
+/// 每个条目先“实时 -> 回滚”再“暂存 -> 实时”，中断后可由日志推断进度。
+fn swap_items(journal: &SwitchJournal) -> Result<(), AppError> {
+    let roxy_dir = get_roxy_data_dir();
+    let staging = get_staging_dir();
+    let rollback = get_rollback_dir();
+
+    fs::create_dir_all(&rollback)
+        .map_err(|e| AppError::new(ErrorCode::Io, "switch.createRollbackDir").caused_by(e))?;
+    fs::create_dir_all(&roxy_dir)
+        .map_err(|e| AppError::new(ErrorCode::Io, "switch.createDataDir").caused_by(e))?;
+
+    for item in &journal_items(journal) {
+        let live = to_local_path(&roxy_dir, item);
+        let staged = to_local_path(&staging, item);
+        let saved = to_local_path(&rollback, item);
+        let is_staged = journal.staged_items.contains(item);
+
+        if is_staged && !staged.exists() {
+            // 恢复时：该条目已换入
+            continue;
+        }
+
+        if live.exists() && !saved.exists() {
+            rename_into(&live, &saved).map_err(|e| item_failed("switch.moveOut", item, e))?;
+        } else if live.exists() {
+            // 回滚目录中已有原数据，实时目录里的是残留，直接清掉
+            remove_path(&live).map_err(|e| item_failed("switch.clean", item, e))?;
+        }
+
+        if is_staged {
+            rename_into(&staged, &live).map_err(|e| item_failed("switch.moveIn", item, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 撤销交换：把回滚目录中的原数据放回实时目录
+///
+/// 已换入的新数据移回暂存目录而不是删除，中断后重复执行时不会把已放回的原数据当作新数据删掉。
+fn rollback_items(journal: &SwitchJournal) -> Result<(), AppError> {
+    let roxy_dir = get_roxy_data_dir();
+    let staging = get_staging_dir();
+    let rollback = get_rollback_dir();
+
+    for item in &journal_items(journal) {
+        let live = to_local_path(&roxy_dir, item);
+        let staged = to_local_path(&staging, item);
+        let saved = to_local_path(&rollback, item);
+        let is_staged = journal.staged_items.contains(item);
+
+        // 实时目录里的是已换入的新数据
+        let live_is_new = is_staged && !staged.exists();
+
+        if live_is_new {
+            rename_into(&live, &staged).map_err(|e| item_failed("switch.moveBack", item, e))?;
+        }
+        if saved.exists() {
+            remove_path(&live).map_err(|e| item_failed("switch.clean", item, e))?;
+            rename_into(&saved, &live).map_err(|e| item_failed("switch.restore", item, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 在配置中把目标用户标记为当前用户
+fn commit_current_user(user_id: &str) -> Result<(), AppError> {
+    let mut config = load_config()?;
+    config.current_user = Some(user_id.to_string());
+    for user in &mut config.users {
+        if user.id == user_id {
+            user.last_used = Utc::now().to_rfc3339();
+        }
+    }
+    save_config(&config)
+}
+
+/// 以事务方式将实时数据切换为目标用户
+///
+/// 调用前 RoxyBrowser 必须已停止，当前用户数据已备份。
+pub fn run_switch(from_user: Option<String>, to_user: &str) -> Result<(), AppError> {
+    // 上一次切换若未完成，先把它收尾
+    recover_interrupted_switch()?;
+
+    let staging = get_staging_dir();
+    remove_path(&staging)
+        .map_err(|e| AppError::new(ErrorCode::Io, "switch.cleanStaging").caused_by(e))?;
+    remove_path(&get_rollback_dir())
+        .map_err(|e| AppError::new(ErrorCode::Io, "switch.cleanRollback").caused_by(e))?;
+
+    // 换出原用户备份项集合覆盖的全部路径，换入目标用户备份中的路径
+    let set = itemset::set_for_user(from_user.as_deref());
+    let mut items = itemset::resolve(&set, &get_roxy_data_dir())?.roots;
+    if let Some(manifest) = store::load_manifest(to_user)? {
+        items.extend(itemset::manifest_roots(&manifest));
+    }
+
+    let mut journal = SwitchJournal {
+        from_user,
+        to_user: to_user.to_string(),
+        phase: SwitchPhase::Staging,
+        items: itemset::normalize_roots(items),
+        staged_items: Vec::new(),
+        started_at: Utc::now().to_rfc3339(),
+    };
+    write_journal(&journal)?;
+
+    // 暂存阶段失败不影响实时目录
+    if let Err(e) = restore_roxy_data(to_user, &staging) {
+        finish_transaction();
+        return Err(e);
+    }
+    journal.staged_items = journal
+        .items
+        .iter()
+        .filter(|item| fs::symlink_metadata(to_local_path(&staging, item)).is_ok())
+        .cloned()
+        .collect();
+
+    journal.phase = SwitchPhase::Swapping;
+    if let Err(e) = write_journal(&journal) {
+        finish_transaction();
+        return Err(e);
+    }
+
+    swap_or_roll_back(&mut journal)?;
+
+    commit_current_user(to_user)?;
+
+    journal.phase = SwitchPhase::Committed;
+    write_journal(&journal)?;
+    finish_transaction();
+
+    Ok(())
+}
+
+/// 交换实时目录，失败时回滚
+///
+/// 回滚前先把日志记为回滚阶段：回滚没有完成时，下次启动继续回滚而不是向前完成。
+fn swap_or_roll_back(journal: &mut SwitchJournal) -> Result<(), AppError> {
+    let Err(e) = swap_items(journal) else {
+        return Ok(());
+    };
+    journal.phase = SwitchPhase::RollingBack;
+    match write_journal(journal).and_then(|_| rollback_items(journal)) {
+        Ok(()) => {
+            finish_transaction();
+            Err(AppError::new(ErrorCode::SwitchFailed, "switch.rolledBack").caused_by(e))
+        }
+        Err(rollback_err) => Err(
+            AppError::new(ErrorCode::SwitchFailed, "switch.rollbackPending")
+                .caused_by(e)
+                .caused_by(rollback_err),
+        ),
+    }
+}
+
+/// 检测并恢复被中断的切换
+///
+/// 暂存阶段中断直接丢弃暂存数据；交换阶段中断则向前完成，失败时回滚；回滚阶段中断则继续回滚。
+pub fn recover_interrupted_switch() -> Result<RecoveryOutcome, AppError> {
+    recover_with(|| super::process::shutdown_roxy().map(|_| ()))
+}
+
+/// 恢复被中断的切换，`shutdown` 在交换实时目录前停止 RoxyBrowser
+fn recover_with(
+    shutdown: impl FnOnce() -> Result<(), AppError>,
+) -> Result<RecoveryOutcome, AppError> {
+    let Some(mut journal) = read_journal()? else {
+        return Ok(RecoveryOutcome::Clean);
+    };
+
+    match journal.phase {
+        SwitchPhase::Staging => {
+            finish_transaction();
+            Ok(RecoveryOutcome::RolledBack)
+        }
+        SwitchPhase::Swapping => {
+            // 交换实时目录前确保 RoxyBrowser 没有在使用这些文件
+            shutdown()?;
+
+            if swap_items(&journal).is_ok() {
+                commit_current_user(&journal.to_user)?;
+                journal.phase = SwitchPhase::Committed;
+                write_journal(&journal)?;
+                finish_transaction();
+                Ok(RecoveryOutcome::RolledForward)
+            } else {
+                journal.phase = SwitchPhase::RollingBack;
+                write_journal(&journal)?;
+                rollback_items(&journal)?;
+                finish_transaction();
+                Ok(RecoveryOutcome::RolledBack)
+            }
+        }
+        SwitchPhase::RollingBack => {
+            shutdown()?;
+            rollback_items(&journal)?;
+            finish_transaction();
+            Ok(RecoveryOutcome::RolledBack)
+        }
+        SwitchPhase::Committed => {
+            let config = load_config()?;
+            if config.current_user.as_deref() != Some(journal.to_user.as_str()) {
+                commit_current_user(&journal.to_user)?;
+            }
+            finish_transaction();
+            Ok(RecoveryOutcome::RolledForward)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::TestRoot;
+
+    fn journal(phase: SwitchPhase, staged_items: &[&str]) -> SwitchJournal {
+        SwitchJournal {
+            from_user: Some("a".to_string()),
+            to_user: "b".to_string(),
+            phase,
+            items: vec!["Cookies".to_string(), "IndexedDB".to_string()],
+            staged_items: staged_items.iter().map(|item| item.to_string()).collect(),
+            started_at: String::new(),
+        }
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    /// 恢复中断的切换，用记录调用次数的桩代替停止 RoxyBrowser，测试不会碰到本机进程
+    fn recover(shutdowns: &mut usize) -> RecoveryOutcome {
+        recover_with(|| {
+            *shutdowns += 1;
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn recovers_interrupted_switch() {
+        let _root = TestRoot::new("switch");
+        let mut shutdowns = 0;
+
+        let live = get_roxy_data_dir();
+        let staging = get_staging_dir();
+        let rollback = get_rollback_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), "A").unwrap();
+        fs::write(live.join("IndexedDB"), "A-idb").unwrap();
+        let mut config = load_config().unwrap();
+        config.current_user = Some("a".to_string());
+        save_config(&config).unwrap();
+
+        assert_eq!(recover(&mut shutdowns), RecoveryOutcome::Clean);
+
+        // 暂存阶段中断：丢弃暂存数据，实时目录不变
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(staging.join("Cookies"), "B").unwrap();
+        write_journal(&journal(SwitchPhase::Staging, &[])).unwrap();
+        assert_eq!(recover(&mut shutdowns), RecoveryOutcome::RolledBack);
+        assert!(!staging.exists() && !get_journal_path().exists());
+        assert_eq!(shutdowns, 0);
+        assert_eq!(read(live.join("Cookies")), "A");
+        assert_eq!(load_config().unwrap().current_user.as_deref(), Some("a"));
+
+        // 交换阶段中断：IndexedDB 已换出但尚未换入，Cookies 还没有处理
+        fs::create_dir_all(&staging).unwrap();
+        fs::create_dir_all(&rollback).unwrap();
+        fs::write(staging.join("Cookies"), "B").unwrap();
+        fs::write(staging.join("IndexedDB"), "B-idb").unwrap();
+        fs::rename(live.join("IndexedDB"), rollback.join("IndexedDB")).unwrap();
+        write_journal(&journal(SwitchPhase::Swapping, &["Cookies", "IndexedDB"])).unwrap();
+        assert_eq!(recover(&mut shutdowns), RecoveryOutcome::RolledForward);
+        assert_eq!(shutdowns, 1);
+        assert_eq!(read(live.join("Cookies")), "B");
+        assert_eq!(read(live.join("IndexedDB")), "B-idb");
+        assert!(!staging.exists() && !rollback.exists() && !get_journal_path().exists());
+        assert_eq!(load_config().unwrap().current_user.as_deref(), Some("b"));
+
+        // 已提交但未清理：只补上当前用户
+        fs::create_dir_all(&rollback).unwrap();
+        write_journal(&journal(SwitchPhase::Committed, &[])).unwrap();
+        assert_eq!(recover(&mut shutdowns), RecoveryOutcome::RolledForward);
+        assert_eq!(shutdowns, 1);
+        assert!(!rollback.exists() && !get_journal_path().exists());
+        assert_eq!(read(live.join("Cookies")), "B");
+    }
+
+    #[test]
+    fn failed_rollback_is_finished_on_next_launch() {
+        let _root = TestRoot::new("switch-rollback");
+        let mut shutdowns = 0;
+
+        let live = get_roxy_data_dir();
+        let staging = get_staging_dir();
+        let rollback = get_rollback_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), "A").unwrap();
+        // 实时目录中的 Local Storage 被文件占用：leveldb 无法换入，z 的原数据也无法放回
+        fs::write(live.join("Local Storage"), "").unwrap();
+        fs::create_dir_all(staging.join("Local Storage")).unwrap();
+        fs::write(staging.join("Cookies"), "B").unwrap();
+        fs::write(staging.join("Local Storage/leveldb"), "B-ldb").unwrap();
+        fs::create_dir_all(rollback.join("Local Storage")).unwrap();
+        fs::write(rollback.join("Local Storage/z"), "A-z").unwrap();
+        let mut config = load_config().unwrap();
+        config.current_user = Some("a".to_string());
+        save_config(&config).unwrap();
+
+        let mut journal = SwitchJournal {
+            items: vec![
+                "Cookies".to_string(),
+                "Local Storage/z".to_string(),
+                "Local Storage/leveldb".to_string(),
+            ],
+            ..journal(SwitchPhase::Swapping, &["Cookies", "Local Storage/leveldb"])
+        };
+        write_journal(&journal).unwrap();
+        let err = swap_or_roll_back(&mut journal).unwrap_err();
+        assert_eq!(err.key, "switch.rollbackPending");
+        assert_eq!(
+            read_journal().unwrap().unwrap().phase,
+            SwitchPhase::RollingBack
+        );
+        assert_eq!(read(live.join("Cookies")), "A");
+
+        // 下次启动时继续回滚，已放回的 Cookies 不会被当作新数据删除
+        fs::remove_file(live.join("Local Storage")).unwrap();
+        assert_eq!(recover(&mut shutdowns), RecoveryOutcome::RolledBack);
+        assert_eq!(shutdowns, 1);
+        assert_eq!(read(live.join("Cookies")), "A");
+        assert_eq!(read(live.join("Local Storage/z")), "A-z");
+        assert!(!live.join("Local Storage/leveldb").exists());
+        assert!(!staging.exists() && !rollback.exists() && !get_journal_path().exists());
+        assert_eq!(load_config().unwrap().current_user.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn recovery_fails_instead_of_overwriting_an_unreadable_config() {
+        let _root = TestRoot::new("switch-config");
+        let config_path = get_app_config_dir().join("config.json");
+        fs::create_dir_all(get_app_config_dir()).unwrap();
+        fs::write(&config_path, "{ not json").unwrap();
+        write_journal(&journal(SwitchPhase::Committed, &[])).unwrap();
+
+        let err = recover_with(|| Ok(())).unwrap_err();
+        assert_eq!(err.code, ErrorCode::DataCorrupted);
+        // 配置和日志都保留，修复配置后下次启动可以继续恢复
+        assert_eq!(read(config_path), "{ not json");
+        assert!(get_journal_path().exists());
+    }
+}
@@ -0,0 +1,66 @@
+//! 单元测试共用的工具
+
+use super::profile::{load_config, save_config};
+use crate::models::user::UserProfile;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// 替代用户目录的根目录，为 `None` 时使用真实目录
+static ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 使用测试根目录的测试依次执行：操作锁、存储密钥等状态是进程内共享的
+static EXCLUSIVE: Mutex<()> = Mutex::new(());
+
+/// 当前的测试根目录
+pub fn root() -> Option<PathBuf> {
+    ROOT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 测试期间把应用配置、设置和 RoxyBrowser 数据目录都放到一个临时目录下，结束时删除
+pub struct TestRoot {
+    path: PathBuf,
+    _exclusive: MutexGuard<'static, ()>,
+}
+
+impl TestRoot {
+    pub fn new(name: &str) -> Self {
+        let exclusive = EXCLUSIVE.lock().unwrap_or_else(|e| e.into_inner());
+        let path = std::env::temp_dir().join(format!("roxy-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        *ROOT.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.clone());
+        TestRoot {
+            path,
+            _exclusive: exclusive,
+        }
+    }
+}
+
+impl Drop for TestRoot {
+    fn drop(&mut self) {
+        *ROOT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// 以 `id` 为 ID 和邮箱的用户
+pub fn user(id: &str) -> UserProfile {
+    UserProfile {
+        id: id.to_string(),
+        email: id.to_string(),
+        display_name: id.to_string(),
+        created_at: String::new(),
+        last_used: String::new(),
+        note: String::new(),
+        backup_item_set: None,
+        launch_config: None,
+    }
+}
+
+/// 把用户写入配置，`current` 为当前用户
+pub fn save_users(ids: &[&str], current: Option<&str>) {
+    let mut config = load_config().unwrap();
+    config.users = ids.iter().map(|id| user(id)).collect();
+    config.current_user = current.map(str::to_string);
+    save_config(&config).unwrap();
+}
@@ -0,0 +1,35 @@
+use crate::i18n;
+use tauri::menu::{Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem};
+use tauri::{AppHandle, Wry};
+
+/// 系统托盘图标的编号
+pub const TRAY_ID: &str = "main";
+
+/// 按当前语言生成托盘菜单
+pub fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let show = MenuItemBuilder::with_id("show", i18n::text("tray.show")).build(app)?;
+    let separator1 = PredefinedMenuItem::separator(app)?;
+    let start_roxy =
+        MenuItemBuilder::with_id("start_roxy", i18n::text("tray.startRoxy")).build(app)?;
+    let stop_roxy =
+        MenuItemBuilder::with_id("stop_roxy", i18n::text("tray.stopRoxy")).build(app)?;
+    let separator2 = PredefinedMenuItem::separator(app)?;
+    let quit = MenuItemBuilder::with_id("quit", i18n::text("tray.quit")).build(app)?;
+
+    MenuBuilder::new(app)
+        .item(&show)
+        .item(&separator1)
+        .item(&start_roxy)
+        .item(&stop_roxy)
+        .item(&separator2)
+        .item(&quit)
+        .build()
+}
+
+/// 语言变化后重新生成托盘菜单，菜单项编号不变，事件处理无需改动
+pub fn rebuild_menu(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(build_menu(app)?))?;
+    }
+    Ok(())
+}
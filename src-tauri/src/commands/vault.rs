@@ -0,0 +1,459 @@
+use super::crypto::{self, KdfParams, SecretKey};
+use super::operation::Description;
+use super::profile::write_file_atomic;
+use super::{operation, progress, store};
+use crate::error::{AppError, ErrorCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+/// 当前密钥文件格式版本
+const KEYFILE_VERSION: u32 = 1;
+
+/// 已解锁的数据密钥，只保存在内存中
+static UNLOCKED_KEY: Mutex<Option<SecretKey>> = Mutex::new(None);
+
+/// 用户手动锁定后不再自动从钥匙串解锁，直到再次用主密码解锁
+static KEYRING_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// 存储加密的密钥文件：数据密钥由主密码派生的密钥包装后保存
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    kdf: KdfParams,
+    #[serde(rename = "wrappedKey")]
+    wrapped_key: String,
+    /// 数据密钥的校验值
+    #[serde(rename = "keyCheck")]
+    key_check: String,
+    /// 数据密钥是否同时保存在系统钥匙串中
+    #[serde(rename = "useKeyring", default)]
+    use_keyring: bool,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+}
+
+/// 存储加密状态
+#[derive(Debug, Serialize)]
+pub struct StoreEncryptionStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    /// 当前系统是否支持钥匙串
+    #[serde(rename = "keyringAvailable")]
+    pub keyring_available: bool,
+    #[serde(rename = "useKeyring")]
+    pub use_keyring: bool,
+    /// 尚未加密的 blob 数量，大于 0 表示迁移未完成
+    #[serde(rename = "plaintextBlobs")]
+    pub plaintext_blobs: usize,
+}
+
+fn get_keyfile_path() -> PathBuf {
+    store::get_store_dir().join("keyfile.json")
+}
+
+fn read_keyfile() -> Result<Option<KeyFile>, AppError> {
+    let path = get_keyfile_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| AppError::new(ErrorCode::Io, "vault.readKeyfile").caused_by(e))?;
+    let keyfile = serde_json::from_str(&content).map_err(|e| {
+        AppError::new(ErrorCode::DataCorrupted, "vault.keyfileCorrupt").caused_by(e)
+    })?;
+    Ok(Some(keyfile))
+}
+
+fn write_keyfile(keyfile: &KeyFile) -> Result<(), AppError> {
+    fs::create_dir_all(store::get_store_dir())
+        .map_err(|e| AppError::new(ErrorCode::Io, "vault.createStoreDir").caused_by(e))?;
+    let content = serde_json::to_string_pretty(keyfile)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "vault.serializeKeyfile").caused_by(e))?;
+    write_file_atomic(&get_keyfile_path(), content.as_bytes())
+        .map_err(|e| AppError::new(ErrorCode::Io, "vault.writeKeyfile").caused_by(e))
+}
+
+fn not_enabled() -> AppError {
+    AppError::new(ErrorCode::StoreNotEncrypted, "vault.notEnabled")
+}
+
+fn keyring_unavailable() -> AppError {
+    AppError::new(ErrorCode::KeyringUnavailable, "vault.keyringUnavailable")
+}
+
+fn set_unlocked(key: Option<SecretKey>) {
+    *UNLOCKED_KEY.lock().unwrap_or_else(|e| e.into_inner()) = key;
+}
+
+fn unlocked_key() -> Option<SecretKey> {
+    *UNLOCKED_KEY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 系统钥匙串（Linux 上为 Secret Service）
+#[cfg(all(
+    not(test),
+    any(target_os = "macos", target_os = "windows", target_os = "linux")
+))]
+mod system_keyring {
+    use super::SecretKey;
+    use crate::error::{AppError, ErrorCode};
+
+    /// 钥匙串中的服务名与条目名
+    const SERVICE: &str = "roxybrowser-manager";
+    const ENTRY: &str = "store-key";
+
+    fn entry() -> Result<keyring::Entry, AppError> {
+        keyring::Entry::new(SERVICE, ENTRY)
+            .map_err(|e| AppError::new(ErrorCode::Io, "vault.keyringFailed").caused_by(e))
+    }
+
+    /// 把数据密钥保存到钥匙串
+    pub fn store(key: &SecretKey) -> Result<(), AppError> {
+        entry()?
+            .set_password(&hex::encode(key))
+            .map_err(|e| AppError::new(ErrorCode::Io, "vault.keyringFailed").caused_by(e))
+    }
+
+    /// 从钥匙串读取数据密钥
+    pub fn load() -> Option<SecretKey> {
+        let secret = entry().ok()?.get_password().ok()?;
+        hex::decode(secret).ok()?.try_into().ok()
+    }
+
+    pub fn delete() {
+        if let Ok(entry) = entry() {
+            entry.delete_credential().ok();
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    pub fn available() -> bool {
+        true
+    }
+
+    /// Linux 上需要运行 Secret Service（GNOME Keyring、KWallet 等）
+    #[cfg(target_os = "linux")]
+    pub fn available() -> bool {
+        matches!(
+            entry().map(|entry| entry.get_password()),
+            Ok(Ok(_)) | Ok(Err(keyring::Error::NoEntry))
+        )
+    }
+}
+
+#[cfg(all(
+    not(test),
+    not(any(target_os = "macos", target_os = "windows", target_os = "linux"))
+))]
+mod system_keyring {
+    use super::SecretKey;
+    use crate::error::AppError;
+
+    pub fn store(_key: &SecretKey) -> Result<(), AppError> {
+        Err(super::keyring_unavailable())
+    }
+
+    pub fn load() -> Option<SecretKey> {
+        None
+    }
+
+    pub fn delete() {}
+
+    pub fn available() -> bool {
+        false
+    }
+}
+
+/// 测试中代替系统钥匙串，只保存在内存中
+#[cfg(test)]
+mod system_keyring {
+    use super::SecretKey;
+    use crate::error::AppError;
+    use std::sync::Mutex;
+
+    static SECRET: Mutex<Option<SecretKey>> = Mutex::new(None);
+
+    pub fn store(key: &SecretKey) -> Result<(), AppError> {
+        *SECRET.lock().unwrap() = Some(*key);
+        Ok(())
+    }
+
+    pub fn load() -> Option<SecretKey> {
+        *SECRET.lock().unwrap()
+    }
+
+    pub fn delete() {
+        *SECRET.lock().unwrap() = None;
+    }
+
+    pub fn available() -> bool {
+        true
+    }
+}
+
+/// 用主密码解开数据密钥
+fn open_keyfile(keyfile: &KeyFile, password: &str) -> Result<SecretKey, AppError> {
+    let kek = crypto::derive_key(password, &keyfile.kdf)?;
+    let key = crypto::unwrap_key(&kek, &keyfile.wrapped_key)?;
+    if crypto::key_check(&key) != keyfile.key_check {
+        return Err(AppError::new(
+            ErrorCode::DataCorrupted,
+            "vault.keyfileCorrupt",
+        ));
+    }
+    Ok(key)
+}
+
+/// 当前用于读写 blob 的数据密钥
+///
+/// 未启用加密时返回 `None`；已启用但未解锁时会尝试系统钥匙串（手动锁定后除外），仍失败则报错。
+pub fn current_key() -> Result<Option<SecretKey>, AppError> {
+    let Some(keyfile) = read_keyfile()? else {
+        return Ok(None);
+    };
+    if let Some(key) = unlocked_key() {
+        return Ok(Some(key));
+    }
+
+    if keyfile.use_keyring && !KEYRING_SUSPENDED.load(Ordering::SeqCst) {
+        if let Some(key) =
+            system_keyring::load().filter(|k| crypto::key_check(k) == keyfile.key_check)
+        {
+            set_unlocked(Some(key));
+            return Ok(Some(key));
+        }
+    }
+
+    Err(AppError::new(ErrorCode::StoreLocked, "vault.locked"))
+}
+
+/// 确认存储可用（未加密或已解锁），用于在停止 RoxyBrowser 等操作前提前失败
+pub fn ensure_unlocked() -> Result<(), AppError> {
+    current_key().map(|_| ())
+}
+
+/// 获取存储加密状态
+#[tauri::command]
+pub fn get_store_encryption_status() -> Result<StoreEncryptionStatus, AppError> {
+    let keyfile = read_keyfile()?;
+    let unlocked = keyfile.is_some() && current_key().is_ok();
+    let plaintext_blobs = if keyfile.is_some() {
+        store::count_plaintext_blobs()
+    } else {
+        0
+    };
+
+    Ok(StoreEncryptionStatus {
+        enabled: keyfile.is_some(),
+        unlocked,
+        keyring_available: system_keyring::available(),
+        use_keyring: keyfile.as_ref().map(|k| k.use_keyring).unwrap_or(false),
+        plaintext_blobs,
+    })
+}
+
+/// 启用存储加密，并把现有的明文 blob、清单和快照全部加密，返回本次加密的 blob 数量
+///
+/// 已启用时需要提供原主密码，可用于继续被中断的迁移。
+#[tauri::command]
+pub async fn enable_store_encryption(
+    app: AppHandle,
+    password: String,
+    use_keyring: Option<bool>,
+) -> Result<usize, AppError> {
+    progress::run_blocking(app, "enable_store_encryption", move || {
+        enable_store_encryption_blocking(password, use_keyring)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "enable_store_encryption", skip_all, err(Display))]
+pub fn enable_store_encryption_blocking(
+    password: String,
+    use_keyring: Option<bool>,
+) -> Result<usize, AppError> {
+    let _operation = operation::begin(
+        "enable_store_encryption",
+        Description::new("activity.enableStoreEncryption"),
+    )?;
+    if password.is_empty() {
+        return Err(AppError::new(
+            ErrorCode::PassphraseRequired,
+            "vault.passwordRequired",
+        ));
+    }
+    if use_keyring == Some(true) && !system_keyring::available() {
+        return Err(keyring_unavailable());
+    }
+
+    let key = match read_keyfile()? {
+        Some(mut keyfile) => {
+            let key = open_keyfile(&keyfile, &password)?;
+            // 已启用时按本次的选择保存或移除钥匙串中的密钥，未指定时保持原设置
+            if let Some(use_keyring) = use_keyring {
+                if use_keyring {
+                    system_keyring::store(&key)?;
+                } else if keyfile.use_keyring {
+                    system_keyring::delete();
+                }
+                if use_keyring != keyfile.use_keyring {
+                    keyfile.use_keyring = use_keyring;
+                    write_keyfile(&keyfile)?;
+                }
+            }
+            key
+        }
+        None => {
+            let use_keyring = use_keyring.unwrap_or(false);
+            // 先写密钥文件再加密 blob：迁移中断后 blob 处于混合状态，重新执行即可继续
+            let key = crypto::generate_key();
+            let kdf = KdfParams::generate();
+            let kek = crypto::derive_key(&password, &kdf)?;
+            if use_keyring {
+                system_keyring::store(&key)?;
+            }
+            write_keyfile(&KeyFile {
+                version: KEYFILE_VERSION,
+                wrapped_key: crypto::wrap_key(&kek, &key)?,
+                key_check: crypto::key_check(&key),
+                kdf,
+                use_keyring,
+                created_at: Utc::now().to_rfc3339(),
+            })?;
+            key
+        }
+    };
+
+    set_unlocked(Some(key));
+    KEYRING_SUSPENDED.store(false, Ordering::SeqCst);
+    store::encrypt_plaintext_blobs(&key)
+}
+
+/// 用主密码解锁存储
+#[tauri::command]
+pub async fn unlock_store(app: AppHandle, password: String) -> Result<(), AppError> {
+    progress::run_blocking(app, "unlock_store", move || unlock_store_blocking(password)).await
+}
+
+pub fn unlock_store_blocking(password: String) -> Result<(), AppError> {
+    let _operation = operation::begin("unlock_store", Description::new("activity.unlockStore"))?;
+    let keyfile = read_keyfile()?.ok_or_else(not_enabled)?;
+    let key = open_keyfile(&keyfile, &password)?;
+    set_unlocked(Some(key));
+    KEYRING_SUSPENDED.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+/// 从内存中清除数据密钥，在用主密码解锁前不再从钥匙串自动解锁
+#[tauri::command]
+pub fn lock_store() -> Result<(), AppError> {
+    // 正在进行的操作可能还要读写 blob
+    let _operation = operation::begin("lock_store", Description::new("activity.lockStore"))?;
+    set_unlocked(None);
+    KEYRING_SUSPENDED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// 关闭存储加密：解密全部 blob、清单和快照并删除密钥文件
+#[tauri::command]
+pub async fn disable_store_encryption(app: AppHandle, password: String) -> Result<usize, AppError> {
+    progress::run_blocking(app, "disable_store_encryption", move || {
+        disable_store_encryption_blocking(password)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "disable_store_encryption", skip_all, err(Display))]
+pub fn disable_store_encryption_blocking(password: String) -> Result<usize, AppError> {
+    let _operation = operation::begin(
+        "disable_store_encryption",
+        Description::new("activity.disableStoreEncryption"),
+    )?;
+    let keyfile = read_keyfile()?.ok_or_else(not_enabled)?;
+    let key = open_keyfile(&keyfile, &password)?;
+
+    // 未引用的加密 blob 无法找回对应的哈希，先回收
+    store::collect_garbage()?;
+    let decrypted = store::decrypt_sealed_blobs(&key)?;
+    // 密钥文件删除后加密的 blob 和清单再也无法读取，删除前确认已全部解密
+    let remaining = store::count_sealed_files();
+    if remaining > 0 {
+        return Err(
+            AppError::new(ErrorCode::Internal, "vault.sealedBlobsRemain").with("count", remaining),
+        );
+    }
+    fs::remove_file(get_keyfile_path())
+        .map_err(|e| AppError::new(ErrorCode::Io, "vault.removeKeyfile").caused_by(e))?;
+    if keyfile.use_keyring {
+        system_keyring::delete();
+    }
+    set_unlocked(None);
+    Ok(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::TestRoot;
+
+    /// 每个测试从未解锁、钥匙串为空开始
+    fn reset() {
+        set_unlocked(None);
+        KEYRING_SUSPENDED.store(false, Ordering::SeqCst);
+        system_keyring::delete();
+    }
+
+    #[test]
+    fn lock_blocks_keyring_until_unlocked_with_password() {
+        let _root = TestRoot::new("vault-lock");
+        reset();
+        enable_store_encryption_blocking("pw".into(), Some(true)).unwrap();
+        assert!(current_key().unwrap().is_some());
+
+        // 内存中的密钥被清除后仍可从钥匙串自动解锁
+        set_unlocked(None);
+        assert!(current_key().unwrap().is_some());
+
+        lock_store().unwrap();
+        assert_eq!(current_key().unwrap_err().key, "vault.locked");
+        assert!(!get_store_encryption_status().unwrap().unlocked);
+
+        assert_eq!(
+            unlock_store_blocking("wrong".into()).unwrap_err().key,
+            "crypto.wrongPassphrase"
+        );
+        unlock_store_blocking("pw".into()).unwrap();
+        set_unlocked(None);
+        assert!(current_key().unwrap().is_some());
+        reset();
+    }
+
+    #[test]
+    fn enabling_again_updates_keyring_choice() {
+        let _root = TestRoot::new("vault-keyring");
+        reset();
+        enable_store_encryption_blocking("pw".into(), None).unwrap();
+        assert!(!read_keyfile().unwrap().unwrap().use_keyring);
+        assert!(system_keyring::load().is_none());
+
+        enable_store_encryption_blocking("pw".into(), Some(true)).unwrap();
+        assert!(read_keyfile().unwrap().unwrap().use_keyring);
+        assert!(system_keyring::load().is_some());
+
+        // 未指定时保持原设置
+        enable_store_encryption_blocking("pw".into(), None).unwrap();
+        assert!(read_keyfile().unwrap().unwrap().use_keyring);
+
+        enable_store_encryption_blocking("pw".into(), Some(false)).unwrap();
+        assert!(!read_keyfile().unwrap().unwrap().use_keyring);
+        assert!(system_keyring::load().is_none());
+        set_unlocked(None);
+        assert_eq!(current_key().unwrap_err().key, "vault.locked");
+        reset();
+    }
+}
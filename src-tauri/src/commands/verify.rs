@@ -0,0 +1,621 @@
+use super::crypto::SecretKey;
+use super::operation::Description;
+use super::profile::{extract_email, load_config};
+use super::progress::ProgressPhase;
+use super::store::{self, ManifestEntry, ProfileManifest};
+use super::{operation, progress, vault};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n;
+use crate::models::user::UserProfile;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use tauri::AppHandle;
+
+/// SQLite 数据库文件头
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// LevelDB 表文件（.ldb/.sst）末尾的魔数
+const LEVELDB_TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+
+/// LevelDB 表文件 footer 长度
+const LEVELDB_FOOTER_SIZE: u64 = 48;
+
+/// 读取文件头时保留的字节数（SQLite 文件头为 100 字节）
+const HEAD_SIZE: usize = 100;
+
+/// 已知一定是 SQLite 数据库的文件名
+const SQLITE_FILE_NAMES: &[&str] = &["Cookies", "Login Data", "Web Data", "History"];
+
+/// 问题严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum IssueSeverity {
+    /// 备份不可用或恢复后会丢失登录状态
+    #[serde(rename = "error")]
+    Error,
+    /// 可以恢复，但结果可能与预期不符
+    #[serde(rename = "warning")]
+    Warning,
+}
+
+/// 校验发现的问题
+///
+/// 与 `AppError` 相同，前端按 `messageKey` 和 `params` 本地化，`message` 为渲染好的消息。
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyIssue {
+    pub severity: IssueSeverity,
+    /// 相关文件（相对数据目录）
+    pub path: Option<String>,
+    /// 消息目录中的键
+    #[serde(rename = "messageKey")]
+    pub message_key: &'static str,
+    pub params: BTreeMap<&'static str, String>,
+    pub message: String,
+}
+
+impl VerifyIssue {
+    fn new(severity: IssueSeverity, path: Option<&str>, key: &'static str) -> Self {
+        Self::from_error(
+            severity,
+            path,
+            &AppError::new(ErrorCode::DataCorrupted, key),
+        )
+    }
+
+    /// 由读取、解密等操作返回的错误生成问题
+    fn from_error(severity: IssueSeverity, path: Option<&str>, error: &AppError) -> Self {
+        VerifyIssue {
+            severity,
+            path: path.map(str::to_string),
+            message_key: error.key,
+            params: error.params.clone(),
+            message: error.message(),
+        }
+    }
+
+    /// 添加消息参数并重新渲染消息
+    fn with(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.params.insert(name, value.to_string());
+        let params = self
+            .params
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()));
+        self.message = i18n::render(self.message_key, params);
+        self
+    }
+}
+
+/// 用户备份的整体状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ProfileHealth {
+    #[serde(rename = "ok")]
+    Ok,
+    #[serde(rename = "warning")]
+    Warning,
+    #[serde(rename = "corrupt")]
+    Corrupt,
+    /// 没有可用的备份清单
+    #[serde(rename = "missing")]
+    Missing,
+}
+
+/// 单个用户的校验结果
+#[derive(Debug, Serialize)]
+pub struct ProfileVerification {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    pub email: String,
+    pub health: ProfileHealth,
+    /// 被校验的快照（当前备份）
+    #[serde(rename = "snapshotId")]
+    pub snapshot_id: Option<String>,
+    /// 从备份中识别出的登录邮箱
+    #[serde(rename = "detectedEmail")]
+    pub detected_email: Option<String>,
+    pub issues: Vec<VerifyIssue>,
+    /// 可用于修复的最近一个完好快照
+    #[serde(rename = "repairSnapshotId")]
+    pub repair_snapshot_id: Option<String>,
+}
+
+/// 单个 blob 的校验结果，按哈希缓存，多个快照共享的 blob 只读一次
+#[derive(Debug, Clone)]
+struct BlobCheck {
+    error: Option<AppError>,
+    size: u64,
+    head: Vec<u8>,
+    tail: Vec<u8>,
+}
+
+/// 边写边计算哈希，同时保留开头和结尾的若干字节
+struct InspectingWriter {
+    hasher: Sha256,
+    size: u64,
+    head: Vec<u8>,
+    tail: Vec<u8>,
+}
+
+impl Write for InspectingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        self.size += buf.len() as u64;
+        if self.head.len() < HEAD_SIZE {
+            let n = (HEAD_SIZE - self.head.len()).min(buf.len());
+            self.head.extend_from_slice(&buf[..n]);
+        }
+        self.tail.extend_from_slice(buf);
+        if self.tail.len() > 8 {
+            self.tail.drain(..self.tail.len() - 8);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 校验上下文：解锁后的密钥与 blob 校验缓存
+struct Verifier {
+    key: Option<SecretKey>,
+    blobs: HashMap<String, BlobCheck>,
+}
+
+impl Verifier {
+    fn new() -> Result<Self, AppError> {
+        Ok(Self {
+            key: vault::current_key()?,
+            blobs: HashMap::new(),
+        })
+    }
+
+    fn check_blob(&mut self, entry: &ManifestEntry) -> BlobCheck {
+        if let Some(check) = self.blobs.get(&entry.hash) {
+            return check.clone();
+        }
+
+        let mut writer = InspectingWriter {
+            hasher: Sha256::new(),
+            size: 0,
+            head: Vec::new(),
+            tail: Vec::new(),
+        };
+        let error = match store::copy_blob_to(&entry.hash, self.key.as_ref(), &mut writer) {
+            Err(e) => Some(e),
+            Ok(()) if format!("{:x}", writer.hasher.finalize_reset()) != entry.hash => Some(
+                AppError::new(ErrorCode::DataCorrupted, "verify.hashMismatch"),
+            ),
+            Ok(()) => None,
+        };
+        let check = BlobCheck {
+            error,
+            size: writer.size,
+            head: writer.head,
+            tail: writer.tail,
+        };
+        self.blobs.insert(entry.hash.clone(), check.clone());
+        check
+    }
+
+    fn read_blob(&self, hash: &str) -> Option<Vec<u8>> {
+        let mut content = Vec::new();
+        store::copy_blob_to(hash, self.key.as_ref(), &mut content).ok()?;
+        Some(content)
+    }
+
+    /// 校验一份清单，返回问题列表和识别出的邮箱（`email` 为用户应登录的邮箱）
+    fn verify_manifest(
+        &mut self,
+        email: &str,
+        manifest: &ProfileManifest,
+    ) -> (Vec<VerifyIssue>, Option<String>) {
+        let mut issues = Vec::new();
+
+        if let Err(e) = super::itemset::check_required(manifest) {
+            issues.push(VerifyIssue::from_error(IssueSeverity::Error, None, &e));
+        }
+
+        progress::phase(
+            ProgressPhase::Verify,
+            Some(&manifest.user_id),
+            manifest.files.len(),
+            manifest.files.iter().map(|f| f.size).sum(),
+        );
+        let mut checks = HashMap::new();
+        for entry in &manifest.files {
+            let check = self.check_blob(entry);
+            progress::advance(&entry.path, entry.size);
+            if let Some(ref e) = check.error {
+                issues.push(VerifyIssue::from_error(
+                    IssueSeverity::Error,
+                    Some(&entry.path),
+                    e,
+                ));
+            } else if check.size != entry.size {
+                issues.push(
+                    VerifyIssue::new(
+                        IssueSeverity::Error,
+                        Some(&entry.path),
+                        "verify.sizeMismatch",
+                    )
+                    .with("expected", entry.size)
+                    .with("actual", check.size),
+                );
+            } else {
+                checks.insert(entry.path.as_str(), check);
+            }
+        }
+
+        for entry in &manifest.files {
+            let Some(check) = checks.get(entry.path.as_str()) else {
+                continue;
+            };
+            if let Some(key) = check_sqlite(&entry.path, check) {
+                issues.push(VerifyIssue::new(
+                    IssueSeverity::Error,
+                    Some(&entry.path),
+                    key,
+                ));
+            }
+            if let Some(key) = check_leveldb_table(&entry.path, check) {
+                issues.push(VerifyIssue::new(
+                    IssueSeverity::Error,
+                    Some(&entry.path),
+                    key,
+                ));
+            }
+        }
+        issues.extend(self.check_leveldb_dirs(manifest));
+
+        let detected = self.detect_email(manifest);
+        match detected {
+            None => issues.push(VerifyIssue::new(
+                IssueSeverity::Error,
+                Some("Local Storage/leveldb"),
+                "verify.emailNotDetected",
+            )),
+            Some(ref found) if found != email => issues.push(
+                VerifyIssue::new(
+                    IssueSeverity::Warning,
+                    Some("Local Storage/leveldb"),
+                    "verify.emailMismatch",
+                )
+                .with("found", found)
+                .with("email", email),
+            ),
+            Some(_) => {}
+        }
+
+        (issues, detected)
+    }
+
+    /// 检查 LevelDB 目录：CURRENT 必须存在并指向清单中的 MANIFEST 文件
+    fn check_leveldb_dirs(&self, manifest: &ProfileManifest) -> Vec<VerifyIssue> {
+        let mut dirs: HashMap<&str, Vec<&ManifestEntry>> = HashMap::new();
+        for entry in &manifest.files {
+            if let Some((dir, _)) = entry.path.rsplit_once('/') {
+                dirs.entry(dir).or_default().push(entry);
+            }
+        }
+
+        let mut issues = Vec::new();
+        for (dir, files) in dirs {
+            let name_of = |e: &ManifestEntry| e.path.rsplit('/').next().unwrap_or("").to_string();
+            let names: HashSet<String> = files.iter().map(|e| name_of(e)).collect();
+            let is_leveldb = names
+                .iter()
+                .any(|n| n == "CURRENT" || n.starts_with("MANIFEST-") || n.ends_with(".ldb"));
+            if !is_leveldb {
+                continue;
+            }
+
+            let Some(current) = files.iter().find(|e| name_of(e) == "CURRENT") else {
+                issues.push(VerifyIssue::new(
+                    IssueSeverity::Error,
+                    Some(dir),
+                    "verify.leveldbMissingCurrent",
+                ));
+                continue;
+            };
+            let target = self
+                .read_blob(&current.hash)
+                .map(|c| String::from_utf8_lossy(&c).trim().to_string())
+                .unwrap_or_default();
+            if !target.starts_with("MANIFEST-") || !names.contains(&target) {
+                issues.push(
+                    VerifyIssue::new(
+                        IssueSeverity::Error,
+                        Some(&current.path),
+                        "verify.leveldbMissingManifest",
+                    )
+                    .with("target", target),
+                );
+            }
+        }
+        issues
+    }
+
+    /// 与 `read_current_email_from_roxy` 相同的规则，从备份中的 Local Storage 读取邮箱
+    fn detect_email(&self, manifest: &ProfileManifest) -> Option<String> {
+        manifest
+            .files
+            .iter()
+            .filter(|e| {
+                e.path.starts_with("Local Storage/leveldb/")
+                    && (e.path.ends_with(".log") || e.path.ends_with(".ldb"))
+            })
+            .find_map(|e| {
+                let content = self.read_blob(&e.hash)?;
+                extract_email(&String::from_utf8_lossy(&content))
+            })
+    }
+}
+
+/// 检查 SQLite 文件头与文件长度，返回问题的消息键
+fn check_sqlite(path: &str, check: &BlobCheck) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let is_sqlite = check.head.starts_with(SQLITE_HEADER);
+    if !is_sqlite {
+        let expected = SQLITE_FILE_NAMES.contains(&name);
+        return (expected && check.size > 0).then_some("verify.notSqlite");
+    }
+    if check.head.len() < HEAD_SIZE {
+        return Some("verify.sqliteHeaderIncomplete");
+    }
+
+    let page_size = match u16::from_be_bytes([check.head[16], check.head[17]]) {
+        1 => 65536,
+        n => n as u64,
+    };
+    if !(512..=65536).contains(&page_size) || !page_size.is_power_of_two() {
+        return Some("verify.sqliteHeaderCorrupt");
+    }
+    if !check.size.is_multiple_of(page_size) {
+        return Some("verify.sqliteSizeIncomplete");
+    }
+
+    // 文件头中的页数仅在修改计数与有效版本号一致时可信
+    let read_u32 = |offset: usize| {
+        u32::from_be_bytes([
+            check.head[offset],
+            check.head[offset + 1],
+            check.head[offset + 2],
+            check.head[offset + 3],
+        ])
+    };
+    let page_count = read_u32(28) as u64;
+    if page_count > 0 && read_u32(24) == read_u32(92) && check.size < page_count * page_size {
+        return Some("verify.sqliteTruncated");
+    }
+    None
+}
+
+/// 检查 LevelDB 表文件末尾的魔数，返回问题的消息键
+fn check_leveldb_table(path: &str, check: &BlobCheck) -> Option<&'static str> {
+    if !(path.ends_with(".ldb") || path.ends_with(".sst")) {
+        return None;
+    }
+    let magic = <[u8; 8]>::try_from(check.tail.as_slice())
+        .map(u64::from_le_bytes)
+        .ok();
+    if check.size < LEVELDB_FOOTER_SIZE || magic != Some(LEVELDB_TABLE_MAGIC) {
+        return Some("verify.leveldbTableCorrupt");
+    }
+    None
+}
+
+fn has_errors(issues: &[VerifyIssue]) -> bool {
+    issues.iter().any(|i| i.severity == IssueSeverity::Error)
+}
+
+/// 从新到旧找到第一个没有错误的快照（跳过 `skip`）
+fn find_good_snapshot(
+    verifier: &mut Verifier,
+    user: &UserProfile,
+    skip: Option<&str>,
+) -> Option<String> {
+    store::list_snapshot_ids(&user.id)
+        .into_iter()
+        .rev()
+        .filter(|id| Some(id.as_str()) != skip)
+        .find(|id| {
+            store::load_snapshot(&user.id, id)
+                .map(|snapshot| !has_errors(&verifier.verify_manifest(&user.email, &snapshot).0))
+                .unwrap_or(false)
+        })
+}
+
+fn verify_user(verifier: &mut Verifier, user: &UserProfile) -> ProfileVerification {
+    let manifest = match store::load_manifest(&user.id) {
+        Ok(Some(manifest)) => manifest,
+        result => {
+            let error = match result {
+                Err(e) => e,
+                _ => AppError::new(ErrorCode::BackupMissing, "backup.notFound")
+                    .with("user", &user.id),
+            };
+            return ProfileVerification {
+                user_id: user.id.clone(),
+                email: user.email.clone(),
+                health: ProfileHealth::Missing,
+                snapshot_id: None,
+                detected_email: None,
+                issues: vec![VerifyIssue::from_error(IssueSeverity::Error, None, &error)],
+                repair_snapshot_id: find_good_snapshot(verifier, user, None),
+            };
+        }
+    };
+
+    let (issues, detected_email) = verifier.verify_manifest(&user.email, &manifest);
+    let (health, repair_snapshot_id) = if has_errors(&issues) {
+        let repair = find_good_snapshot(verifier, user, manifest.snapshot_id.as_deref());
+        (ProfileHealth::Corrupt, repair)
+    } else if issues.is_empty() {
+        (ProfileHealth::Ok, None)
+    } else {
+        (ProfileHealth::Warning, None)
+    };
+
+    ProfileVerification {
+        user_id: user.id.clone(),
+        email: user.email.clone(),
+        health,
+        snapshot_id: manifest.snapshot_id,
+        detected_email,
+        issues,
+        repair_snapshot_id,
+    }
+}
+
+/// 校验用户备份：文件清单、大小与哈希，LevelDB/SQLite 结构，以及能否识别登录邮箱
+///
+/// 不指定 `user_ids` 时校验全部用户；有错误的用户会给出可用于修复的最近完好快照。
+#[tauri::command]
+pub async fn verify_profiles(
+    app: AppHandle,
+    user_ids: Option<Vec<String>>,
+) -> Result<Vec<ProfileVerification>, AppError> {
+    progress::run_blocking(app, "verify_profiles", move || {
+        verify_profiles_blocking(user_ids)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "verify_profiles", skip_all, err(Display))]
+pub fn verify_profiles_blocking(
+    user_ids: Option<Vec<String>>,
+) -> Result<Vec<ProfileVerification>, AppError> {
+    // 校验期间不能有备份、删除快照等操作回收 blob，否则会误报缺失
+    let _operation = operation::begin(
+        "verify_profiles",
+        Description::new("activity.verifyProfiles"),
+    )?;
+    let config = load_config()?;
+    let users = match user_ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| {
+                config
+                    .find_user(id)
+                    .ok_or_else(|| AppError::user_not_found(id))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => config.users.iter().collect(),
+    };
+    let mut verifier = Verifier::new()?;
+    Ok(users
+        .into_iter()
+        .map(|user| verify_user(&mut verifier, user))
+        .collect())
+}
+
+/// 用完好的快照修复用户备份，返回使用的快照编号
+///
+/// 不指定 `snapshot_id` 时使用最近一个没有错误的快照；指定的快照有错误时拒绝修复。
+#[tauri::command]
+pub async fn repair_profile(
+    app: AppHandle,
+    user_id: String,
+    snapshot_id: Option<String>,
+) -> Result<String, AppError> {
+    progress::run_blocking(app, "repair_profile", move || {
+        repair_profile_blocking(user_id, snapshot_id)
+    })
+    .await
+}
+
+#[tracing::instrument(name = "repair_profile", skip_all, fields(user = %user_id), ret(Display), err(Display))]
+pub fn repair_profile_blocking(
+    user_id: String,
+    snapshot_id: Option<String>,
+) -> Result<String, AppError> {
+    store::ensure_safe_name(&user_id)?;
+    if let Some(ref id) = snapshot_id {
+        store::ensure_safe_name(id)?;
+    }
+    let _operation = operation::begin(
+        "repair_profile",
+        Description::new("activity.repairProfile").with("user", &user_id),
+    )?;
+    let user = load_config()?
+        .find_user(&user_id)
+        .cloned()
+        .ok_or_else(|| AppError::user_not_found(&user_id))?;
+    let mut verifier = Verifier::new()?;
+
+    let snapshot_id = match snapshot_id {
+        Some(id) => {
+            let snapshot = store::load_snapshot(&user_id, &id)?;
+            let (issues, _) = verifier.verify_manifest(&user.email, &snapshot);
+            if has_errors(&issues) {
+                return Err(
+                    AppError::new(ErrorCode::SnapshotCorrupt, "repair.snapshotCorrupt")
+                        .with("id", &id),
+                );
+            }
+            id
+        }
+        None => {
+            let head = store::load_manifest(&user_id)
+                .ok()
+                .flatten()
+                .and_then(|m| m.snapshot_id);
+            find_good_snapshot(&mut verifier, &user, head.as_deref()).ok_or_else(|| {
+                AppError::new(ErrorCode::SnapshotNotFound, "repair.noGoodSnapshot")
+                    .with("user", &user_id)
+            })?
+        }
+    };
+
+    let snapshot = store::load_snapshot(&user_id, &snapshot_id)?;
+    store::set_head(&snapshot)?;
+    Ok(snapshot_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::profile::{backup_roxy_data, get_roxy_data_dir};
+    use crate::commands::testing::{save_users, TestRoot};
+    use std::fs;
+
+    #[test]
+    fn verify_waits_for_running_operations() {
+        let _root = TestRoot::new("verify-busy");
+        save_users(&["a"], None);
+        let operation = operation::begin(
+            "switch_user",
+            Description::new("activity.switchUser").with("user", "a"),
+        )
+        .unwrap();
+        assert_eq!(
+            verify_profiles_blocking(None).unwrap_err().key,
+            "operation.busy"
+        );
+        drop(operation);
+
+        let results = verify_profiles_blocking(None).unwrap();
+        assert_eq!(results[0].health, ProfileHealth::Missing);
+    }
+
+    #[test]
+    fn missing_blob_marks_profile_corrupt() {
+        let _root = TestRoot::new("verify-blob");
+        save_users(&["a"], None);
+        let live = get_roxy_data_dir();
+        fs::create_dir_all(&live).unwrap();
+        fs::write(live.join("Cookies"), "c").unwrap();
+        backup_roxy_data("a").unwrap();
+        fs::remove_dir_all(store::get_store_dir().join("blobs")).unwrap();
+
+        let results = verify_profiles_blocking(Some(vec!["a".to_string()])).unwrap();
+        assert_eq!(results[0].health, ProfileHealth::Corrupt);
+        assert!(results[0]
+            .issues
+            .iter()
+            .any(|i| i.message_key == "store.blobMissing"));
+        assert_eq!(results[0].repair_snapshot_id, None);
+    }
+}
@@ -0,0 +1,259 @@
+use super::process;
+use super::profile::get_app_config_dir;
+use super::settings::{load_settings, WatchdogSettings};
+use super::watcher::RoxyExitedEvent;
+use crate::error::{AppError, ErrorCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 崩溃日志最多保留的条数
+const MAX_CRASH_RECORDS: usize = 200;
+
+/// 时间窗口内已经执行的自动重启
+static RESTARTS: Mutex<Vec<Instant>> = Mutex::new(Vec::new());
+
+/// 崩溃后的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchdogAction {
+    /// 未启用自动重启，或 RoxyBrowser 不是由本程序启动
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "restart")]
+    Restart,
+    /// 时间窗口内重启次数已达上限
+    #[serde(rename = "gaveUp")]
+    GaveUp,
+}
+
+/// 崩溃日志记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashRecord {
+    pub time: String,
+    pub pid: u32,
+    #[serde(rename = "exitCode")]
+    pub exit_code: Option<i32>,
+    #[serde(rename = "uptimeSecs")]
+    pub uptime_secs: u64,
+    pub action: WatchdogAction,
+    /// 第几次自动重启（时间窗口内）
+    #[serde(rename = "restartCount", default)]
+    pub restart_count: u32,
+    /// 重启前的等待秒数
+    #[serde(rename = "delaySecs", default)]
+    pub delay_secs: u64,
+    /// 重启失败时的错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn get_crash_log_path() -> PathBuf {
+    get_app_config_dir().join("crash_log.jsonl")
+}
+
+fn read_records() -> Vec<CrashRecord> {
+    fs::read_to_string(get_crash_log_path())
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 追加一条崩溃记录，超过上限时只保留最近的记录
+fn append_record(record: &CrashRecord) -> Result<(), AppError> {
+    let write_failed = |e: std::io::Error| AppError::new(ErrorCode::Io, "crash.write").caused_by(e);
+    let path = get_crash_log_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::new(ErrorCode::Io, "config.createDir").caused_by(e))?;
+    }
+    let line = serde_json::to_string(record)
+        .map_err(|e| AppError::new(ErrorCode::Internal, "crash.serialize").caused_by(e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(write_failed)?;
+    writeln!(file, "{}", line).map_err(write_failed)?;
+
+    let records = read_records();
+    if records.len() > MAX_CRASH_RECORDS * 2 {
+        let kept: Vec<String> = records[records.len() - MAX_CRASH_RECORDS..]
+            .iter()
+            .filter_map(|r| serde_json::to_string(r).ok())
+            .collect();
+        super::profile::write_file_atomic(&path, format!("{}\n", kept.join("\n")).as_bytes())
+            .map_err(write_failed)?;
+    }
+    Ok(())
+}
+
+/// 按策略决定是否重启，`launched` 为崩溃的进程是否由本程序启动，返回 (处理方式, 第几次重启, 等待秒数)
+fn plan_restart(policy: &WatchdogSettings, launched: bool) -> (WatchdogAction, u32, u64) {
+    if !policy.enabled || !launched {
+        return (WatchdogAction::None, 0, 0);
+    }
+
+    let mut restarts = RESTARTS.lock().unwrap_or_else(|e| e.into_inner());
+    let window = Duration::from_secs(policy.window_secs);
+    restarts.retain(|t| t.elapsed() < window);
+    if restarts.len() >= policy.max_restarts as usize {
+        return (WatchdogAction::GaveUp, restarts.len() as u32, 0);
+    }
+
+    restarts.push(Instant::now());
+    let count = restarts.len() as u32;
+    let delay = policy
+        .backoff_secs
+        .saturating_mul(1u64 << (count - 1).min(16))
+        .min(policy.max_backoff_secs);
+    (WatchdogAction::Restart, count, delay)
+}
+
+/// 处理 RoxyBrowser 崩溃：记录日志，并按策略在等待后自动重启
+///
+/// 只重启由本程序启动的进程；等待期间 RoxyBrowser 被主动停止、有切换用户等操作正在进行
+/// 或已经重新运行时放弃本次重启。
+pub fn handle_crash(event: &RoxyExitedEvent, launched: bool) {
+    let policy = load_settings().unwrap_or_default().watchdog;
+    let (action, restart_count, delay_secs) = plan_restart(&policy, launched);
+    let record = CrashRecord {
+        time: Utc::now().to_rfc3339(),
+        pid: event.pid,
+        exit_code: event.exit_code,
+        uptime_secs: event.uptime_secs,
+        action,
+        restart_count,
+        delay_secs,
+        error: None,
+    };
+    tracing::warn!(
+        pid = event.pid,
+        exit_code = ?event.exit_code,
+        uptime_secs = event.uptime_secs,
+        action = ?action,
+        "RoxyBrowser 异常退出"
+    );
+    if let Err(e) = append_record(&record) {
+        tracing::error!(error = %e, "写入崩溃日志失败");
+    }
+    if action != WatchdogAction::Restart {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(delay_secs));
+        // 持有操作锁期间重启，避免与切换用户等操作同时改动 RoxyBrowser；锁被占用时跳过
+        let Ok(_operation) = super::operation::begin(
+            "watchdog_restart",
+            super::operation::Description::new("activity.watchdogRestart"),
+        ) else {
+            return;
+        };
+        let running = process::find_roxy_processes(&process::refresh_processes())
+            .main
+            .is_some();
+        if !process::launched_by_manager() || running {
+            return;
+        }
+        if let Err(e) = process::launch_roxy(Some(false)) {
+            tracing::error!(error = %e, "自动重启 RoxyBrowser 失败");
+            let failed = CrashRecord {
+                time: Utc::now().to_rfc3339(),
+                error: Some(e.to_string()),
+                ..record
+            };
+            if let Err(e) = append_record(&failed) {
+                tracing::error!(error = %e, "写入崩溃日志失败");
+            }
+        }
+    });
+}
+
+/// Tauri 命令: 获取最近的崩溃记录（最新的在前）
+#[tauri::command]
+pub fn get_crash_log(limit: Option<usize>) -> Result<Vec<CrashRecord>, AppError> {
+    let mut records = read_records();
+    records.reverse();
+    records.truncate(limit.unwrap_or(MAX_CRASH_RECORDS));
+    Ok(records)
+}
+
+/// Tauri 命令: 清空崩溃记录
+#[tauri::command]
+pub fn clear_crash_log() -> Result<(), AppError> {
+    let path = get_crash_log_path();
+    if path.exists() {
+        fs::remove_file(&path)
+            .map_err(|e| AppError::new(ErrorCode::Io, "crash.remove").caused_by(e))?;
+    }
+    RESTARTS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::TestRoot;
+
+    fn record(pid: u32) -> CrashRecord {
+        CrashRecord {
+            time: String::new(),
+            pid,
+            exit_code: Some(1),
+            uptime_secs: 0,
+            action: WatchdogAction::None,
+            restart_count: 0,
+            delay_secs: 0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn crash_log_keeps_the_latest_records() {
+        let _root = TestRoot::new("crash-log");
+        for pid in 0..(MAX_CRASH_RECORDS * 2 + 1) as u32 {
+            append_record(&record(pid)).unwrap();
+        }
+        let records = get_crash_log(None).unwrap();
+        assert_eq!(records.len(), MAX_CRASH_RECORDS);
+        assert_eq!(records[0].pid, (MAX_CRASH_RECORDS * 2) as u32);
+        assert_eq!(get_crash_log(Some(3)).unwrap().len(), 3);
+
+        clear_crash_log().unwrap();
+        assert!(get_crash_log(None).unwrap().is_empty());
+        clear_crash_log().unwrap();
+    }
+
+    #[test]
+    fn restarts_only_manager_launched_processes() {
+        // 与清空崩溃日志的测试共用重启记录，持有测试目录锁避免同时运行
+        let _root = TestRoot::new("watchdog-restart");
+        let policy = WatchdogSettings {
+            enabled: true,
+            max_restarts: 2,
+            window_secs: 600,
+            backoff_secs: 5,
+            max_backoff_secs: 8,
+        };
+        RESTARTS.lock().unwrap().clear();
+
+        assert_eq!(plan_restart(&policy, false), (WatchdogAction::None, 0, 0));
+        assert_eq!(plan_restart(&policy, true), (WatchdogAction::Restart, 1, 5));
+        assert_eq!(plan_restart(&policy, true), (WatchdogAction::Restart, 2, 8));
+        assert_eq!(plan_restart(&policy, true), (WatchdogAction::GaveUp, 2, 0));
+        let disabled = WatchdogSettings {
+            enabled: false,
+            ..policy
+        };
+        assert_eq!(plan_restart(&disabled, true), (WatchdogAction::None, 0, 0));
+        RESTARTS.lock().unwrap().clear();
+    }
+}
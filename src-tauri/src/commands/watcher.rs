@@ -0,0 +1,232 @@
+use super::process::{self, RoxyProcessTree};
+use super::settings::load_settings;
+use super::tray::TRAY_ID;
+use crate::i18n;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::process::ExitStatus;
+use std::time::Duration;
+use sysinfo::{Pid, System};
+use tauri::{AppHandle, Emitter};
+
+/// RoxyBrowser 启动事件
+pub const EVENT_STARTED: &str = "roxy://started";
+/// RoxyBrowser 退出事件（包括崩溃）
+pub const EVENT_EXITED: &str = "roxy://exited";
+/// RoxyBrowser 崩溃事件
+pub const EVENT_CRASHED: &str = "roxy://crashed";
+
+/// 退出原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ExitReason {
+    /// 由本程序停止（切换用户、托盘菜单等）
+    #[serde(rename = "stopped")]
+    Stopped,
+    /// 用户关闭或正常退出
+    #[serde(rename = "exited")]
+    Exited,
+    /// 异常退出
+    #[serde(rename = "crashed")]
+    Crashed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoxyStartedEvent {
+    pub pid: u32,
+    #[serde(rename = "exePath")]
+    pub exe_path: Option<String>,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoxyExitedEvent {
+    pub pid: u32,
+    pub reason: ExitReason,
+    /// 仅在 RoxyBrowser 由本程序直接启动时可用
+    #[serde(rename = "exitCode")]
+    pub exit_code: Option<i32>,
+    #[serde(rename = "uptimeSecs")]
+    pub uptime_secs: u64,
+}
+
+/// 正在运行的主进程
+struct Running {
+    pid: u32,
+    start_time: u64,
+    kernels: Vec<Pid>,
+    /// 是否由本程序启动，崩溃后只自动重启这种情况
+    launched: bool,
+}
+
+fn interval() -> Duration {
+    let settings = load_settings().unwrap_or_default();
+    Duration::from_millis(settings.process_watch_interval_ms.max(500))
+}
+
+/// 托盘提示按当前语言生成，切换语言后下一次检测时更新
+fn tooltip(tree: &RoxyProcessTree) -> String {
+    let status = match tree.main {
+        Some(ref main) if tree.kernels.is_empty() => {
+            i18n::render("tray.running", [("pid", main.pid.to_string().as_str())])
+        }
+        Some(ref main) => i18n::render(
+            "tray.runningWithKernels",
+            [
+                ("pid", main.pid.to_string().as_str()),
+                ("kernels", tree.kernels.len().to_string().as_str()),
+            ],
+        ),
+        None => i18n::text("tray.notRunning").to_string(),
+    };
+    format!("RoxyBrowser Manager\n{}", status)
+}
+
+/// 判断退出原因：本程序停止的为主动停止；直接启动的按退出码判断；
+/// 否则主进程退出后仍有内核残留视为崩溃（正常退出时 RoxyBrowser 会先关闭内核）
+fn exit_reason(stop_requested: bool, status: Option<ExitStatus>, orphaned: bool) -> ExitReason {
+    if stop_requested {
+        ExitReason::Stopped
+    } else if status.map(|s| !s.success()).unwrap_or(orphaned) {
+        ExitReason::Crashed
+    } else {
+        ExitReason::Exited
+    }
+}
+
+fn exit_event(sys: &System, running: &Running) -> RoxyExitedEvent {
+    let status = process::take_exit_status(running.pid);
+    let orphaned = running
+        .kernels
+        .iter()
+        .any(|pid| sys.process(*pid).is_some_and(|p| !process::is_exited(p)));
+    let reason = exit_reason(process::take_stop_request(), status, orphaned);
+
+    RoxyExitedEvent {
+        pid: running.pid,
+        reason,
+        exit_code: status.and_then(|s| s.code()),
+        uptime_secs: (Utc::now().timestamp() as u64).saturating_sub(running.start_time),
+    }
+}
+
+/// 启动后台检测：RoxyBrowser 启动、退出或崩溃时向前端发送事件，并更新托盘提示
+pub fn spawn(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut sys = System::new();
+        let mut running: Option<Running> = None;
+        let mut last_tooltip = String::new();
+
+        loop {
+            process::refresh(&mut sys);
+            let tree = process::find_roxy_processes(&sys);
+            let main = tree.main.as_ref();
+            let kernels = tree
+                .kernels
+                .iter()
+                .map(|k| Pid::from_u32(k.process.pid))
+                .collect();
+
+            let same = matches!((&running, main), (Some(r), Some(m)) if r.pid == m.pid && r.start_time == m.start_time);
+            if same {
+                if let Some(ref mut r) = running {
+                    r.kernels = kernels;
+                }
+            } else {
+                if let Some(previous) = running.take() {
+                    let event = exit_event(&sys, &previous);
+                    if event.reason == ExitReason::Crashed {
+                        let _ = app.emit(EVENT_CRASHED, event.clone());
+                        super::watchdog::handle_crash(&event, previous.launched);
+                    }
+                    let _ = app.emit(EVENT_EXITED, event);
+                }
+                if let Some(main) = main {
+                    let _ = app.emit(
+                        EVENT_STARTED,
+                        RoxyStartedEvent {
+                            pid: main.pid,
+                            exe_path: main.exe.clone(),
+                            started_at: DateTime::<Utc>::from_timestamp(main.start_time as i64, 0)
+                                .map(|t| t.to_rfc3339()),
+                        },
+                    );
+                    running = Some(Running {
+                        pid: main.pid,
+                        start_time: main.start_time,
+                        kernels,
+                        launched: process::claim_launch(),
+                    });
+                }
+            }
+
+            let text = tooltip(&tree);
+            if text != last_tooltip {
+                if let Some(tray) = app.tray_by_id(TRAY_ID) {
+                    let _ = tray.set_tooltip(Some(&text));
+                }
+                last_tooltip = text;
+            }
+
+            std::thread::sleep(interval());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit_status(code: i32) -> ExitStatus {
+        #[cfg(unix)]
+        let status = std::os::unix::process::ExitStatusExt::from_raw(code << 8);
+        #[cfg(windows)]
+        let status = std::os::windows::process::ExitStatusExt::from_raw(code as u32);
+        status
+    }
+
+    #[test]
+    fn classifies_exit_reasons() {
+        assert_eq!(
+            exit_reason(true, Some(exit_status(3)), true),
+            ExitReason::Stopped
+        );
+        // 直接启动的按退出码判断，不看内核是否残留
+        assert_eq!(
+            exit_reason(false, Some(exit_status(3)), false),
+            ExitReason::Crashed
+        );
+        assert_eq!(
+            exit_reason(false, Some(exit_status(0)), true),
+            ExitReason::Exited
+        );
+        assert_eq!(exit_reason(false, None, true), ExitReason::Crashed);
+        assert_eq!(exit_reason(false, None, false), ExitReason::Exited);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_with_running_kernels_is_a_crash() {
+        // 停止请求是全局状态，与其他停止 RoxyBrowser 的测试互斥
+        let _root = crate::commands::testing::TestRoot::new("watcher-exit");
+        let mut kernel = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let running = Running {
+            pid: u32::MAX,
+            start_time: 0,
+            kernels: vec![Pid::from_u32(kernel.id())],
+            launched: false,
+        };
+        let mut sys = System::new();
+
+        process::refresh(&mut sys);
+        assert_eq!(exit_event(&sys, &running).reason, ExitReason::Crashed);
+
+        kernel.kill().unwrap();
+        kernel.wait().unwrap();
+        process::refresh(&mut sys);
+        assert_eq!(exit_event(&sys, &running).reason, ExitReason::Exited);
+    }
+}
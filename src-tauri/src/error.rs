@@ -0,0 +1,182 @@
+use crate::i18n;
+use serde::ser::{SerializeStruct, Serializer};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 稳定的错误码，前端据此区分错误类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorCode {
+    /// 未找到 RoxyBrowser 安装路径
+    #[serde(rename = "ROXY_NOT_FOUND")]
+    RoxyNotFound,
+    /// RoxyBrowser 无法启动或在启动过程中退出
+    #[serde(rename = "ROXY_START_FAILED")]
+    RoxyStartFailed,
+    /// RoxyBrowser 已启动但未在规定时间内就绪
+    #[serde(rename = "ROXY_NOT_READY")]
+    RoxyNotReady,
+    /// RoxyBrowser 进程未能在规定时间内退出
+    #[serde(rename = "STOP_TIMEOUT")]
+    StopTimeout,
+    /// 配置中没有指定的用户
+    #[serde(rename = "USER_NOT_FOUND")]
+    UserNotFound,
+    /// 不能对当前活动用户执行该操作
+    #[serde(rename = "ACTIVE_USER")]
+    ActiveUser,
+    /// 未能从 RoxyBrowser 数据中检测到登录用户
+    #[serde(rename = "LOGIN_NOT_DETECTED")]
+    LoginNotDetected,
+    /// 已有切换、导入等操作正在进行
+    #[serde(rename = "OPERATION_BUSY")]
+    OperationBusy,
+    /// 操作已被用户取消
+    #[serde(rename = "CANCELLED")]
+    Cancelled,
+    /// 要取消的操作不存在或已经结束
+    #[serde(rename = "OPERATION_NOT_FOUND")]
+    OperationNotFound,
+    /// 操作不支持取消
+    #[serde(rename = "NOT_CANCELLABLE")]
+    NotCancellable,
+    /// 指定的备份项集合不存在
+    #[serde(rename = "ITEM_SET_NOT_FOUND")]
+    ItemSetNotFound,
+    /// 路径无效或选择的文件不可用
+    #[serde(rename = "INVALID_PATH")]
+    InvalidPath,
+    /// 设置值超出允许范围，或设置文件格式错误
+    #[serde(rename = "INVALID_SETTINGS")]
+    InvalidSettings,
+    /// 没有可导出的用户
+    #[serde(rename = "NOTHING_TO_EXPORT")]
+    NothingToExport,
+    /// 导入的目录或归档不存在
+    #[serde(rename = "IMPORT_SOURCE_NOT_FOUND")]
+    ImportSourceNotFound,
+    /// 加密备份需要密码
+    #[serde(rename = "PASSPHRASE_REQUIRED")]
+    PassphraseRequired,
+    /// 导入来源缺少配置或配置无法解析
+    #[serde(rename = "INVALID_IMPORT")]
+    InvalidImport,
+    /// 用户没有备份，或备份引用的数据缺失
+    #[serde(rename = "BACKUP_MISSING")]
+    BackupMissing,
+    /// 备份缺少备份项集合中的必需项
+    #[serde(rename = "BACKUP_INCOMPLETE")]
+    BackupIncomplete,
+    /// 用户没有指定的快照
+    #[serde(rename = "SNAPSHOT_NOT_FOUND")]
+    SnapshotNotFound,
+    /// 快照校验有错误，不能用于修复
+    #[serde(rename = "SNAPSHOT_CORRUPT")]
+    SnapshotCorrupt,
+    /// 本地备份已加密但尚未解锁
+    #[serde(rename = "STORE_LOCKED")]
+    StoreLocked,
+    /// 本地备份未启用加密
+    #[serde(rename = "STORE_NOT_ENCRYPTED")]
+    StoreNotEncrypted,
+    /// 当前系统不支持钥匙串
+    #[serde(rename = "KEYRING_UNAVAILABLE")]
+    KeyringUnavailable,
+    /// 密码错误
+    #[serde(rename = "WRONG_PASSPHRASE")]
+    WrongPassphrase,
+    /// 加密数据认证失败、文件头或清单损坏
+    #[serde(rename = "DATA_CORRUPTED")]
+    DataCorrupted,
+    /// 归档格式、版本或内容不符合要求
+    #[serde(rename = "INVALID_ARCHIVE")]
+    InvalidArchive,
+    /// 切换用户失败（已回滚或等待下次启动时恢复）
+    #[serde(rename = "SWITCH_FAILED")]
+    SwitchFailed,
+    /// 读写配置或数据文件失败
+    #[serde(rename = "IO")]
+    Io,
+    /// 其他模块返回的未分类错误
+    #[serde(rename = "INTERNAL")]
+    Internal,
+}
+
+/// 命令返回给前端的错误
+///
+/// 序列化为 `{ code, messageKey, params, message, source }`：前端按 `code` 分支，
+/// 按 `messageKey` 和 `params` 本地化，`message` 为渲染好的消息，`source` 为底层错误链。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppError {
+    pub code: ErrorCode,
+    /// 消息目录中的键
+    pub key: &'static str,
+    pub params: BTreeMap<&'static str, String>,
+    /// 底层错误，由外到内
+    pub source: Vec<String>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, key: &'static str) -> Self {
+        AppError {
+            code,
+            key,
+            params: BTreeMap::new(),
+            source: Vec::new(),
+        }
+    }
+
+    /// 添加消息参数
+    pub fn with(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.params.insert(name, value.to_string());
+        self
+    }
+
+    /// 追加底层错误
+    pub fn caused_by(mut self, source: impl fmt::Display) -> Self {
+        self.source.push(source.to_string());
+        self
+    }
+
+    pub fn user_not_found(user_id: &str) -> Self {
+        AppError::new(ErrorCode::UserNotFound, "user.notFound").with("user", user_id)
+    }
+
+    pub fn cancelled() -> Self {
+        AppError::new(ErrorCode::Cancelled, "operation.cancelled")
+    }
+
+    /// 按当前语言渲染的消息，底层错误以 `: ` 接在后面
+    pub fn message(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()));
+        let mut message = i18n::render(self.key, params);
+        for source in &self.source {
+            message.push_str(": ");
+            message.push_str(source);
+        }
+        message
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 5)?;
+        state.serialize_field("code", &self.code)?;
+        state.serialize_field("messageKey", self.key)?;
+        state.serialize_field("params", &self.params)?;
+        state.serialize_field("message", &self.message())?;
+        state.serialize_field("source", &self.source)?;
+        state.end()
+    }
+}
@@ -0,0 +1,491 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en-US")]
+    EnUs,
+}
+
+/// 当前语言，首次使用时从设置中读取
+static CURRENT: Mutex<Option<Language>> = Mutex::new(None);
+
+/// 简体中文消息，`{name}` 由参数替换；其他语言缺少的键回退到这里
+const ZH_CN: &[(&str, &str)] = &[
+    // 托盘与对话框
+    ("tray.show", "显示窗口"),
+    ("tray.startRoxy", "启动 RoxyBrowser"),
+    ("tray.stopRoxy", "停止 RoxyBrowser"),
+    ("tray.quit", "退出"),
+    ("tray.running", "RoxyBrowser 运行中 (PID {pid})"),
+    ("tray.runningWithKernels", "RoxyBrowser 运行中 (PID {pid})，{kernels} 个环境"),
+    ("tray.notRunning", "RoxyBrowser 未运行"),
+    ("dialog.selectExecutable", "选择 RoxyBrowser 可执行文件"),
+    ("dialog.executableFilter", "可执行文件"),
+    ("dialog.applicationFilter", "应用程序"),
+    ("dialog.selectArchive", "选择要导入的备份文件"),
+    ("dialog.selectExportDir", "选择导出目录"),
+    ("dialog.selectImportDir", "选择导入配置目录"),
+    ("dialog.archiveFilter", "RoxyBrowser Manager 备份"),
+    ("itemSet.builtinName", "默认"),
+    // 错误
+    ("roxy.notFound", "未找到 RoxyBrowser 安装路径。\n\n可能的原因：\n• RoxyBrowser 未安装在默认位置\n• 需要手动配置安装路径\n\n请在设置中手动指定可执行文件位置。"),
+    ("roxy.spawnFailed", "无法启动 RoxyBrowser"),
+    ("roxy.bundleExecutableUnknown", "无法确定应用包中的可执行文件"),
+    ("roxy.openFailed", "无法启动 RoxyBrowser: open 返回 {status}"),
+    ("roxy.exitedDuringStartup", "RoxyBrowser 在启动过程中退出"),
+    ("roxy.startFailed", "RoxyBrowser 启动失败: {status}"),
+    ("roxy.notReady", "RoxyBrowser 未能在 {seconds} 秒内就绪（本地端口 {port} 无法连接）"),
+    ("roxy.notDetected", "启动后 {seconds} 秒内未检测到 RoxyBrowser 进程"),
+    ("roxy.stopTimeout", "RoxyBrowser 进程未能在 {seconds} 秒内退出，请手动关闭后重试"),
+    ("user.notFound", "用户 {user} 不存在"),
+    ("user.deleteActive", "无法删除当前活动用户"),
+    ("user.loginNotDetected", "无法检测到登录用户，请确保已完成登录"),
+    ("user.newProfileMissing", "无法找到新用户配置"),
+    ("operation.busy", "操作进行中: {description}（开始于 {startedAt}），请稍后再试"),
+    ("operation.busyOther", "另一个 RoxyBrowser Manager（PID {pid}）正在执行: {description}，请稍后再试"),
+    ("operation.lockFailed", "无法获取操作锁，请稍后再试"),
+    ("operation.serialize", "无法序列化操作锁"),
+    ("operation.writeLock", "无法写入操作锁"),
+    ("operation.createLock", "无法创建操作锁"),
+    ("operation.aborted", "操作异常中止"),
+    ("operation.cancelled", "操作已取消，已写入的内容已清理"),
+    ("operation.notFound", "没有找到该操作，可能已经结束"),
+    ("operation.notCancellable", "该操作无法取消: {description}"),
+    ("itemSet.notFound", "备份项集合 {id} 不存在"),
+    ("path.invalidExecutable", "无效的 RoxyBrowser 可执行文件路径"),
+    ("path.invalidSelection", "所选文件不是有效的 RoxyBrowser 可执行文件"),
+    ("path.fileUnavailable", "无法获取文件路径"),
+    ("path.folderUnavailable", "无法获取文件夹路径"),
+    ("settings.shutdownTooLong", "等待时间不能超过 300 秒"),
+    ("settings.killTimeoutTooShort", "强制结束后的等待时间至少为 1 秒"),
+    ("settings.watchIntervalRange", "检测间隔必须在 500 到 60000 毫秒之间"),
+    ("settings.readyTimeoutRange", "就绪等待时间必须在 1 到 600 秒之间"),
+    ("settings.invalidPort", "无效的端口号"),
+    ("settings.watchdogWindow", "时间窗口至少为 1 秒"),
+    ("settings.watchdogBackoff", "重启等待时间不能超过最大等待时间"),
+    ("settings.keepLastRange", "保留的快照数量必须在 1 到 1000 之间"),
+    ("settings.keepDailyDaysRange", "按天保留快照的天数不能超过 3650 天"),
+    ("settings.trayUpdateFailed", "无法更新托盘菜单"),
+    ("config.dirUnavailable", "无法获取配置目录"),
+    ("config.createDir", "无法创建配置目录"),
+    ("config.read", "无法读取配置文件"),
+    ("config.parse", "配置文件格式错误"),
+    ("config.serialize", "无法序列化配置"),
+    ("config.write", "无法写入配置文件"),
+    ("config.readUsers", "无法读取用户配置文件"),
+    ("config.usersCorrupt", "用户配置文件已损坏，请修复或从备份恢复后再试"),
+    ("export.nothing", "没有可导出的用户配置"),
+    ("export.noMatch", "没有符合条件的用户配置"),
+    ("export.createDir", "无法创建导出目录"),
+    ("export.createProfilesDir", "无法创建profiles目录"),
+    ("export.serialize", "序列化配置失败"),
+    ("export.write", "写入配置失败"),
+    ("import.sourceNotFound", "导入路径不存在"),
+    ("import.passphraseRequired", "该备份文件已加密，请输入密码"),
+    ("import.configMissing", "导入目录中未找到 config.json"),
+    ("import.readConfig", "读取配置失败"),
+    ("import.parseConfig", "解析配置失败"),
+    ("temp.createDir", "无法创建临时目录"),
+    ("log.read", "无法读取日志文件"),
+    ("log.createFile", "无法创建日志文件"),
+    ("log.init", "无法初始化日志"),
+    ("crash.serialize", "无法序列化崩溃记录"),
+    ("crash.write", "无法写入崩溃日志"),
+    ("crash.remove", "无法删除崩溃日志"),
+    ("diagnostics.write", "写入诊断包失败"),
+    ("config.save", "无法保存配置"),
+    ("backup.notFound", "未找到用户 {user} 的备份"),
+    ("backup.missingRequired", "缺少必需的备份项: {items}"),
+    ("backup.missingRequiredFor", "{user} 的备份缺少必需项: {items}"),
+    ("launch.invalidEnvName", "无效的环境变量名: {name}"),
+    ("launch.workingDirMissing", "工作目录不存在: {path}"),
+    ("itemSet.invalidPattern", "无效的匹配模式 {pattern}"),
+    ("itemSet.invalidExclude", "无效的排除模式"),
+    ("itemSet.notRelative", "备份项必须是数据目录内的相对路径: {pattern}"),
+    ("itemSet.emptyId", "备份项集合编号不能为空"),
+    ("itemSet.duplicateId", "备份项集合编号重复: {id}"),
+    ("itemSet.empty", "备份项集合 {name} 没有任何备份项"),
+    ("itemSet.invalidVersion", "无效的 RoxyBrowser 版本号或重复的版本号: {version}"),
+    ("store.readManifest", "无法读取 {user} 的备份清单"),
+    ("store.manifestCorrupt", "{user} 的备份清单格式错误"),
+    ("store.serializeManifest", "无法序列化备份清单"),
+    ("store.writeManifest", "无法写入备份清单"),
+    ("store.removeManifest", "无法删除备份清单"),
+    ("store.removeSnapshots", "无法删除快照"),
+    ("store.readFile", "无法读取文件 {path}"),
+    ("store.readDir", "无法读取目录 {path}"),
+    ("store.createDir", "无法创建目录 {path}"),
+    ("store.writeBlob", "无法写入备份数据"),
+    ("store.readBlob", "无法读取备份数据 {hash}"),
+    ("store.blobMissing", "备份数据缺失: {hash}"),
+    ("store.fileMissing", "备份数据缺失: {path} ({hash})"),
+    ("store.blobCorrupt", "备份数据已损坏"),
+    ("store.restoreFile", "无法还原 {path}"),
+    ("store.removeLegacy", "无法删除已迁移的目录 {path}"),
+    ("snapshot.notFound", "未找到快照: {user} / {id}"),
+    ("snapshot.corrupt", "快照 {id} 格式错误"),
+    ("snapshot.remove", "无法删除快照 {id}"),
+    ("repair.snapshotCorrupt", "快照 {id} 同样存在错误，无法用于修复"),
+    ("repair.noGoodSnapshot", "{user} 没有可用于修复的完好快照"),
+    ("crypto.unsupportedKdf", "不支持的密钥派生算法: {algorithm}"),
+    ("crypto.invalidKdf", "密钥派生参数无效"),
+    ("crypto.deriveFailed", "密钥派生失败"),
+    ("crypto.encryptFailed", "加密失败"),
+    ("crypto.read", "读取失败"),
+    ("crypto.write", "写入失败"),
+    ("crypto.tampered", "备份文件已损坏或被篡改"),
+    ("crypto.notEncrypted", "不是有效的加密备份文件"),
+    ("crypto.headerCorrupt", "加密备份文件头已损坏"),
+    ("crypto.wrongPassphrase", "密码错误"),
+    ("crypto.serializeHeader", "无法序列化加密文件头"),
+    ("crypto.openSource", "无法打开备份文件"),
+    ("crypto.createOutput", "无法创建输出文件"),
+    ("archive.create", "无法创建归档文件"),
+    ("archive.write", "写入归档失败"),
+    ("archive.serialize", "序列化归档内容失败"),
+    ("archive.open", "无法打开备份文件"),
+    ("archive.corrupt", "备份文件已损坏"),
+    ("archive.manifestMissing", "归档中未找到 manifest.json"),
+    ("archive.manifestInvalid", "解析归档清单失败"),
+    ("archive.manifestTooLarge", "归档清单过大，文件可能已损坏或被篡改"),
+    ("archive.notRoxybak", "不是有效的 RoxyBrowser Manager 备份文件"),
+    ("archive.tooNew", "备份文件由更新版本 ({version}) 创建，请升级后再导入"),
+    ("archive.unsafePath", "备份文件包含非法路径: {path}"),
+    ("archive.unexpectedFile", "备份文件包含清单外的文件: {path}"),
+    ("archive.extractFile", "无法解压 {path}"),
+    ("archive.hashMismatch", "文件校验失败，备份可能已损坏或被篡改: {path}"),
+    ("archive.incomplete", "备份文件不完整：部分文件缺失"),
+    ("archive.sizeMismatch", "文件大小与清单不符，备份可能已损坏或被篡改: {path}"),
+    ("vault.readKeyfile", "无法读取密钥文件"),
+    ("vault.keyfileCorrupt", "密钥文件已损坏"),
+    ("vault.serializeKeyfile", "无法序列化密钥文件"),
+    ("vault.writeKeyfile", "无法写入密钥文件"),
+    ("vault.removeKeyfile", "无法删除密钥文件"),
+    ("vault.createStoreDir", "无法创建存储目录"),
+    ("vault.keyringFailed", "无法访问系统钥匙串"),
+    ("vault.keyringUnavailable", "当前系统不支持钥匙串，请使用主密码"),
+    ("vault.locked", "本地备份已加密，请先输入主密码解锁"),
+    ("vault.passwordRequired", "主密码不能为空"),
+    ("vault.notEnabled", "未启用本地备份加密"),
+    ("switch.readJournal", "无法读取切换日志"),
+    ("switch.journalCorrupt", "切换日志格式错误"),
+    ("switch.serializeJournal", "无法序列化切换日志"),
+    ("switch.writeJournal", "无法写入切换日志"),
+    ("switch.createRollbackDir", "无法创建回滚目录"),
+    ("switch.createDataDir", "无法创建 RoxyBrowser 数据目录"),
+    ("switch.cleanStaging", "无法清理暂存目录"),
+    ("switch.cleanRollback", "无法清理回滚目录"),
+    ("switch.moveOut", "无法移出 {item}"),
+    ("switch.moveIn", "无法换入 {item}"),
+    ("switch.clean", "无法清理 {item}"),
+    ("switch.restore", "无法还原 {item}"),
+    ("switch.moveBack", "无法撤回已换入的 {item}"),
+    ("switch.rolledBack", "切换失败，已恢复原用户数据"),
+    ("switch.rollbackPending", "切换失败且回滚未完成，将在下次启动时继续恢复原用户数据"),
+    ("import.overwriteActive", "不能覆盖当前活动用户，请先切换到其他用户或选择保留两份"),
+    ("store.invalidName", "名称不能用作存储路径: {name}"),
+    ("store.fileChanged", "文件 {path} 在备份过程中被修改，请关闭 RoxyBrowser 后重试"),
+    ("import.invalidUserId", "导入的配置包含非法的用户 ID: {user}"),
+    ("vault.sealedBlobsRemain", "仍有 {count} 个备份文件未能解密，已保留密钥文件，请重试"),
+    ("export.done", "成功导出 {count} 个用户配置到 {destination}"),
+    ("export.doneWithSkipped", "成功导出 {count} 个用户配置到 {destination}（已排除 {skipped} 个锁文件或缓存文件）"),
+    ("import.done", "成功导入 {count} 个用户配置"),
+    ("import.doneWithFailed", "成功导入 {count} 个用户配置，{failed} 个失败"),
+    ("verify.hashMismatch", "内容哈希不一致"),
+    ("verify.sizeMismatch", "文件大小不一致：清单 {expected} 字节，实际 {actual} 字节"),
+    ("verify.emailNotDetected", "备份中无法识别登录邮箱，切换后 RoxyBrowser 可能处于未登录状态"),
+    ("verify.emailMismatch", "备份中的登录邮箱为 {found}，与用户 {email} 不一致"),
+    ("verify.leveldbMissingCurrent", "LevelDB 缺少 CURRENT 文件"),
+    ("verify.leveldbMissingManifest", "LevelDB CURRENT 指向的清单文件缺失: {target}"),
+    ("verify.notSqlite", "不是有效的 SQLite 数据库"),
+    ("verify.sqliteHeaderIncomplete", "SQLite 文件头不完整"),
+    ("verify.sqliteHeaderCorrupt", "SQLite 文件头已损坏"),
+    ("verify.sqliteSizeIncomplete", "SQLite 文件长度不完整"),
+    ("verify.sqliteTruncated", "SQLite 文件被截断"),
+    ("verify.leveldbTableCorrupt", "LevelDB 表文件已损坏"),
+    // 操作说明
+    ("activity.startRoxy", "启动 RoxyBrowser"),
+    ("activity.stopRoxy", "停止 RoxyBrowser"),
+    ("activity.switchUser", "切换到 {user}"),
+    ("activity.deleteUser", "删除用户 {user}"),
+    ("activity.updateUserNote", "更新 {user} 的备注"),
+    ("activity.setUserBackupItemSet", "设置 {user} 的备份项集合"),
+    ("activity.setUserLaunchConfig", "设置 {user} 的启动配置"),
+    ("activity.prepareNewUser", "准备添加新用户"),
+    ("activity.finalizeNewUser", "完成添加新用户"),
+    ("activity.exportProfiles", "导出用户配置"),
+    ("activity.importProfiles", "导入用户配置"),
+    ("activity.restoreSnapshot", "恢复 {user} 的快照 {id}"),
+    ("activity.repairProfile", "修复 {user} 的备份"),
+    ("activity.enableStoreEncryption", "启用存储加密"),
+    ("activity.unlockStore", "解锁存储"),
+    ("activity.lockStore", "锁定存储"),
+    ("activity.disableStoreEncryption", "关闭存储加密"),
+    ("activity.watchdogRestart", "自动重启 RoxyBrowser"),
+    ("activity.startup", "启动时恢复、清理和迁移"),
+    ("activity.verifyProfiles", "校验用户备份"),
+];
+
+/// 英文消息
+const EN_US: &[(&str, &str)] = &[
+    ("tray.show", "Show Window"),
+    ("tray.startRoxy", "Start RoxyBrowser"),
+    ("tray.stopRoxy", "Stop RoxyBrowser"),
+    ("tray.quit", "Quit"),
+    ("tray.running", "RoxyBrowser running (PID {pid})"),
+    ("tray.runningWithKernels", "RoxyBrowser running (PID {pid}), {kernels} environments"),
+    ("tray.notRunning", "RoxyBrowser not running"),
+    ("dialog.selectExecutable", "Select the RoxyBrowser executable"),
+    ("dialog.executableFilter", "Executables"),
+    ("dialog.applicationFilter", "Applications"),
+    ("dialog.selectArchive", "Select the backup file to import"),
+    ("dialog.selectExportDir", "Select the export folder"),
+    ("dialog.selectImportDir", "Select the folder to import from"),
+    ("dialog.archiveFilter", "RoxyBrowser Manager backups"),
+    ("itemSet.builtinName", "Default"),
+    ("roxy.notFound", "RoxyBrowser installation not found.\n\nPossible causes:\n• RoxyBrowser is not installed in the default location\n• The installation path needs to be configured manually\n\nPlease choose the executable in Settings."),
+    ("roxy.spawnFailed", "Failed to start RoxyBrowser"),
+    ("roxy.bundleExecutableUnknown", "Cannot determine the executable inside the application bundle"),
+    ("roxy.openFailed", "Failed to start RoxyBrowser: open returned {status}"),
+    ("roxy.exitedDuringStartup", "RoxyBrowser exited during startup"),
+    ("roxy.startFailed", "RoxyBrowser failed to start: {status}"),
+    ("roxy.notReady", "RoxyBrowser was not ready within {seconds} seconds (cannot connect to local port {port})"),
+    ("roxy.notDetected", "No RoxyBrowser process detected within {seconds} seconds after launch"),
+    ("roxy.stopTimeout", "RoxyBrowser did not exit within {seconds} seconds, please close it manually and try again"),
+    ("user.notFound", "User {user} does not exist"),
+    ("user.deleteActive", "Cannot delete the active user"),
+    ("user.loginNotDetected", "No signed-in user detected, please make sure you have finished signing in"),
+    ("user.newProfileMissing", "Cannot find the new user's profile"),
+    ("operation.busy", "Operation in progress: {description} (started at {startedAt}), please try again later"),
+    ("operation.busyOther", "Another RoxyBrowser Manager (PID {pid}) is running: {description}, please try again later"),
+    ("operation.lockFailed", "Cannot acquire the operation lock, please try again later"),
+    ("operation.serialize", "Failed to serialize the operation lock"),
+    ("operation.writeLock", "Failed to write the operation lock"),
+    ("operation.createLock", "Failed to create the operation lock"),
+    ("operation.aborted", "The operation was aborted unexpectedly"),
+    ("operation.cancelled", "The operation was cancelled and partially written data was removed"),
+    ("operation.notFound", "Operation not found, it may have already finished"),
+    ("operation.notCancellable", "This operation cannot be cancelled: {description}"),
+    ("itemSet.notFound", "Backup item set {id} does not exist"),
+    ("path.invalidExecutable", "Invalid RoxyBrowser executable path"),
+    ("path.invalidSelection", "The selected file is not a valid RoxyBrowser executable"),
+    ("path.fileUnavailable", "Cannot get the file path"),
+    ("path.folderUnavailable", "Cannot get the folder path"),
+    ("settings.shutdownTooLong", "Wait times cannot exceed 300 seconds"),
+    ("settings.killTimeoutTooShort", "The wait after force-killing must be at least 1 second"),
+    ("settings.watchIntervalRange", "The check interval must be between 500 and 60000 ms"),
+    ("settings.readyTimeoutRange", "The ready timeout must be between 1 and 600 seconds"),
+    ("settings.invalidPort", "Invalid port number"),
+    ("settings.watchdogWindow", "The time window must be at least 1 second"),
+    ("settings.watchdogBackoff", "The restart delay cannot exceed the maximum delay"),
+    ("settings.keepLastRange", "The number of snapshots to keep must be between 1 and 1000"),
+    ("settings.keepDailyDaysRange", "Daily snapshots cannot be kept for more than 3650 days"),
+    ("settings.trayUpdateFailed", "Failed to update the tray menu"),
+    ("config.dirUnavailable", "Cannot determine the config directory"),
+    ("config.createDir", "Failed to create the config directory"),
+    ("config.read", "Failed to read the settings file"),
+    ("config.parse", "The settings file is malformed"),
+    ("config.serialize", "Failed to serialize the settings"),
+    ("config.write", "Failed to write the settings file"),
+    ("config.readUsers", "Failed to read the user config file"),
+    ("config.usersCorrupt", "The user config file is corrupt; repair it or restore it from a backup and try again"),
+    ("export.nothing", "There are no user profiles to export"),
+    ("export.noMatch", "No user profiles match the filter"),
+    ("export.createDir", "Failed to create the export directory"),
+    ("export.createProfilesDir", "Failed to create the profiles directory"),
+    ("export.serialize", "Failed to serialize the config"),
+    ("export.write", "Failed to write the config"),
+    ("import.sourceNotFound", "The import path does not exist"),
+    ("import.passphraseRequired", "This backup is encrypted, please enter the password"),
+    ("import.configMissing", "config.json was not found in the import directory"),
+    ("import.readConfig", "Failed to read the config"),
+    ("import.parseConfig", "Failed to parse the config"),
+    ("temp.createDir", "Failed to create a temporary directory"),
+    ("log.read", "Failed to read the log file"),
+    ("log.createFile", "Failed to create the log file"),
+    ("log.init", "Failed to initialize logging"),
+    ("crash.serialize", "Failed to serialize the crash record"),
+    ("crash.write", "Failed to write the crash log"),
+    ("crash.remove", "Failed to delete the crash log"),
+    ("diagnostics.write", "Failed to write the diagnostics bundle"),
+    ("config.save", "Failed to save the config"),
+    ("backup.notFound", "No backup found for user {user}"),
+    ("backup.missingRequired", "Required backup items are missing: {items}"),
+    ("backup.missingRequiredFor", "The backup of {user} is missing required items: {items}"),
+    ("launch.invalidEnvName", "Invalid environment variable name: {name}"),
+    ("launch.workingDirMissing", "The working directory does not exist: {path}"),
+    ("itemSet.invalidPattern", "Invalid pattern {pattern}"),
+    ("itemSet.invalidExclude", "Invalid exclude patterns"),
+    ("itemSet.notRelative", "Backup items must be relative paths inside the data directory: {pattern}"),
+    ("itemSet.emptyId", "The backup item set id cannot be empty"),
+    ("itemSet.duplicateId", "Duplicate backup item set id: {id}"),
+    ("itemSet.empty", "Backup item set {name} has no items"),
+    ("itemSet.invalidVersion", "Invalid or duplicate RoxyBrowser version: {version}"),
+    ("store.readManifest", "Failed to read the backup manifest of {user}"),
+    ("store.manifestCorrupt", "The backup manifest of {user} is malformed"),
+    ("store.serializeManifest", "Failed to serialize the backup manifest"),
+    ("store.writeManifest", "Failed to write the backup manifest"),
+    ("store.removeManifest", "Failed to remove the backup manifest"),
+    ("store.removeSnapshots", "Failed to remove the snapshots"),
+    ("store.readFile", "Failed to read file {path}"),
+    ("store.readDir", "Failed to read directory {path}"),
+    ("store.createDir", "Failed to create directory {path}"),
+    ("store.writeBlob", "Failed to write backup data"),
+    ("store.readBlob", "Failed to read backup data {hash}"),
+    ("store.blobMissing", "Backup data is missing: {hash}"),
+    ("store.fileMissing", "Backup data is missing: {path} ({hash})"),
+    ("store.blobCorrupt", "The backup data is corrupted"),
+    ("store.restoreFile", "Failed to restore {path}"),
+    ("store.removeLegacy", "Failed to remove the migrated directory {path}"),
+    ("snapshot.notFound", "Snapshot not found: {user} / {id}"),
+    ("snapshot.corrupt", "Snapshot {id} is malformed"),
+    ("snapshot.remove", "Failed to remove snapshot {id}"),
+    ("repair.snapshotCorrupt", "Snapshot {id} also has errors and cannot be used for repair"),
+    ("repair.noGoodSnapshot", "{user} has no intact snapshot to repair from"),
+    ("crypto.unsupportedKdf", "Unsupported key derivation algorithm: {algorithm}"),
+    ("crypto.invalidKdf", "Invalid key derivation parameters"),
+    ("crypto.deriveFailed", "Key derivation failed"),
+    ("crypto.encryptFailed", "Encryption failed"),
+    ("crypto.read", "Read failed"),
+    ("crypto.write", "Write failed"),
+    ("crypto.tampered", "The backup file is corrupted or has been tampered with"),
+    ("crypto.notEncrypted", "Not a valid encrypted backup file"),
+    ("crypto.headerCorrupt", "The encrypted backup header is corrupted"),
+    ("crypto.wrongPassphrase", "Wrong password"),
+    ("crypto.serializeHeader", "Failed to serialize the encryption header"),
+    ("crypto.openSource", "Failed to open the backup file"),
+    ("crypto.createOutput", "Failed to create the output file"),
+    ("archive.create", "Failed to create the archive file"),
+    ("archive.write", "Failed to write the archive"),
+    ("archive.serialize", "Failed to serialize the archive contents"),
+    ("archive.open", "Failed to open the backup file"),
+    ("archive.corrupt", "The backup file is corrupted"),
+    ("archive.manifestMissing", "manifest.json was not found in the archive"),
+    ("archive.manifestInvalid", "Failed to parse the archive manifest"),
+    ("archive.manifestTooLarge", "The archive manifest is too large, the file may be corrupted or tampered with"),
+    ("archive.notRoxybak", "Not a valid RoxyBrowser Manager backup file"),
+    ("archive.tooNew", "The backup was created by a newer version ({version}), please upgrade before importing"),
+    ("archive.unsafePath", "The backup contains an illegal path: {path}"),
+    ("archive.unexpectedFile", "The backup contains a file not listed in its manifest: {path}"),
+    ("archive.extractFile", "Failed to extract {path}"),
+    ("archive.hashMismatch", "File verification failed, the backup may be corrupted or tampered with: {path}"),
+    ("archive.incomplete", "The backup is incomplete: some files are missing"),
+    ("archive.sizeMismatch", "File size does not match the manifest, the backup may be corrupted or tampered with: {path}"),
+    ("vault.readKeyfile", "Failed to read the key file"),
+    ("vault.keyfileCorrupt", "The key file is corrupted"),
+    ("vault.serializeKeyfile", "Failed to serialize the key file"),
+    ("vault.writeKeyfile", "Failed to write the key file"),
+    ("vault.removeKeyfile", "Failed to remove the key file"),
+    ("vault.createStoreDir", "Failed to create the store directory"),
+    ("vault.keyringFailed", "Cannot access the system keychain"),
+    ("vault.keyringUnavailable", "The system keychain is not available, please use a master password"),
+    ("vault.locked", "Local backups are encrypted, please unlock them with the master password first"),
+    ("vault.passwordRequired", "The master password cannot be empty"),
+    ("vault.notEnabled", "Local backup encryption is not enabled"),
+    ("switch.readJournal", "Failed to read the switch journal"),
+    ("switch.journalCorrupt", "The switch journal is malformed"),
+    ("switch.serializeJournal", "Failed to serialize the switch journal"),
+    ("switch.writeJournal", "Failed to write the switch journal"),
+    ("switch.createRollbackDir", "Failed to create the rollback directory"),
+    ("switch.createDataDir", "Failed to create the RoxyBrowser data directory"),
+    ("switch.cleanStaging", "Failed to clean the staging directory"),
+    ("switch.cleanRollback", "Failed to clean the rollback directory"),
+    ("switch.moveOut", "Failed to move out {item}"),
+    ("switch.moveIn", "Failed to move in {item}"),
+    ("switch.clean", "Failed to clean {item}"),
+    ("switch.restore", "Failed to restore {item}"),
+    ("switch.moveBack", "Failed to move back the swapped-in {item}"),
+    ("switch.rolledBack", "Switching failed, the previous user's data was restored"),
+    ("switch.rollbackPending", "Switching failed and the rollback did not finish, the previous user's data will be restored on next launch"),
+    ("import.overwriteActive", "Cannot overwrite the active user, switch to another user first or choose to keep both"),
+    ("store.invalidName", "The name cannot be used as a storage path: {name}"),
+    ("store.fileChanged", "File {path} changed while it was being backed up; close RoxyBrowser and try again"),
+    ("import.invalidUserId", "The imported config contains an invalid user ID: {user}"),
+    ("vault.sealedBlobsRemain", "{count} backup files are still encrypted; the key file was kept, please try again"),
+    ("export.done", "Exported {count} user profiles to {destination}"),
+    ("export.doneWithSkipped", "Exported {count} user profiles to {destination} ({skipped} lock or cache files excluded)"),
+    ("import.done", "Imported {count} user profiles"),
+    ("import.doneWithFailed", "Imported {count} user profiles, {failed} failed"),
+    ("verify.hashMismatch", "Content hash mismatch"),
+    ("verify.sizeMismatch", "File size mismatch: manifest says {expected} bytes, actual {actual} bytes"),
+    ("verify.emailNotDetected", "No login email found in the backup; RoxyBrowser may be signed out after switching"),
+    ("verify.emailMismatch", "The backup is signed in as {found}, which does not match user {email}"),
+    ("verify.leveldbMissingCurrent", "LevelDB is missing its CURRENT file"),
+    ("verify.leveldbMissingManifest", "The manifest referenced by LevelDB CURRENT is missing: {target}"),
+    ("verify.notSqlite", "Not a valid SQLite database"),
+    ("verify.sqliteHeaderIncomplete", "The SQLite header is incomplete"),
+    ("verify.sqliteHeaderCorrupt", "The SQLite header is corrupted"),
+    ("verify.sqliteSizeIncomplete", "The SQLite file length is not a whole number of pages"),
+    ("verify.sqliteTruncated", "The SQLite file is truncated"),
+    ("verify.leveldbTableCorrupt", "The LevelDB table file is corrupted"),
+    ("activity.startRoxy", "Start RoxyBrowser"),
+    ("activity.stopRoxy", "Stop RoxyBrowser"),
+    ("activity.switchUser", "Switch to {user}"),
+    ("activity.deleteUser", "Delete user {user}"),
+    ("activity.updateUserNote", "Update the note of {user}"),
+    ("activity.setUserBackupItemSet", "Set the backup item set of {user}"),
+    ("activity.setUserLaunchConfig", "Set the launch config of {user}"),
+    ("activity.prepareNewUser", "Prepare to add a new user"),
+    ("activity.finalizeNewUser", "Finish adding a new user"),
+    ("activity.exportProfiles", "Export profiles"),
+    ("activity.importProfiles", "Import profiles"),
+    ("activity.restoreSnapshot", "Restore snapshot {id} of {user}"),
+    ("activity.repairProfile", "Repair the backup of {user}"),
+    ("activity.enableStoreEncryption", "Enable store encryption"),
+    ("activity.unlockStore", "Unlock the store"),
+    ("activity.lockStore", "Lock the store"),
+    ("activity.disableStoreEncryption", "Disable store encryption"),
+    ("activity.watchdogRestart", "Restart RoxyBrowser automatically"),
+    ("activity.startup", "Startup recovery, cleanup and migration"),
+    ("activity.verifyProfiles", "Verify user backups"),
+];
+
+fn catalog(language: Language) -> &'static [(&'static str, &'static str)] {
+    match language {
+        Language::ZhCn => ZH_CN,
+        Language::EnUs => EN_US,
+    }
+}
+
+fn lookup(catalog: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+}
+
+/// 当前语言
+pub fn current_language() -> Language {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    *current.get_or_insert_with(|| {
+        crate::commands::settings::load_settings()
+            .map(|s| s.language)
+            .unwrap_or_default()
+    })
+}
+
+/// 切换语言，之后渲染的消息使用新语言
+pub fn set_current_language(language: Language) {
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(language);
+}
+
+/// 按当前语言取出消息模板，未收录的键原样返回
+pub fn text(key: &str) -> &str {
+    lookup(catalog(current_language()), key)
+        .or_else(|| lookup(ZH_CN, key))
+        .unwrap_or(key)
+}
+
+/// 按当前语言渲染消息，替换模板中的 `{name}`
+pub fn render<'a>(key: &str, params: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    let mut message = text(key).to_string();
+    for (name, value) in params {
+        message = message.replace(&format!("{{{}}}", name), value);
+    }
+    message
+}
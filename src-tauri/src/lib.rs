@@ -1,9 +1,13 @@
 mod commands;
+mod error;
+mod i18n;
 mod models;
 
-use commands::{process, profile, settings};
+use commands::{
+    logging, operation, process, profile, settings, snapshot, store, switch, tray, vault, verify,
+    watchdog, watcher,
+};
 use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
     tray::{TrayIconBuilder, TrayIconEvent},
     Manager, WindowEvent,
 };
@@ -15,25 +19,40 @@ pub fn run() {
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
-            // 创建系统托盘菜单
-            let show = MenuItemBuilder::with_id("show", "显示窗口").build(app)?;
-            let separator1 = tauri::menu::PredefinedMenuItem::separator(app)?;
-            let start_roxy = MenuItemBuilder::with_id("start_roxy", "启动 RoxyBrowser").build(app)?;
-            let stop_roxy = MenuItemBuilder::with_id("stop_roxy", "停止 RoxyBrowser").build(app)?;
-            let separator2 = tauri::menu::PredefinedMenuItem::separator(app)?;
-            let quit = MenuItemBuilder::with_id("quit", "退出").build(app)?;
+            // 日志写入配置目录下按天滚动的文件，失败时不影响启动
+            if let Err(e) = logging::init() {
+                logging::init_stderr();
+                tracing::error!(error = %e, "无法写入日志文件，日志改为输出到标准错误");
+            }
+
+            // 恢复、清理和迁移会改写 RoxyBrowser 目录和存储，另一个管理器实例正在操作时跳过，留到下次启动
+            if let Some(running) = operation::running_operation() {
+                tracing::warn!(pid = running.pid, operation = %running.kind, "另一个管理器实例正在执行操作，跳过启动时的恢复、清理和迁移");
+            } else {
+                match operation::begin("startup", operation::Description::new("activity.startup")) {
+                    Ok(_operation) => {
+                        // 上次切换用户若被中断（崩溃、断电、磁盘写满），启动时自动向前完成或回滚
+                        if let Err(e) = switch::recover_interrupted_switch() {
+                            tracing::error!(error = %e, "恢复未完成的用户切换失败");
+                        }
+
+                        // 上次运行中断时残留的明文导出或解密文件；另一个实例运行时其中可能有它正在使用的文件
+                        profile::clean_private_temp();
+
+                        // 首次运行新版本时，将旧版 profiles/{email} 完整备份迁移到去重存储
+                        if let Err(e) = store::migrate_legacy_profiles() {
+                            tracing::error!(error = %e, "迁移旧版用户备份失败");
+                        }
+                    }
+                    Err(e) => tracing::warn!(error = %e, "跳过启动时的恢复、清理和迁移"),
+                }
+            }
 
-            let menu = MenuBuilder::new(app)
-                .item(&show)
-                .item(&separator1)
-                .item(&start_roxy)
-                .item(&stop_roxy)
-                .item(&separator2)
-                .item(&quit)
-                .build()?;
+            // 创建系统托盘菜单（按设置中的语言）
+            let menu = tray::build_menu(app.handle())?;
 
             // 创建系统托盘图标
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id(tray::TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&menu)
                 .tooltip("RoxyBrowser Manager")
@@ -44,11 +63,20 @@ pub fn run() {
                             let _ = window.set_focus();
                         }
                     }
+                    // 与界面上的命令一样经过操作锁，在后台执行避免阻塞托盘菜单
                     "start_roxy" => {
-                        let _ = process::start_roxy();
+                        tauri::async_runtime::spawn_blocking(|| {
+                            if let Err(e) = process::start_roxy_blocking(None) {
+                                tracing::warn!(error = %e, "托盘启动 RoxyBrowser 失败");
+                            }
+                        });
                     }
                     "stop_roxy" => {
-                        let _ = process::stop_roxy();
+                        tauri::async_runtime::spawn_blocking(|| {
+                            if let Err(e) = process::stop_roxy_blocking() {
+                                tracing::warn!(error = %e, "托盘停止 RoxyBrowser 失败");
+                            }
+                        });
                     }
                     "quit" => {
                         app.exit(0);
@@ -67,6 +95,9 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            // 后台检测 RoxyBrowser 进程状态，推送给前端并更新托盘提示
+            watcher::spawn(app.handle().clone());
+
             Ok(())
         })
         .on_window_event(|window, event| {
@@ -81,16 +112,40 @@ pub fn run() {
             process::get_roxy_status,
             process::start_roxy,
             process::stop_roxy,
+            watchdog::get_crash_log,
+            watchdog::clear_crash_log,
+            operation::get_running_operation,
+            operation::cancel_operation,
+            // 日志与诊断
+            logging::get_recent_logs,
+            logging::export_diagnostics,
             // 配置文件管理
             profile::list_users,
             profile::switch_user,
             profile::delete_user,
             profile::update_user_note,
+            profile::set_user_backup_item_set,
+            profile::set_user_launch_config,
             profile::prepare_for_new_user,
             profile::finalize_new_user,
             // 导入导出
             profile::export_profiles,
             profile::import_profiles,
+            profile::inspect_import_source,
+            profile::preview_import,
+            profile::import_selected_profiles,
+            // 快照
+            snapshot::list_snapshots,
+            snapshot::restore_snapshot,
+            // 完整性校验
+            verify::verify_profiles,
+            verify::repair_profile,
+            // 本地备份加密
+            vault::get_store_encryption_status,
+            vault::enable_store_encryption,
+            vault::unlock_store,
+            vault::lock_store,
+            vault::disable_store_encryption,
             // 设置管理
             settings::get_roxy_exe_path,
             settings::set_roxy_exe_path,
@@ -98,7 +153,28 @@ pub fn run() {
             settings::auto_detect_roxy_path,
             settings::browse_for_exe,
             settings::browse_for_folder,
+            settings::browse_for_archive,
             settings::clear_roxy_exe_path,
+            settings::get_snapshot_retention,
+            settings::set_snapshot_retention,
+            settings::get_backup_item_sets,
+            settings::set_backup_item_sets,
+            settings::get_exclude_patterns,
+            settings::set_exclude_patterns,
+            settings::get_shutdown_settings,
+            settings::set_shutdown_settings,
+            settings::get_startup_settings,
+            settings::set_startup_settings,
+            settings::get_default_launch_config,
+            settings::set_default_launch_config,
+            settings::get_watchdog_settings,
+            settings::set_watchdog_settings,
+            settings::get_process_watch_interval,
+            settings::set_process_watch_interval,
+            settings::get_language,
+            settings::set_language,
+            settings::get_log_level,
+            settings::set_log_level,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
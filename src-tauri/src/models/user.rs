@@ -1,7 +1,12 @@
+use crate::error::{AppError, ErrorCode};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserProfile {
+    /// 用户 ID，备份存储和当前用户都以它为键；旧版配置中没有，载入时取邮箱
+    #[serde(default)]
+    pub id: String,
     pub email: String,
     #[serde(rename = "displayName")]
     pub display_name: String,
@@ -11,11 +16,77 @@ pub struct UserProfile {
     pub last_used: String,
     #[serde(default)]
     pub note: String,
+    /// 单独为该用户指定的备份项集合，未设置时使用默认集合
+    #[serde(
+        rename = "backupItemSet",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub backup_item_set: Option<String>,
+    /// 切换到该用户后启动 RoxyBrowser 使用的参数，未设置时使用默认配置
+    #[serde(
+        rename = "launchConfig",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub launch_config: Option<LaunchConfig>,
+}
+
+/// RoxyBrowser 启动配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LaunchConfig {
+    /// 命令行参数，例如 `--lang=en-US`
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 环境变量，例如 `HTTPS_PROXY`
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// 工作目录，未设置时使用可执行文件所在目录
+    #[serde(rename = "workingDir", default)]
+    pub working_dir: Option<String>,
+}
+
+impl LaunchConfig {
+    /// 在默认配置上叠加用户配置：参数追加在默认参数之后，同名环境变量和工作目录以用户配置为准
+    pub fn merged_with(&self, user: &LaunchConfig) -> LaunchConfig {
+        let mut env = self.env.clone();
+        env.extend(user.env.clone());
+        LaunchConfig {
+            args: self.args.iter().chain(&user.args).cloned().collect(),
+            env,
+            working_dir: user
+                .working_dir
+                .clone()
+                .or_else(|| self.working_dir.clone()),
+        }
+    }
+
+    /// 检查环境变量名和工作目录
+    pub fn validate(&self) -> Result<(), AppError> {
+        for key in self.env.keys() {
+            if key.is_empty() || key.contains('=') || key.contains('\0') {
+                return Err(
+                    AppError::new(ErrorCode::InvalidSettings, "launch.invalidEnvName")
+                        .with("name", key),
+                );
+            }
+        }
+        if let Some(ref dir) = self.working_dir {
+            if !std::path::Path::new(dir).is_dir() {
+                return Err(
+                    AppError::new(ErrorCode::InvalidSettings, "launch.workingDirMissing")
+                        .with("path", dir),
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub version: u32,
+    /// 当前用户的 ID
     #[serde(rename = "currentUser")]
     pub current_user: Option<String>,
     pub users: Vec<UserProfile>,
@@ -30,3 +101,28 @@ impl Default for AppConfig {
         }
     }
 }
+
+impl AppConfig {
+    /// 为旧版配置中没有 ID 的用户补上 ID（即邮箱）
+    pub fn fill_missing_ids(&mut self) {
+        for user in &mut self.users {
+            if user.id.is_empty() {
+                user.id = user.email.clone();
+            }
+        }
+    }
+
+    /// 按 ID 查找用户
+    pub fn find_user(&self, id: &str) -> Option<&UserProfile> {
+        self.users.iter().find(|u| u.id == id)
+    }
+
+    /// 按邮箱查找用户：同一邮箱有多个用户（导入时“保留两者”产生的副本）时，
+    /// 优先以邮箱为 ID 的用户，其次最近使用的用户
+    pub fn find_user_by_email(&self, email: &str) -> Option<&UserProfile> {
+        self.users
+            .iter()
+            .filter(|u| u.email.eq_ignore_ascii_case(email))
+            .max_by_key(|u| (u.id == u.email, u.last_used.as_str()))
+    }
+}
@@ -1,10 +1,21 @@
-import { User, Play, Square, RefreshCw, Plus, Download, Upload, Settings, Sun, Moon, FolderOpen } from "lucide-react";
+import { User, Play, Square, RefreshCw, Plus, Download, Upload, Settings, Sun, Moon, FolderOpen, FileArchive } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useStore } from "../stores/useStore";
 import UserCard from "./UserCard";
 import AddUserWizard from "./AddUserWizard";
 import SettingsModal from "./SettingsModal";
 import { useEffect, useState } from "react";
+import { errorMessage, isAppError, isPassphraseError } from "../types/error";
+import {
+    OPERATION_PROGRESS_EVENT,
+    OperationProgress,
+    formatBytes,
+    formatEta,
+    isCancellable,
+    phaseLabel,
+    progressPercent,
+} from "../types/progress";
 
 export default function Dashboard() {
     const {
@@ -61,29 +72,92 @@ export default function Dashboard() {
         return () => window.removeEventListener('keydown', handleKeyDown);
     }, [openWizard, refreshStatus]);
 
+    // 切换用户、导入导出的进度
+    const [progress, setProgress] = useState<OperationProgress | null>(null);
+    useEffect(() => {
+        const unlisten = listen<OperationProgress>(OPERATION_PROGRESS_EVENT, (event) => {
+            setProgress(event.payload);
+        });
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+    useEffect(() => {
+        if (!isLoading) {
+            setProgress(null);
+        }
+    }, [isLoading]);
+
+    // 取消导入导出，后端会清理已写入的内容
+    const handleCancelOperation = async () => {
+        if (!progress) return;
+        try {
+            await invoke("cancel_operation", { operationId: progress.operationId });
+        } catch (error) {
+            alert(errorMessage(error));
+        }
+    };
+
+    // 运行时长，精确到分钟
+    const formatUptime = (secs: number) => {
+        const hours = Math.floor(secs / 3600);
+        const minutes = Math.floor((secs % 3600) / 60);
+        return hours > 0 ? `${hours} 小时 ${minutes} 分钟` : `${minutes} 分钟`;
+    };
+
+    const isCancelled = (error: unknown) => isAppError(error) && error.code === "CANCELLED";
+
     // 导出配置（使用原生文件夹选择器）
     const handleExportWithDialog = async () => {
         try {
-            const selectedPath = await invoke<string | null>("browse_for_folder", { title: "选择导出目录" });
+            const selectedPath = await invoke<string | null>("browse_for_folder", { titleKey: "dialog.selectExportDir" });
             if (selectedPath) {
                 const result = await exportProfiles(selectedPath);
-                alert(result);
+                alert(result.message);
             }
         } catch (error) {
-            alert(`导出失败: ${error}`);
+            alert(isCancelled(error) ? errorMessage(error) : `导出失败: ${errorMessage(error)}`);
         }
     };
 
     // 导入配置（使用原生文件夹选择器）
     const handleImportWithDialog = async () => {
         try {
-            const selectedPath = await invoke<string | null>("browse_for_folder", { title: "选择导入配置目录" });
+            const selectedPath = await invoke<string | null>("browse_for_folder", { titleKey: "dialog.selectImportDir" });
+            if (selectedPath) {
+                await importWithPassphrase(selectedPath);
+            }
+        } catch (error) {
+            alert(isCancelled(error) ? errorMessage(error) : `导入失败: ${errorMessage(error)}`);
+        }
+    };
+
+    // 导入 .roxybak 备份文件（使用原生文件选择器）
+    const handleImportArchiveWithDialog = async () => {
+        try {
+            const selectedPath = await invoke<string | null>("browse_for_archive");
             if (selectedPath) {
-                const result = await importProfiles(selectedPath);
-                alert(result);
+                await importWithPassphrase(selectedPath);
             }
         } catch (error) {
-            alert(`导入失败: ${error}`);
+            alert(isCancelled(error) ? errorMessage(error) : `导入失败: ${errorMessage(error)}`);
+        }
+    };
+
+    // 加密归档需要密码：提示输入后重试，用户取消输入时放弃导入
+    const importWithPassphrase = async (path: string) => {
+        let passphrase: string | undefined;
+        for (;;) {
+            try {
+                const result = await importProfiles(path, passphrase);
+                alert(result.message);
+                return;
+            } catch (error) {
+                if (!isPassphraseError(error)) throw error;
+                const input = prompt(passphrase === undefined ? "该备份已加密，请输入密码" : "密码错误，请重新输入");
+                if (input === null) return;
+                passphrase = input;
+            }
         }
     };
 
@@ -152,6 +226,19 @@ export default function Dashboard() {
                                     导入配置
                                 </a>
                             </li>
+                            <li>
+                                <a
+                                    onClick={(e) => {
+                                        e.preventDefault();
+                                        (document.activeElement as HTMLElement)?.blur();
+                                        handleImportArchiveWithDialog();
+                                    }}
+                                    className={isLoading ? 'disabled' : ''}
+                                >
+                                    <FileArchive className="w-4 h-4" />
+                                    导入备份文件
+                                </a>
+                            </li>
                         </ul>
                     </div>
                 </div>
@@ -164,6 +251,35 @@ export default function Dashboard() {
                 </div>
             )}
 
+            {/* 操作进度 */}
+            {isLoading && progress && (
+                <div className="card bg-base-100 shadow-lg mb-4">
+                    <div className="card-body p-4 gap-2">
+                        <div className="flex items-center justify-between text-sm">
+                            <span>
+                                {phaseLabel(progress.phase)}
+                                {progress.user ? ` · ${progress.user}` : ''}
+                            </span>
+                            <span className="text-base-content/60">
+                                {formatBytes(progress.bytesDone)} / {formatBytes(progress.bytesTotal)}
+                                {progress.etaSecs !== null ? ` · ${formatEta(progress.etaSecs)}` : ''}
+                            </span>
+                        </div>
+                        <div className="flex items-center gap-3">
+                            <progress className="progress progress-primary flex-1" value={progressPercent(progress)} max={100} />
+                            {isCancellable(progress) && (
+                                <button className="btn btn-ghost btn-xs" onClick={handleCancelOperation}>
+                                    取消
+                                </button>
+                            )}
+                        </div>
+                        {progress.currentItem && (
+                            <div className="text-xs text-base-content/50 truncate">{progress.currentItem}</div>
+                        )}
+                    </div>
+                </div>
+            )}
+
             {/* 状态卡片 */}
             <div className="card bg-base-100 shadow-lg mb-6">
                 <div className="card-body p-4">
@@ -173,6 +289,12 @@ export default function Dashboard() {
                                 <span className={`w-2 h-2 rounded-full ${roxyStatus.isRunning ? 'bg-success animate-pulse' : 'bg-base-content/30'}`} />
                                 {roxyStatus.isRunning ? 'RoxyBrowser 运行中' : 'RoxyBrowser 已停止'}
                             </div>
+                            {roxyStatus.isRunning && (
+                                <span className="text-xs text-base-content/60" title={roxyStatus.exePath ?? undefined}>
+                                    {roxyStatus.uptimeSecs !== null ? `已运行 ${formatUptime(roxyStatus.uptimeSecs)} · ` : ''}
+                                    内存 {formatBytes(roxyStatus.memoryBytes)} · CPU {roxyStatus.cpuUsage.toFixed(0)}% · {roxyStatus.kernelCount} 个环境
+                                </span>
+                            )}
                         </div>
                         <div className="flex items-center gap-2">
                             <button
@@ -209,9 +331,9 @@ export default function Dashboard() {
                 ) : (
                     users.map((user) => (
                         <UserCard
-                            key={user.email}
+                            key={user.id}
                             user={user}
-                            isActive={user.email === currentUser}
+                            isActive={user.id === currentUser}
                         />
                     ))
                 )}
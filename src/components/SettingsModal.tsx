@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { X, FolderOpen, Search, Trash2, CheckCircle, XCircle } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../types/error";
 
 interface SettingsModalProps {
     isOpen: boolean;
@@ -60,7 +61,7 @@ export default function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                 await validatePath(selectedPath);
             }
         } catch (err) {
-            setError(err as string);
+            setError(errorMessage(err));
         } finally {
             setIsLoading(false);
         }
@@ -78,7 +79,7 @@ export default function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                 setError("未检测到 RoxyBrowser 安装路径，请手动选择");
             }
         } catch (err) {
-            setError(err as string);
+            setError(errorMessage(err));
         } finally {
             setIsLoading(false);
         }
@@ -97,7 +98,7 @@ export default function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                 await invoke("clear_roxy_exe_path");
                 onClose();
             } catch (err) {
-                setError(err as string);
+                setError(errorMessage(err));
             }
             return;
         }
@@ -113,7 +114,7 @@ export default function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
             await invoke("set_roxy_exe_path", { path: currentPath });
             onClose();
         } catch (err) {
-            setError(err as string);
+            setError(errorMessage(err));
         } finally {
             setIsLoading(false);
         }
@@ -14,13 +14,13 @@ export default function UserCard({ user, isActive }: UserCardProps) {
 
     const handleSwitch = () => {
         if (!isActive) {
-            switchUser(user.email);
+            switchUser(user.id);
         }
     };
 
     const handleDelete = () => {
         if (confirm(`确定要删除用户 ${user.email} 吗？\n\n此操作将删除该用户的本地配置备份。`)) {
-            deleteUser(user.email);
+            deleteUser(user.id);
         }
     };
 
@@ -31,7 +31,7 @@ export default function UserCard({ user, isActive }: UserCardProps) {
 
     const handleSaveNote = async () => {
         try {
-            await updateUserNote(user.email, noteValue);
+            await updateUserNote(user.id, noteValue);
             setIsEditingNote(false);
         } catch (e) {
             console.error("Error saving note:", e);
@@ -1,7 +1,9 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage, isPassphraseError } from "../types/error";
 
 export interface UserProfile {
+    id: string;
     email: string;
     displayName: string;
     createdAt: string;
@@ -9,11 +11,67 @@ export interface UserProfile {
     note: string;
 }
 
-interface RoxyStatus {
+export interface ExportSummary {
+    users: number;
+    destination: string;
+    skipped: number;
+    message: string;
+}
+
+export interface ImportSummary {
+    imported: number;
+    failed: number;
+    message: string;
+}
+
+export interface RoxyProcess {
+    pid: number;
+    parentPid: number | null;
+    name: string;
+    exe: string | null;
+    // 常驻内存（字节）
+    memory: number;
+    // CPU 占用（百分比，多核时可能超过 100）
+    cpuUsage: number;
+    // 启动时间（Unix 时间戳，秒）
+    startTime: number;
+}
+
+// 浏览器环境内核进程及其渲染、GPU 等子进程
+export interface RoxyKernel extends RoxyProcess {
+    helpers: RoxyProcess[];
+}
+
+export interface RoxyStatus {
     isRunning: boolean;
     pid: number | null;
+    exePath: string | null;
+    startedAt: string | null;
+    uptimeSecs: number | null;
+    // 整个进程树的常驻内存合计（字节）
+    memoryBytes: number;
+    // 整个进程树的 CPU 占用合计（百分比）
+    cpuUsage: number;
+    kernelCount: number;
+    main: RoxyProcess | null;
+    children: RoxyProcess[];
+    kernels: RoxyKernel[];
 }
 
+const STOPPED_STATUS: RoxyStatus = {
+    isRunning: false,
+    pid: null,
+    exePath: null,
+    startedAt: null,
+    uptimeSecs: null,
+    memoryBytes: 0,
+    cpuUsage: 0,
+    kernelCount: 0,
+    main: null,
+    children: [],
+    kernels: [],
+};
+
 interface AppState {
     // 状态
     users: UserProfile[];
@@ -32,8 +90,8 @@ interface AppState {
     // Actions
     loadUsers: () => Promise<void>;
     refreshStatus: () => Promise<void>;
-    switchUser: (email: string) => Promise<void>;
-    deleteUser: (email: string) => Promise<void>;
+    switchUser: (userId: string) => Promise<void>;
+    deleteUser: (userId: string) => Promise<void>;
     startRoxy: () => Promise<void>;
     stopRoxy: () => Promise<void>;
 
@@ -45,11 +103,11 @@ interface AppState {
     finalizeNewUser: () => Promise<void>;
 
     // 导入导出
-    exportProfiles: (path: string) => Promise<string>;
-    importProfiles: (path: string) => Promise<string>;
+    exportProfiles: (path: string) => Promise<ExportSummary>;
+    importProfiles: (path: string, passphrase?: string) => Promise<ImportSummary>;
 
     // 更新备注
-    updateUserNote: (email: string, note: string) => Promise<void>;
+    updateUserNote: (userId: string, note: string) => Promise<void>;
 
     // 设置模态框 Actions
     openSettingsModal: () => void;
@@ -60,7 +118,7 @@ export const useStore = create<AppState>((set, get) => ({
     // 初始状态
     users: [],
     currentUser: null,
-    roxyStatus: { isRunning: false, pid: null },
+    roxyStatus: STOPPED_STATUS,
     isLoading: false,
     error: null,
     wizardOpen: false,
@@ -74,7 +132,7 @@ export const useStore = create<AppState>((set, get) => ({
             const result = await invoke<{ users: UserProfile[]; currentUser: string | null }>("list_users");
             set({ users: result.users, currentUser: result.currentUser, isLoading: false });
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
@@ -89,25 +147,25 @@ export const useStore = create<AppState>((set, get) => ({
     },
 
     // 切换用户
-    switchUser: async (email: string) => {
+    switchUser: async (userId: string) => {
         try {
             set({ isLoading: true, error: null });
-            await invoke("switch_user", { email });
+            await invoke("switch_user", { userId });
             await get().loadUsers();
             await get().refreshStatus();
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
     // 删除用户
-    deleteUser: async (email: string) => {
+    deleteUser: async (userId: string) => {
         try {
             set({ isLoading: true, error: null });
-            await invoke("delete_user", { email });
+            await invoke("delete_user", { userId });
             await get().loadUsers();
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
@@ -119,7 +177,7 @@ export const useStore = create<AppState>((set, get) => ({
             await get().refreshStatus();
             set({ isLoading: false });
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
@@ -131,7 +189,7 @@ export const useStore = create<AppState>((set, get) => ({
             await get().refreshStatus();
             set({ isLoading: false });
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
@@ -146,7 +204,7 @@ export const useStore = create<AppState>((set, get) => ({
             await invoke("prepare_for_new_user");
             set({ wizardStep: 1, isLoading: false });
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
@@ -157,7 +215,7 @@ export const useStore = create<AppState>((set, get) => ({
             await get().loadUsers();
             set({ wizardOpen: false, wizardStep: 0, isLoading: false });
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
         }
     },
 
@@ -165,38 +223,39 @@ export const useStore = create<AppState>((set, get) => ({
     exportProfiles: async (path: string) => {
         try {
             set({ isLoading: true, error: null });
-            const result = await invoke<string>("export_profiles", { exportPath: path });
+            const result = await invoke<ExportSummary>("export_profiles", { exportPath: path });
             set({ isLoading: false });
             return result;
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
             throw error;
         }
     },
 
     // 导入配置
-    importProfiles: async (path: string) => {
+    importProfiles: async (path: string, passphrase?: string) => {
         try {
             set({ isLoading: true, error: null });
-            const result = await invoke<string>("import_profiles", { importPath: path });
+            const result = await invoke<ImportSummary>("import_profiles", { importPath: path, passphrase });
             await get().loadUsers();
             set({ isLoading: false });
             return result;
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            // 需要密码时由调用方提示输入后重试，不显示为错误
+            set({ error: isPassphraseError(error) ? null : errorMessage(error), isLoading: false });
             throw error;
         }
     },
 
     // 更新用户备注
-    updateUserNote: async (email: string, note: string) => {
+    updateUserNote: async (userId: string, note: string) => {
         try {
             set({ isLoading: true, error: null });
-            await invoke("update_user_note", { email, note });
+            await invoke("update_user_note", { userId, note });
             await get().loadUsers();
             set({ isLoading: false });
         } catch (error) {
-            set({ error: String(error), isLoading: false });
+            set({ error: errorMessage(error), isLoading: false });
             throw error;
         }
     },
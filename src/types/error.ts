@@ -0,0 +1,55 @@
+export type ErrorCode =
+    | "ROXY_NOT_FOUND"
+    | "ROXY_START_FAILED"
+    | "ROXY_NOT_READY"
+    | "STOP_TIMEOUT"
+    | "USER_NOT_FOUND"
+    | "ACTIVE_USER"
+    | "LOGIN_NOT_DETECTED"
+    | "OPERATION_BUSY"
+    | "CANCELLED"
+    | "OPERATION_NOT_FOUND"
+    | "NOT_CANCELLABLE"
+    | "ITEM_SET_NOT_FOUND"
+    | "INVALID_PATH"
+    | "INVALID_SETTINGS"
+    | "NOTHING_TO_EXPORT"
+    | "IMPORT_SOURCE_NOT_FOUND"
+    | "PASSPHRASE_REQUIRED"
+    | "INVALID_IMPORT"
+    | "BACKUP_MISSING"
+    | "BACKUP_INCOMPLETE"
+    | "SNAPSHOT_NOT_FOUND"
+    | "SNAPSHOT_CORRUPT"
+    | "STORE_LOCKED"
+    | "STORE_NOT_ENCRYPTED"
+    | "KEYRING_UNAVAILABLE"
+    | "WRONG_PASSPHRASE"
+    | "DATA_CORRUPTED"
+    | "INVALID_ARCHIVE"
+    | "SWITCH_FAILED"
+    | "IO"
+    | "INTERNAL";
+
+// 后端命令返回的错误
+export interface AppError {
+    code: ErrorCode;
+    messageKey: string;
+    params: Record<string, string>;
+    message: string;
+    source: string[];
+}
+
+export function isAppError(error: unknown): error is AppError {
+    return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+// 加密归档缺少密码或密码错误，需要让用户输入密码后重试
+export function isPassphraseError(error: unknown): boolean {
+    return isAppError(error) && (error.code === "PASSPHRASE_REQUIRED" || error.code === "WRONG_PASSPHRASE");
+}
+
+// 取出可显示的错误消息，兼容仍返回字符串的命令
+export function errorMessage(error: unknown): string {
+    return isAppError(error) ? error.message : String(error);
+}
@@ -0,0 +1,69 @@
+// 后端长时间操作（切换用户、导入导出）推送的进度事件
+export const OPERATION_PROGRESS_EVENT = "operation://progress";
+
+export type ProgressPhase = "capture" | "materialize" | "archive" | "encrypt" | "decrypt" | "extract" | "verify";
+
+export interface OperationProgress {
+    operationId: string;
+    operation: string;
+    phase: ProgressPhase | null;
+    user: string | null;
+    currentItem: string | null;
+    filesDone: number;
+    filesTotal: number;
+    bytesDone: number;
+    bytesTotal: number;
+    etaSecs: number | null;
+    elapsedMs: number;
+}
+
+const PHASE_LABELS: Record<ProgressPhase, string> = {
+    capture: "备份数据",
+    materialize: "还原数据",
+    archive: "写入归档",
+    encrypt: "加密",
+    decrypt: "解密",
+    extract: "解压校验",
+    verify: "校验备份",
+};
+
+// 可以通过 cancel_operation 取消的操作
+export function isCancellable(progress: OperationProgress): boolean {
+    return progress.operation === "export_profiles" || progress.operation === "import_profiles";
+}
+
+export function phaseLabel(phase: ProgressPhase | null): string {
+    return phase ? PHASE_LABELS[phase] : "准备中";
+}
+
+// 完成比例（0-100），优先按字节计算
+export function progressPercent(progress: OperationProgress): number {
+    if (progress.bytesTotal > 0) {
+        return Math.min(100, (progress.bytesDone / progress.bytesTotal) * 100);
+    }
+    if (progress.filesTotal > 0) {
+        return Math.min(100, (progress.filesDone / progress.filesTotal) * 100);
+    }
+    return 0;
+}
+
+export function formatBytes(bytes: number): string {
+    const units = ["B", "KB", "MB", "GB", "TB"];
+    let value = bytes;
+    let unit = 0;
+    while (value >= 1024 && unit < units.length - 1) {
+        value /= 1024;
+        unit++;
+    }
+    return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+export function formatEta(secs: number | null): string {
+    if (secs === null) {
+        return "";
+    }
+    if (secs < 60) {
+        return `约 ${secs} 秒`;
+    }
+    return `约 ${Math.ceil(secs / 60)} 分钟`;
+}